@@ -1,6 +1,6 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 pub struct CLI {
@@ -12,20 +12,166 @@ pub struct CLI {
 pub enum Command {
     #[command(about = "Run a specific script")]
     Run {
-        file_path: PathBuf,
-        args: Vec<String>,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = UnhandledRejections::Throw,
+            help = "Define what happens when a promise is rejected without a handler"
+        )]
+        unhandled_rejections: UnhandledRejections,
+
+        #[arg(
+            long,
+            help = "Only install web globals, leaving out Node.js compatible ones such as process"
+        )]
+        no_node_globals: bool,
+
+        #[arg(
+            long,
+            value_enum,
+            num_args = 0..=1,
+            require_equals = true,
+            default_value_t = ColorMode::Auto,
+            default_missing_value = "always",
+            help = "Define when console output is colored"
+        )]
+        color: ColorMode,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = LogFormat::Text,
+            help = "Define how console output is written"
+        )]
+        log_format: LogFormat,
+
+        #[arg(
+            long,
+            help = "Map the positions of stack traces to the original sources of files with a source map"
+        )]
+        enable_source_maps: bool,
+
+        /// The script path followed by its arguments, taken as they are so flags after the path
+        /// are left to the script
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "FILE_PATH [ARGS]"
+        )]
+        script: Vec<String>,
     },
 }
 
-pub fn get_program_argv() -> Vec<String> {
-    let mut argv = Vec::new();
+impl Command {
+    /// The path of the script to run and the arguments given to it
+    pub fn get_script(&self) -> (PathBuf, &[String]) {
+        match self {
+            Command::Run { script, .. } => match script.split_first() {
+                Some((file_path, args)) => (PathBuf::from(file_path), args),
 
-    match CLI::parse().command {
-        Command::Run { file_path, args } => {
-            argv.push(file_path.to_string_lossy().to_string());
-            argv.extend(args);
+                None => (PathBuf::new(), &[]),
+            },
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UnhandledRejections {
+    /// Emit `unhandledRejection`, raise it as an uncaught exception if nothing listens to it
+    Throw,
+    /// Raise it as an uncaught exception even if something listens to `unhandledRejection`
+    Strict,
+    /// Emit `unhandledRejection` and always print a warning
+    Warn,
+    /// Only emit `unhandledRejection`
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    /// Color output written to a terminal, unless `NO_COLOR` is set or `FORCE_COLOR` says otherwise
+    Auto,
+    /// Always color output
+    Always,
+    /// Never color output
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Write formatted messages, indented by `console.group`
+    Text,
+    /// Write every message as a line of JSON, `{level, time, message, args}`
+    Json,
+}
+
+impl CLI {
+    /// Parse the command line, also returning the runtime flags given before the script path
+    pub fn parse_with_exec_argv() -> (CLI, Vec<String>) {
+        let args: Vec<String> = std::env::args().collect();
+
+        let matches = CLI::command().get_matches_from(&args);
+
+        let cli = CLI::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+        let exec_argv = match matches.subcommand_name() {
+            Some(name) => {
+                let command = CLI::command();
+
+                match command.find_subcommand(name) {
+                    Some(subcommand) => get_exec_argv(subcommand, &args),
+
+                    None => Vec::new(),
+                }
+            }
+
+            None => Vec::new(),
+        };
+
+        (cli, exec_argv)
+    }
+}
+
+/// The flags given to `subcommand` before the script path, walking them the way clap parsed
+/// them up to the first positional argument
+fn get_exec_argv(subcommand: &clap::Command, args: &[String]) -> Vec<String> {
+    let start = 2.min(args.len());
+    let mut index = start;
+
+    while index < args.len() {
+        let arg = &args[index];
+
+        if arg == "--" || !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+
+        // Options taking a value without `=` take the next argument as their value
+        let takes_next = arg.strip_prefix("--").is_some_and(|name| {
+            subcommand.get_arguments().any(|option| {
+                option.get_long() == Some(name)
+                    && option.get_action().takes_values()
+                    && !option.is_require_equals_set()
+            })
+        });
+
+        index += if takes_next { 2 } else { 1 };
+    }
+
+    args[start..index.min(args.len())].to_vec()
+}
+
+/// Build a Node.js shaped argv, `[execPath, absolute script path, ...args]`
+pub fn get_program_argv(file_path: &Path, args: &[String]) -> Vec<String> {
+    let mut argv = Vec::new();
+
+    let exec_path = std::env::current_exe().unwrap_or_default();
+
+    let file_path = std::path::absolute(file_path).unwrap_or(file_path.to_path_buf());
+
+    argv.push(exec_path.to_string_lossy().to_string());
+    argv.push(file_path.to_string_lossy().to_string());
+    argv.extend_from_slice(args);
 
     argv
 }
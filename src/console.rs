@@ -1,157 +1,1146 @@
-use rquickjs::function::{Func, Rest};
-use rquickjs::{Ctx, Object, Result as QuickJsResult, Type, Value};
+use crate::cli::ColorMode;
+use crate::inspect::{format_with_options, inspect, visible_length, InspectOptions};
+use crate::utils::throw_type_error;
 
-use std::io::{stderr, stdout, Write};
+use rquickjs::function::{Constructor, IntoJsFunc, Opt, Rest, This};
+use rquickjs::{Array, Class, Ctx, Function, Object, Result as QuickJsResult, Value};
 
-pub fn init(ctx: &Ctx<'_>) -> QuickJsResult<()> {
+use std::io::{self, stderr, stdout, IsTerminal};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn is_terminal(self) -> bool {
+        match self {
+            Stream::Stdout => stdout().is_terminal(),
+
+            Stream::Stderr => stderr().is_terminal(),
+        }
+    }
+
+    fn write_all(self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Stream::Stdout => crate::stdio::write_stdout(bytes),
+
+            Stream::Stderr => crate::stdio::write_stderr(bytes),
+        }
+    }
+
+    fn colors_key(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdoutColors",
+
+            Stream::Stderr => "stderrColors",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// The level of a console call, which decides the stream its output goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Log,
+    Info,
+    Debug,
+    Warn,
+    Error,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Log => "log",
+
+            LogLevel::Info => "info",
+
+            LogLevel::Debug => "debug",
+
+            LogLevel::Warn => "warn",
+
+            LogLevel::Error => "error",
+
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    pub fn stream(self) -> Stream {
+        match self {
+            LogLevel::Log | LogLevel::Info | LogLevel::Debug => Stream::Stdout,
+
+            LogLevel::Warn | LogLevel::Error | LogLevel::Trace => Stream::Stderr,
+        }
+    }
+}
+
+/// The output of a single console call
+pub struct LogRecord<'a, 'js> {
+    pub ctx: &'a Ctx<'js>,
+    pub level: LogLevel,
+    pub time: SystemTime,
+    /// The formatted output, without the `console.group` indentation
+    pub message: &'a str,
+    /// The values the message was formatted from
+    pub args: &'a [Value<'js>],
+    /// The number of spaces `console.group` indents the message by
+    pub indentation: usize,
+}
+
+impl LogRecord<'_, '_> {
+    /// Serialize the record as `{level, time, message, args}` on a single line, `time` being in
+    /// milliseconds since the epoch and arguments JSON can not represent being inspected
+    pub fn to_json(&self) -> QuickJsResult<String> {
+        let time = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg_to_json(self.ctx, arg))
+            .collect::<QuickJsResult<Vec<_>>>()?;
+
+        Ok(format!(
+            "{{\"level\":\"{}\",\"time\":{},\"message\":{},\"args\":[{}]}}",
+            self.level.as_str(),
+            time,
+            string_to_json(self.ctx, self.message)?,
+            args.join(",")
+        ))
+    }
+}
+
+fn string_to_json(ctx: &Ctx<'_>, string: &str) -> QuickJsResult<String> {
+    match ctx.json_stringify(string)? {
+        Some(json) => json.to_string(),
+
+        None => Ok(String::from("\"\"")),
+    }
+}
+
+fn arg_to_json<'js>(ctx: &Ctx<'js>, arg: &Value<'js>) -> QuickJsResult<String> {
+    match ctx.json_stringify(arg.clone()) {
+        Ok(Some(json)) => return json.to_string(),
+
+        Ok(None) => (),
+
+        // Circular structures and BigInts can not be serialized
+        Err(rquickjs::Error::Exception) => {
+            ctx.catch();
+        }
+
+        Err(err) => return Err(err),
+    }
+
+    let options = InspectOptions {
+        break_length: usize::MAX,
+        ..InspectOptions::default()
+    };
+
+    string_to_json(ctx, &inspect(ctx, arg, &options)?)
+}
+
+/// Receives the output of the global console, in place of the default text output
+pub trait ConsoleSink {
+    /// Whether values written to `stream` are colored
+    fn has_colors(&self, _stream: Stream) -> bool {
+        false
+    }
+
+    fn write(&self, record: &LogRecord<'_, '_>) -> QuickJsResult<()>;
+}
+
+/// Write indented messages to stdout and stderr
+pub struct TextSink {
+    stdout_colors: bool,
+    stderr_colors: bool,
+}
+
+impl TextSink {
+    pub fn new(color: ColorMode) -> TextSink {
+        TextSink {
+            stdout_colors: should_colorize(color, Stream::Stdout.is_terminal()),
+            stderr_colors: should_colorize(color, Stream::Stderr.is_terminal()),
+        }
+    }
+}
+
+impl ConsoleSink for TextSink {
+    fn has_colors(&self, stream: Stream) -> bool {
+        match stream {
+            Stream::Stdout => self.stdout_colors,
+
+            Stream::Stderr => self.stderr_colors,
+        }
+    }
+
+    fn write(&self, record: &LogRecord<'_, '_>) -> QuickJsResult<()> {
+        let text = indent(record.message, record.indentation);
+
+        let result = record.level.stream().write_all(text.as_bytes());
+
+        crate::stdio::check_write(record.ctx, result)
+    }
+}
+
+/// Write every message as a line of JSON, see `LogRecord::to_json`
+pub struct JsonSink;
+
+impl ConsoleSink for JsonSink {
+    fn write(&self, record: &LogRecord<'_, '_>) -> QuickJsResult<()> {
+        let mut line = record.to_json()?;
+
+        line.push('\n');
+
+        let result = record.level.stream().write_all(line.as_bytes());
+
+        crate::stdio::check_write(record.ctx, result)
+    }
+}
+
+#[rquickjs::class]
+#[derive(rquickjs::class::Trace)]
+struct SinkHandle {
+    #[qjs(skip_trace)]
+    inner: Rc<dyn ConsoleSink>,
+}
+
+/// Whether output written to a stream should be colored, `FORCE_COLOR` takes precedence over
+/// `NO_COLOR` when the mode is `auto`
+fn should_colorize(color: ColorMode, is_terminal: bool) -> bool {
+    match color {
+        ColorMode::Always => true,
+
+        ColorMode::Never => false,
+
+        ColorMode::Auto => {
+            if let Some(force_color) = std::env::var_os("FORCE_COLOR") {
+                return !matches!(force_color.to_str(), Some("0" | "false"));
+            }
+
+            if std::env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty()) {
+                return false;
+            }
+
+            if std::env::var_os("TERM").is_some_and(|term| term == "dumb") {
+                return false;
+            }
+
+            is_terminal
+        }
+    }
+}
+
+const CONSOLE_KEY: &str = "__yaso_console";
+
+const DEFAULT_LABEL: &str = "default";
+
+const DEFAULT_GROUP_INDENTATION: usize = 2;
+
+/// Install the global console, handing its output to `sink`
+pub fn init(ctx: &Ctx<'_>, sink: Rc<dyn ConsoleSink>) -> QuickJsResult<()> {
     let globals = ctx.globals();
 
-    let console = Object::new(ctx.clone())?;
+    let state = create_state(ctx, DEFAULT_GROUP_INDENTATION)?;
 
-    console.set("log", Func::from(log_stdout))?;
-    console.set("info", Func::from(log_stdout))?;
-    console.set("trace", Func::from(log_stdout))?;
-    console.set("debug", Func::from(log_stdout))?;
-    console.set("error", Func::from(log_stderr))?;
-    console.set("warn", Func::from(log_stderr))?;
-    console.set("assert", Func::from(log_assert))?;
+    state.set(Stream::Stdout.colors_key(), sink.has_colors(Stream::Stdout))?;
+    state.set(Stream::Stderr.colors_key(), sink.has_colors(Stream::Stderr))?;
+    state.set(
+        "sink",
+        Class::instance(ctx.clone(), SinkHandle { inner: sink })?,
+    )?;
+
+    let console = create_console(ctx, &state)?;
+
+    let constructor = Function::new(ctx.clone(), construct_console)?
+        .with_constructor(true)
+        .with_name("Console")?;
+
+    console.set("Console", constructor)?;
 
     globals.set("console", console)?;
+    globals.prop(CONSOLE_KEY, state)?;
 
     Ok(())
 }
 
-fn log_assert(expression: bool, args: Rest<Value<'_>>) -> QuickJsResult<()> {
+/// The timers, counters, group indentation and output of a console
+fn create_state<'js>(ctx: &Ctx<'js>, group_indentation: usize) -> QuickJsResult<Object<'js>> {
+    let state = Object::new(ctx.clone())?;
+
+    state.set("times", Object::new(ctx.clone())?)?;
+    state.set("counts", Object::new(ctx.clone())?)?;
+    state.set("indentation", 0)?;
+    state.set("groupIndentation", group_indentation)?;
+
+    Ok(state)
+}
+
+/// Bind `method` to the state of a console, so it keeps working when taken off the console
+fn set_method<'js, F, P>(
+    ctx: &Ctx<'js>,
+    console: &Object<'js>,
+    state: &Object<'js>,
+    name: &str,
+    method: F,
+) -> QuickJsResult<()>
+where
+    F: IntoJsFunc<'js, P> + 'js,
+{
+    let method = Function::new(ctx.clone(), method)?;
+
+    let bind: Function = method.get("bind")?;
+
+    let method: Function = bind.call((This(method), state.clone()))?;
+
+    method.set_name(name)?;
+
+    console.set(name, method)
+}
+
+fn create_console<'js>(ctx: &Ctx<'js>, state: &Object<'js>) -> QuickJsResult<Object<'js>> {
+    let console = Object::new(ctx.clone())?;
+
+    set_method(ctx, &console, state, "log", log)?;
+    set_method(ctx, &console, state, "info", log_info)?;
+    set_method(ctx, &console, state, "debug", log_debug)?;
+    set_method(ctx, &console, state, "dirxml", log)?;
+    set_method(ctx, &console, state, "error", log_error)?;
+    set_method(ctx, &console, state, "warn", log_warn)?;
+    set_method(ctx, &console, state, "trace", log_trace)?;
+    set_method(ctx, &console, state, "assert", log_assert)?;
+    set_method(ctx, &console, state, "dir", log_dir)?;
+    set_method(ctx, &console, state, "table", log_table)?;
+    set_method(ctx, &console, state, "time", time)?;
+    set_method(ctx, &console, state, "timeLog", time_log)?;
+    set_method(ctx, &console, state, "timeEnd", time_end)?;
+    set_method(ctx, &console, state, "count", count)?;
+    set_method(ctx, &console, state, "countReset", count_reset)?;
+    set_method(ctx, &console, state, "group", group)?;
+    set_method(ctx, &console, state, "groupCollapsed", group)?;
+    set_method(ctx, &console, state, "groupEnd", group_end)?;
+    set_method(ctx, &console, state, "clear", clear)?;
+
+    Ok(console)
+}
+
+fn is_writable_stream(value: &Value<'_>) -> QuickJsResult<bool> {
+    let Some(object) = value.as_object() else {
+        return Ok(false);
+    };
+
+    let write: Value = object.get("write")?;
+    let write_sync: Value = object.get("writeSync")?;
+
+    Ok(write.is_function() || write_sync.is_function())
+}
+
+/// Whether a console writing to `stream` colors its output, `colorMode` being `'auto'`, `true` or
+/// `false` as in Node.js
+fn get_stream_colors<'js>(
+    ctx: &Ctx<'js>,
+    stream: &Object<'js>,
+    color_mode: &Value<'js>,
+) -> QuickJsResult<bool> {
+    if let Some(color_mode) = color_mode.as_bool() {
+        return Ok(color_mode);
+    }
+
+    let is_auto = color_mode.is_undefined()
+        || color_mode
+            .as_string()
+            .map(|string| string.to_string())
+            .transpose()?
+            .is_some_and(|string| string == "auto");
+
+    if !is_auto {
+        return Err(throw_type_error(
+            ctx,
+            "ERR_INVALID_ARG_VALUE",
+            "The argument 'colorMode' must be one of: 'auto', true, false",
+        ));
+    }
+
+    let is_terminal: Option<bool> = stream.get("isTTY")?;
+
+    Ok(should_colorize(ColorMode::Auto, is_terminal == Some(true)))
+}
+
+/// `new Console(stdout[, stderr[, ignoreErrors]])` or `new Console({ stdout, stderr, ignoreErrors,
+/// colorMode, groupIndentation })`, a console writing to streams or files with a `write` or
+/// `writeSync` method
+fn construct_console<'js>(
+    ctx: Ctx<'js>,
+    options: Value<'js>,
+    stderr: Opt<Value<'js>>,
+    ignore_errors: Opt<bool>,
+) -> QuickJsResult<Object<'js>> {
+    let options = if is_writable_stream(&options)? {
+        let object = Object::new(ctx.clone())?;
+
+        object.set("stdout", options)?;
+        object.set("stderr", stderr.0)?;
+        object.set("ignoreErrors", ignore_errors.0)?;
+
+        object
+    } else {
+        match options.into_object() {
+            Some(options) => options,
+
+            None => {
+                return Err(throw_type_error(
+                    &ctx,
+                    "ERR_CONSOLE_WRITABLE_STREAM",
+                    "Console expects a writable stream instance for stdout",
+                ))
+            }
+        }
+    };
+
+    let stdout: Value = options.get("stdout")?;
+
+    if !is_writable_stream(&stdout)? {
+        return Err(throw_type_error(
+            &ctx,
+            "ERR_CONSOLE_WRITABLE_STREAM",
+            "Console expects a writable stream instance for stdout",
+        ));
+    }
+
+    let stderr = match options.get::<_, Value>("stderr")? {
+        stderr if stderr.is_undefined() => stdout.clone(),
+
+        stderr if is_writable_stream(&stderr)? => stderr,
+
+        _ => {
+            return Err(throw_type_error(
+                &ctx,
+                "ERR_CONSOLE_WRITABLE_STREAM",
+                "Console expects a writable stream instance for stderr",
+            ))
+        }
+    };
+
+    let group_indentation = match options.get::<_, Option<f64>>("groupIndentation")? {
+        Some(indentation) if (0.0..=1000.0).contains(&indentation) => indentation as usize,
+
+        Some(_) => {
+            return Err(throw_type_error(
+                &ctx,
+                "ERR_OUT_OF_RANGE",
+                "The value of \"groupIndentation\" is out of range. It must be >= 0 && <= 1000",
+            ))
+        }
+
+        None => DEFAULT_GROUP_INDENTATION,
+    };
+
+    let ignore_errors: Option<bool> = options.get("ignoreErrors")?;
+    let color_mode: Value = options.get("colorMode")?;
+
+    let stdout = stdout.into_object().unwrap();
+    let stderr = stderr.into_object().unwrap();
+
+    let state = create_state(&ctx, group_indentation)?;
+
+    state.set(
+        Stream::Stdout.colors_key(),
+        get_stream_colors(&ctx, &stdout, &color_mode)?,
+    )?;
+    state.set(
+        Stream::Stderr.colors_key(),
+        get_stream_colors(&ctx, &stderr, &color_mode)?,
+    )?;
+    state.set("ignoreErrors", ignore_errors.unwrap_or(true))?;
+    state.set(Stream::Stdout.key(), stdout)?;
+    state.set(Stream::Stderr.key(), stderr)?;
+
+    create_console(&ctx, &state)
+}
+
+fn get_indentation(state: &Object<'_>) -> QuickJsResult<usize> {
+    state.get("indentation")
+}
+
+/// Whether values the global console prints to `stream` are colored
+pub fn has_colors(ctx: &Ctx<'_>, stream: Stream) -> QuickJsResult<bool> {
+    let state: Object = ctx.globals().get(CONSOLE_KEY)?;
+
+    state.get(stream.colors_key())
+}
+
+fn get_inspect_options(state: &Object<'_>, stream: Stream) -> QuickJsResult<InspectOptions> {
+    Ok(InspectOptions {
+        colors: state.get(stream.colors_key())?,
+        ..InspectOptions::default()
+    })
+}
+
+fn log_assert<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    expression: bool,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
     if !expression {
-        log_stderr(args)?;
+        log_write(&ctx, &this, LogLevel::Warn, &args)?;
     }
 
     Ok(())
 }
 
-fn log_stdout(args: Rest<Value<'_>>) -> QuickJsResult<()> {
-    log_write(stdout(), args)
+fn log<'js>(ctx: Ctx<'js>, this: This<Object<'js>>, args: Rest<Value<'js>>) -> QuickJsResult<()> {
+    log_write(&ctx, &this, LogLevel::Log, &args)
+}
+
+fn log_info<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
+    log_write(&ctx, &this, LogLevel::Info, &args)
+}
+
+fn log_debug<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
+    log_write(&ctx, &this, LogLevel::Debug, &args)
 }
 
-fn log_stderr(args: Rest<Value<'_>>) -> QuickJsResult<()> {
-    log_write(stderr(), args)
+fn log_warn<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
+    log_write(&ctx, &this, LogLevel::Warn, &args)
 }
 
-fn js_format(args: Rest<Value<'_>>) -> QuickJsResult<String> {
-    let mut result = String::new();
+fn log_error<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
+    log_write(&ctx, &this, LogLevel::Error, &args)
+}
+
+/// Print the arguments followed by the stack of the caller to stderr
+fn log_trace<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
+    let error: Object = ctx
+        .globals()
+        .get::<_, Constructor>("Error")?
+        .construct(())?;
+
+    let stack = crate::source_map::get_mapped_stack(&ctx, &error)?;
+
+    let options = get_inspect_options(&this, Stream::Stderr)?;
+
+    let message = format_with_options(&ctx, &args, &options)?;
+
+    let mut output = String::from("Trace");
+
+    if !message.is_empty() {
+        output.push_str(": ");
+        output.push_str(&message);
+    }
 
-    for arg in args.iter() {
-        result.push_str(js_stringify(arg)?.as_str());
-        result.push(' ');
+    if let Some(stack) = stack {
+        output.push('\n');
+        output.push_str(stack.trim_end());
     }
 
-    Ok(result)
+    write_record(&ctx, &this, LogLevel::Trace, &output, &args)
 }
 
-pub fn js_stringify(value: &Value<'_>) -> QuickJsResult<String> {
-    let mut result = String::new();
+/// Print `object` with `util.inspect`, without calling custom inspect methods unless asked to
+fn log_dir<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    object: Value<'js>,
+    options: Opt<Object<'js>>,
+) -> QuickJsResult<()> {
+    let options = match options.0 {
+        Some(options) => {
+            let custom_inspect = options.contains_key("customInspect")?;
 
-    match value.type_of() {
-        Type::String => result = value.as_string().unwrap().to_string()?,
+            let colors = options.contains_key("colors")?;
 
-        Type::Bool => result = value.as_bool().unwrap().to_string(),
+            let mut options = InspectOptions::from_object(&options)?;
 
-        Type::Int => result = value.as_int().unwrap().to_string(),
+            options.custom_inspect = options.custom_inspect && custom_inspect;
 
-        Type::BigInt => {
-            result = value.as_big_int().unwrap().clone().to_i64()?.to_string();
+            if !colors {
+                options.colors = this.get(Stream::Stdout.colors_key())?;
+            }
 
-            result.push('n');
+            options
         }
 
-        Type::Float => result = value.as_float().unwrap().to_string(),
+        None => InspectOptions {
+            custom_inspect: false,
+            ..get_inspect_options(&this, Stream::Stdout)?
+        },
+    };
 
-        Type::Array => {
-            let array = value.as_array().unwrap();
+    let message = inspect(&ctx, &object, &options)?;
 
-            result.push('[');
+    write_record(&ctx, &this, LogLevel::Log, &message, &[object])
+}
 
-            for (i, value) in array.clone().into_iter().enumerate() {
-                result.push_str(&js_stringify(&value?)?);
+fn get_label(label: Opt<Value<'_>>) -> QuickJsResult<String> {
+    match label.0 {
+        Some(label) if !label.is_undefined() => {
+            let label: rquickjs::Coerced<String> = label.get()?;
 
-                if i < array.len() - 1 {
-                    result.push_str(", ");
-                }
-            }
+            Ok(label.0)
+        }
+
+        _ => Ok(String::from(DEFAULT_LABEL)),
+    }
+}
+
+fn print_warning<'js>(ctx: &Ctx<'js>, state: &Object<'js>, message: &str) -> QuickJsResult<()> {
+    write_record(
+        ctx,
+        state,
+        LogLevel::Warn,
+        &format!("Warning: {}", message),
+        &[],
+    )
+}
+
+fn get_elapsed_millis() -> f64 {
+    crate::process::get_elapsed_nanos() as f64 / 1_000_000.0
+}
+
+/// Format a duration the way Node.js does, `1.5ms`, `2.345s` or `1:02.345 (m:ss.mmm)`
+fn format_duration(millis: f64) -> String {
+    let seconds = millis / 1000.0;
+
+    if seconds >= 60.0 {
+        let hours = (seconds / 3600.0).floor();
+        let minutes = ((seconds % 3600.0) / 60.0).floor();
+        let seconds = seconds % 60.0;
+
+        let seconds = format!("{:06.3}", seconds);
 
-            result.push(']');
+        if hours > 0.0 {
+            return format!("{}:{:02}:{} (h:mm:ss.mmm)", hours, minutes, seconds);
         }
 
-        Type::Symbol => {
-            let description = value.as_symbol().unwrap().description()?;
-            let description = description.to_string()?;
+        return format!("{}:{} (m:ss.mmm)", minutes, seconds);
+    }
 
-            result.push_str("Symbol(");
+    if seconds >= 1.0 {
+        return format!("{:.3}s", seconds);
+    }
 
-            if description != "undefined" {
-                result.push_str(&description);
-            }
+    let millis = format!("{:.3}", millis);
+    let millis = millis.trim_end_matches('0').trim_end_matches('.');
+
+    format!("{}ms", millis)
+}
+
+fn time<'js>(ctx: Ctx<'js>, this: This<Object<'js>>, label: Opt<Value<'js>>) -> QuickJsResult<()> {
+    let label = get_label(label)?;
+
+    let times: Object = this.get("times")?;
+
+    if times.contains_key(label.as_str())? {
+        return print_warning(
+            &ctx,
+            &this,
+            &format!("Label '{}' already exists for console.time()", label),
+        );
+    }
+
+    times.set(label, get_elapsed_millis())
+}
+
+fn print_time<'js>(
+    ctx: &Ctx<'js>,
+    state: &Object<'js>,
+    method: &str,
+    label: Opt<Value<'js>>,
+    args: &[Value<'js>],
+    end: bool,
+) -> QuickJsResult<()> {
+    let label = get_label(label)?;
+
+    let times: Object = state.get("times")?;
+
+    let Some(start) = times.get::<_, Option<f64>>(label.as_str())? else {
+        return print_warning(
+            ctx,
+            state,
+            &format!("No such label '{}' for console.{}()", label, method),
+        );
+    };
 
-            result.push(')');
+    if end {
+        times.remove(label.as_str())?;
+    }
+
+    let mut output = format!(
+        "{}: {}",
+        label,
+        format_duration(get_elapsed_millis() - start)
+    );
+
+    if !args.is_empty() {
+        output.push(' ');
+        output.push_str(&format_with_options(
+            ctx,
+            args,
+            &get_inspect_options(state, Stream::Stdout)?,
+        )?);
+    }
+
+    write_record(ctx, state, LogLevel::Log, &output, args)
+}
+
+fn time_log<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    label: Opt<Value<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<()> {
+    print_time(&ctx, &this, "timeLog", label, &args, false)
+}
+
+fn time_end<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    label: Opt<Value<'js>>,
+) -> QuickJsResult<()> {
+    print_time(&ctx, &this, "timeEnd", label, &[], true)
+}
+
+fn count<'js>(ctx: Ctx<'js>, this: This<Object<'js>>, label: Opt<Value<'js>>) -> QuickJsResult<()> {
+    let label = get_label(label)?;
+
+    let counts: Object = this.get("counts")?;
+
+    let count = counts.get::<_, Option<u64>>(label.as_str())?.unwrap_or(0) + 1;
+
+    counts.set(label.as_str(), count)?;
+
+    write_record(
+        &ctx,
+        &this,
+        LogLevel::Log,
+        &format!("{}: {}", label, count),
+        &[],
+    )
+}
+
+fn count_reset<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    label: Opt<Value<'js>>,
+) -> QuickJsResult<()> {
+    let label = get_label(label)?;
+
+    let counts: Object = this.get("counts")?;
+
+    if !counts.contains_key(label.as_str())? {
+        return print_warning(
+            &ctx,
+            &this,
+            &format!("Count for '{}' does not exist", label),
+        );
+    }
+
+    counts.remove(label.as_str())
+}
+
+/// Print the label if any, then indent the following output by `groupIndentation` spaces
+fn group<'js>(ctx: Ctx<'js>, this: This<Object<'js>>, args: Rest<Value<'js>>) -> QuickJsResult<()> {
+    if !args.is_empty() {
+        log_write(&ctx, &this, LogLevel::Log, &args)?;
+    }
+
+    let group_indentation: usize = this.get("groupIndentation")?;
+
+    this.set("indentation", get_indentation(&this)? + group_indentation)
+}
+
+fn group_end(this: This<Object<'_>>) -> QuickJsResult<()> {
+    let group_indentation: usize = this.get("groupIndentation")?;
+
+    this.set(
+        "indentation",
+        get_indentation(&this)?.saturating_sub(group_indentation),
+    )
+}
+
+const CLEAR_SCREEN: &str = "\x1b[1;1H\x1b[0J";
+
+/// Move the cursor to the top left corner and clear the screen, when stdout is a terminal
+fn clear<'js>(ctx: Ctx<'js>, this: This<Object<'js>>) -> QuickJsResult<()> {
+    let Some(stream) = this.get::<_, Option<Object>>(Stream::Stdout.key())? else {
+        if Stream::Stdout.is_terminal() {
+            let result = Stream::Stdout.write_all(CLEAR_SCREEN.as_bytes());
+
+            return crate::stdio::check_write(&ctx, result);
         }
 
-        Type::Exception => {
-            let exception = value.as_exception().unwrap();
+        return Ok(());
+    };
 
-            if let Some(message) = exception.message() {
-                let name: String = exception.get("name")?;
+    if stream.get::<_, Option<bool>>("isTTY")? == Some(true) {
+        write_stream(&ctx, &this, &stream, CLEAR_SCREEN)?;
+    }
 
-                result.push_str(&name);
+    Ok(())
+}
 
-                result.push_str(": ");
+/// Inspect a table cell, objects with many keys are only shown as `[Object]`
+fn inspect_cell<'js>(
+    ctx: &Ctx<'js>,
+    state: &Object<'js>,
+    value: &Value<'js>,
+) -> QuickJsResult<String> {
+    let is_large_object = match value.as_object() {
+        Some(object) if !value.is_array() && !value.is_function() => {
+            object.keys::<String>().count() > 2
+        }
 
-                result.push_str(&message);
+        _ => false,
+    };
 
-                result.push('\n');
-            }
+    let options = InspectOptions {
+        depth: if is_large_object { -1.0 } else { 0.0 },
+        break_length: usize::MAX,
+        ..get_inspect_options(state, Stream::Stdout)?
+    };
 
-            if let Some(stack) = exception.stack() {
-                result.push_str(&stack);
-            }
+    inspect(ctx, value, &options)
+}
+
+fn get_entries<'js>(ctx: &Ctx<'js>, object: &Object<'js>) -> QuickJsResult<Vec<Value<'js>>> {
+    let array: Object = ctx.globals().get("Array")?;
+    let from: Function = array.get("from")?;
+
+    from.call::<_, Array>((object.clone(),))?.iter().collect()
+}
+
+fn is_instance_of<'js>(ctx: &Ctx<'js>, object: &Object<'js>, class: &str) -> QuickJsResult<bool> {
+    let constructor: Value = ctx.globals().get(class)?;
+
+    Ok(constructor.is_function() && object.is_instance_of(constructor))
+}
+
+/// Print `data` as a table with a row for each of its entries and a column for each of their
+/// properties, limited to `properties` if given
+fn log_table<'js>(
+    ctx: Ctx<'js>,
+    this: This<Object<'js>>,
+    data: Value<'js>,
+    properties: Opt<Vec<String>>,
+) -> QuickJsResult<()> {
+    let Some(object) = data.as_object().filter(|_| !data.is_function()) else {
+        return log_write(&ctx, &this, LogLevel::Log, &[data]);
+    };
+
+    let mut head = Vec::new();
+    let mut columns: Vec<Vec<String>> = Vec::new();
+
+    if is_instance_of(&ctx, object, "Map")? {
+        let mut index = Vec::new();
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+
+        for (i, entry) in get_entries(&ctx, object)?.into_iter().enumerate() {
+            let entry = entry.into_array().unwrap();
+
+            index.push(i.to_string());
+            keys.push(inspect_cell(&ctx, &this, &entry.get(0)?)?);
+            values.push(inspect_cell(&ctx, &this, &entry.get(1)?)?);
+        }
+
+        head.extend(["(iteration index)", "Key", "Values"].map(String::from));
+        columns.extend([index, keys, values]);
+    } else if is_instance_of(&ctx, object, "Set")? {
+        let mut index = Vec::new();
+        let mut values = Vec::new();
+
+        for (i, entry) in get_entries(&ctx, object)?.into_iter().enumerate() {
+            index.push(i.to_string());
+            values.push(inspect_cell(&ctx, &this, &entry)?);
         }
 
-        // TODO: stringify these properly
-        Type::Object => result.push_str("[Object]"),
-        Type::Module => result.push_str("[Module]"),
+        head.extend(["(iteration index)", "Values"].map(String::from));
+        columns.extend([index, values]);
+    } else {
+        let index: Vec<String> = object.keys::<String>().collect::<QuickJsResult<_>>()?;
+
+        let mut has_primitives = false;
+        let mut values = vec![String::new(); index.len()];
+
+        for (i, key) in index.iter().enumerate() {
+            let item: Value = object.get(key.as_str())?;
+
+            let item_object = item.as_object();
+
+            if properties.0.is_none() && item_object.is_none() {
+                has_primitives = true;
+
+                values[i] = inspect_cell(&ctx, &this, &item)?;
 
-        Type::Constructor | Type::Function => {
-            result.push_str("[Function");
+                continue;
+            }
+
+            let keys = match (&properties.0, item_object) {
+                (Some(properties), _) => properties.clone(),
+
+                (None, Some(item)) => item.keys::<String>().collect::<QuickJsResult<_>>()?,
 
-            let name: String = value.as_function().unwrap().get("name")?;
+                (None, None) => Vec::new(),
+            };
 
-            if !name.is_empty() {
-                result.push_str(": ");
+            for key in keys {
+                let column = match head.iter().position(|name| *name == key) {
+                    Some(column) => column,
 
-                result.push_str(&name);
+                    None => {
+                        head.push(key.clone());
+                        columns.push(vec![String::new(); index.len()]);
 
-                result.push(']');
-            } else {
-                result.push_str(" (anonymous)]");
+                        columns.len() - 1
+                    }
+                };
+
+                if let Some(item) = item_object {
+                    let descriptor = get_own_property_descriptor(&ctx, item, &key)?;
+
+                    if descriptor.is_some() {
+                        columns[column][i] = inspect_cell(&ctx, &this, &item.get(key.as_str())?)?;
+                    }
+                }
             }
         }
 
-        Type::Uninitialized | Type::Undefined => result.push_str("undefined"),
+        if has_primitives {
+            head.push(String::from("Values"));
+            columns.push(values);
+        }
+
+        head.insert(0, String::from("(index)"));
+        columns.insert(0, index);
+    }
 
-        Type::Null => result.push_str("null"),
+    let table = render_table(&head, &columns);
+
+    write_record(&ctx, &this, LogLevel::Log, &table, &[data])
+}
 
-        Type::Unknown => result.push_str("{unknown}"),
+fn get_own_property_descriptor<'js>(
+    ctx: &Ctx<'js>,
+    object: &Object<'js>,
+    key: &str,
+) -> QuickJsResult<Option<Object<'js>>> {
+    let constructor: Object = ctx.globals().get("Object")?;
+    let get_own_property_descriptor: Function = constructor.get("getOwnPropertyDescriptor")?;
+
+    get_own_property_descriptor.call((object.clone(), key))
+}
+
+fn render_row(row: &[&str], widths: &[usize]) -> String {
+    let cells: Vec<String> = row
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - visible_length(cell))))
+        .collect();
+
+    format!("│ {} │", cells.join(" │ "))
+}
+
+/// Draw the box around the columns of a table
+fn render_table(head: &[String], columns: &[Vec<String>]) -> String {
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+
+    let widths: Vec<usize> = head
+        .iter()
+        .zip(columns)
+        .map(|(name, column)| {
+            column
+                .iter()
+                .map(|cell| visible_length(cell))
+                .chain([name.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let divider = |left: &str, middle: &str, right: &str| {
+        let lines: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+
+        format!("{}{}{}", left, lines.join(middle), right)
     };
 
-    Ok(result)
+    let mut lines = vec![divider("┌", "┬", "┐")];
+
+    let head: Vec<&str> = head.iter().map(String::as_str).collect();
+
+    lines.push(render_row(&head, &widths));
+    lines.push(divider("├", "┼", "┤"));
+
+    for i in 0..rows {
+        let row: Vec<&str> = columns
+            .iter()
+            .map(|column| column.get(i).map(String::as_str).unwrap_or(""))
+            .collect();
+
+        lines.push(render_row(&row, &widths));
+    }
+
+    lines.push(divider("└", "┴", "┘"));
+
+    lines.join("\n")
 }
 
-fn log_write<O>(mut output: O, args: Rest<Value<'_>>) -> QuickJsResult<()>
-where
-    O: Write,
-{
-    let _ = output.write_all(js_format(args)?.as_bytes());
+/// Turn `value` into the text `console.log` prints for it, strings are printed as they are
+pub fn js_stringify<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> QuickJsResult<String> {
+    if let Some(string) = value.as_string() {
+        return string.to_string();
+    }
 
-    let _ = output.write(b"\n");
+    inspect(ctx, value, &InspectOptions::default())
+}
 
-    Ok(())
+/// Indent every line of `text` by `indentation` spaces and end it with a newline
+fn indent(text: &str, indentation: usize) -> String {
+    let indentation = " ".repeat(indentation);
+
+    let mut text = if indentation.is_empty() {
+        text.to_string()
+    } else {
+        format!(
+            "{}{}",
+            indentation,
+            text.replace('\n', &format!("\n{}", indentation))
+        )
+    };
+
+    text.push('\n');
+
+    text
+}
+
+/// Call the `writeSync` or `write` method of a stream given to `new Console()`, swallowing the
+/// errors it throws unless the console was built with `ignoreErrors: false`
+fn write_stream<'js>(
+    ctx: &Ctx<'js>,
+    state: &Object<'js>,
+    stream: &Object<'js>,
+    text: &str,
+) -> QuickJsResult<()> {
+    let write = match stream.get::<_, Value>("writeSync")? {
+        write if write.is_function() => write,
+
+        _ => stream.get("write")?,
+    };
+
+    let Some(write) = write.into_function() else {
+        return Ok(());
+    };
+
+    let result = write.call::<_, Value>((This(stream.clone()), text));
+
+    match result {
+        Err(rquickjs::Error::Exception) if state.get("ignoreErrors")? => {
+            ctx.catch();
+
+            Ok(())
+        }
+
+        result => result.map(|_| ()),
+    }
+}
+
+/// Hand the report of an error nothing caught to the sink of the global console, without the
+/// indentation of its groups
+pub fn write_error(ctx: &Ctx<'_>, message: &str) -> QuickJsResult<()> {
+    let state: Object = ctx.globals().get(CONSOLE_KEY)?;
+
+    let sink = state
+        .get::<_, Class<SinkHandle>>("sink")?
+        .borrow()
+        .inner
+        .clone();
+
+    sink.write(&LogRecord {
+        ctx,
+        level: LogLevel::Error,
+        time: SystemTime::now(),
+        message,
+        args: &[],
+        indentation: 0,
+    })
+}
+
+/// Hand the output of a console call to the sink of the console, or to the stream of a console
+/// built with `new Console()`
+fn write_record<'js>(
+    ctx: &Ctx<'js>,
+    state: &Object<'js>,
+    level: LogLevel,
+    message: &str,
+    args: &[Value<'js>],
+) -> QuickJsResult<()> {
+    let indentation = get_indentation(state)?;
+
+    if let Some(sink) = state.get::<_, Option<Class<SinkHandle>>>("sink")? {
+        let sink = sink.borrow().inner.clone();
+
+        return sink.write(&LogRecord {
+            ctx,
+            level,
+            time: SystemTime::now(),
+            message,
+            args,
+            indentation,
+        });
+    }
+
+    let stream: Object = state.get(level.stream().key())?;
+
+    write_stream(ctx, state, &stream, &indent(message, indentation))
+}
+
+fn log_write<'js>(
+    ctx: &Ctx<'js>,
+    state: &Object<'js>,
+    level: LogLevel,
+    args: &[Value<'js>],
+) -> QuickJsResult<()> {
+    let options = get_inspect_options(state, level.stream())?;
+
+    write_record(
+        ctx,
+        state,
+        level,
+        &format_with_options(ctx, args, &options)?,
+        args,
+    )
 }
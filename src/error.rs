@@ -0,0 +1,145 @@
+use rquickjs::{CaughtError, Ctx, Object};
+
+use std::fmt;
+
+/// What JavaScript code run by a `VirtualMachine` threw without anything catching it
+#[derive(Debug, Clone)]
+pub struct JsException {
+    /// The name of the error, empty for thrown values that aren't errors
+    pub name: String,
+    /// The message of the error, or the inspected value for thrown values that aren't errors
+    pub message: String,
+    /// The stack of the error, mapped through source maps when they are enabled
+    pub stack: Option<String>,
+    report: String,
+}
+
+impl JsException {
+    fn from_caught<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>, colors: bool) -> YasoError {
+        let value = match err {
+            CaughtError::Error(err) => return YasoError::Engine(err),
+
+            CaughtError::Exception(exception) => exception.into_value(),
+
+            CaughtError::Value(value) => value,
+        };
+
+        let result = match value.as_object() {
+            Some(error) if crate::report::is_error(&value) => Self::from_error(ctx, error, colors),
+
+            _ => crate::console::js_stringify(ctx, &value).map(|message| JsException {
+                name: String::new(),
+                report: format!("{}\n", message),
+                message,
+                stack: None,
+            }),
+        };
+
+        match result {
+            Ok(exception) => YasoError::Exception(exception),
+
+            Err(err) => YasoError::Engine(err),
+        }
+    }
+
+    fn from_error<'js>(
+        ctx: &Ctx<'js>,
+        error: &Object<'js>,
+        colors: bool,
+    ) -> rquickjs::Result<JsException> {
+        let stack = crate::source_map::get_mapped_stack(ctx, error)?;
+
+        Ok(JsException {
+            name: error.get::<_, Option<String>>("name")?.unwrap_or_default(),
+            message: error
+                .get::<_, Option<String>>("message")?
+                .unwrap_or_default(),
+            stack,
+            report: crate::report::format_report(ctx, error, colors)?,
+        })
+    }
+
+    /// The report printed for the error when it is uncaught, colored when the `VirtualMachine`
+    /// colors what it writes to stderr
+    pub fn report(&self) -> &str {
+        &self.report
+    }
+}
+
+impl fmt::Display for JsException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name.is_empty(), self.message.is_empty()) {
+            (true, _) => write!(f, "Uncaught {}", self.message),
+
+            (false, true) => write!(f, "{}", self.name),
+
+            (false, false) => write!(f, "{}: {}", self.name, self.message),
+        }
+    }
+}
+
+/// Why a `VirtualMachine` could not be built or could not run some code
+#[derive(Debug)]
+pub enum YasoError {
+    /// QuickJS failed, like when the runtime can't be allocated
+    Engine(rquickjs::Error),
+    /// The JavaScript code threw and nothing caught it
+    Exception(JsException),
+    /// The JavaScript code asked the process to exit with this code, through `process.exit()`
+    Exit(i32),
+    /// The `VirtualMachineBuilder` was given an option it can't honor
+    InvalidOption(String),
+    /// The promise the host waits for can never settle, nothing is left to run on the event loop
+    Unsettled,
+}
+
+impl YasoError {
+    /// Turn what running some code in `ctx` failed with into an error
+    pub(crate) fn from_caught<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) -> YasoError {
+        if crate::process::is_exiting(ctx) {
+            return YasoError::Exit(crate::process::exit_code(ctx).unwrap_or(1));
+        }
+
+        YasoError::from_uncaught(ctx, err)
+    }
+
+    /// Turn what nothing caught into an error, even once the process is exiting
+    pub(crate) fn from_uncaught<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) -> YasoError {
+        let colors =
+            crate::console::has_colors(ctx, crate::console::Stream::Stderr).unwrap_or(false);
+
+        JsException::from_caught(ctx, err, colors)
+    }
+}
+
+impl fmt::Display for YasoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YasoError::Engine(err) => write!(f, "{}", err),
+
+            YasoError::Exception(exception) => write!(f, "{}", exception),
+
+            YasoError::Exit(code) => write!(f, "The process exited with code {}", code),
+
+            YasoError::InvalidOption(message) => write!(f, "{}", message),
+
+            YasoError::Unsettled => write!(f, "The promise never settled"),
+        }
+    }
+}
+
+impl std::error::Error for YasoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            YasoError::Engine(err) => Some(err),
+
+            _ => None,
+        }
+    }
+}
+
+impl From<rquickjs::Error> for YasoError {
+    fn from(err: rquickjs::Error) -> Self {
+        YasoError::Engine(err)
+    }
+}
@@ -1,4 +1,6 @@
-use rquickjs::{Ctx, Exception, Result as QuickJsResult};
+use crate::utils::throw_io_error;
+
+use rquickjs::{Ctx, Result as QuickJsResult};
 
 use tokio::io::{
     AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader as AsyncBufReader,
@@ -28,12 +30,7 @@ impl File {
 
         match reader.read_to_string(&mut buf).await {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not read file: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not read file", err)),
         };
 
         Ok(buf)
@@ -46,12 +43,7 @@ impl File {
 
         match reader.read_line(&mut buf).await {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not read line: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not read line", err)),
         };
 
         Ok(buf)
@@ -62,22 +54,12 @@ impl File {
 
         match writer.write_all(buf.as_bytes()).await {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not write to file: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not write to file", err)),
         };
 
         match writer.flush().await {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not write to file: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not write to file", err)),
         };
 
         Ok(())
@@ -105,12 +87,7 @@ impl FileSync {
 
         match reader.read_to_string(&mut buf) {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not read file: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not read file", err)),
         };
 
         Ok(buf)
@@ -123,12 +100,7 @@ impl FileSync {
 
         match reader.read_line(&mut buf) {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not read line: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not read line", err)),
         };
 
         Ok(buf)
@@ -139,22 +111,12 @@ impl FileSync {
 
         match writer.write_all(buf.as_bytes()) {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not write to file: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not write to file", err)),
         };
 
         match writer.flush() {
             Ok(_) => (),
-            Err(err) => {
-                return Err(Exception::throw_message(
-                    &ctx,
-                    &format!("Could not write to file: {}", err),
-                ))
-            }
+            Err(err) => return Err(throw_io_error(&ctx, "Could not write to file", err)),
         };
 
         Ok(())
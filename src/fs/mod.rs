@@ -1,6 +1,6 @@
 mod file;
 
-use crate::utils::export_default;
+use crate::utils::{export_default, throw_io_error};
 
 use rquickjs::function::{Async, Func, Opt};
 use rquickjs::module::{Declarations, Exports, ModuleDef};
@@ -37,10 +37,7 @@ async fn open(ctx: Ctx<'_>, path: String, flags: Opt<String>) -> QuickJsResult<f
     match open_options.open(path).await {
         Ok(inner) => Ok(file::File::new(inner)),
 
-        Err(err) => Err(Exception::throw_message(
-            &ctx,
-            &format!("Could not open file: {}", err),
-        )),
+        Err(err) => Err(throw_io_error(&ctx, "Could not open file", err)),
     }
 }
 
@@ -75,10 +72,7 @@ fn open_sync(ctx: Ctx<'_>, path: String, flags: Opt<String>) -> QuickJsResult<fi
     match open_options.open(path) {
         Ok(inner) => Ok(file::FileSync::new(inner)),
 
-        Err(err) => Err(Exception::throw_message(
-            &ctx,
-            &format!("Could not open file: {}", err),
-        )),
+        Err(err) => Err(throw_io_error(&ctx, "Could not open file", err)),
     }
 }
 
@@ -0,0 +1,268 @@
+use rquickjs::function::{Constructor, Func, This};
+use rquickjs::{
+    qjs, Array, ArrayBuffer, Ctx, Exception, Function, Object, Result as QuickJsResult, Type, Value,
+};
+
+use std::collections::HashMap;
+use std::ffi::c_int;
+use std::ptr;
+
+/// Install the global values, `node` adds the Node.js compatible ones such as `process` and `global`
+/// on top of the ones every web environment has
+pub fn init(ctx: &Ctx<'_>, node: bool) -> QuickJsResult<()> {
+    let globals = ctx.globals();
+
+    globals.set("queueMicrotask", Func::from(queue_microtask))?;
+    globals.set("structuredClone", Func::from(structured_clone))?;
+    globals.set("setTimeout", Func::from(crate::timers::set_timeout))?;
+    globals.set("clearTimeout", Func::from(crate::timers::clear_timeout))?;
+    globals.set("setInterval", Func::from(crate::timers::set_interval))?;
+    globals.set("clearInterval", Func::from(crate::timers::clear_timeout))?;
+
+    if node {
+        globals.set("global", globals.clone())?;
+        globals.set("process", crate::process::get_process_object(ctx)?)?;
+        globals.set("setImmediate", Func::from(crate::timers::set_immediate))?;
+        globals.set("clearImmediate", Func::from(crate::timers::clear_immediate))?;
+    }
+
+    Ok(())
+}
+
+unsafe extern "C" fn call_microtask(
+    ctx: *mut qjs::JSContext,
+    _argc: c_int,
+    argv: *mut qjs::JSValue,
+) -> qjs::JSValue {
+    qjs::JS_Call(ctx, *argv, qjs::JS_UNDEFINED, 0, ptr::null_mut())
+}
+
+fn queue_microtask<'js>(ctx: Ctx<'js>, callback: Value<'js>) -> QuickJsResult<()> {
+    if !callback.is_function() {
+        return Err(Exception::throw_type(
+            &ctx,
+            "The callback argument must be of type function",
+        ));
+    }
+
+    // Errors thrown by the callback make the job fail, which reports them as uncaught exceptions
+    let mut argv = [callback.as_raw()];
+
+    let result = unsafe {
+        qjs::JS_EnqueueJob(
+            ctx.as_raw().as_ptr(),
+            Some(call_microtask),
+            1,
+            argv.as_mut_ptr(),
+        )
+    };
+
+    if result < 0 {
+        return Err(rquickjs::Error::Exception);
+    }
+
+    Ok(())
+}
+
+fn structured_clone<'js>(ctx: Ctx<'js>, value: Value<'js>) -> QuickJsResult<Value<'js>> {
+    clone_value(&ctx, value, &mut HashMap::new())
+}
+
+fn throw_data_clone_error(ctx: &Ctx<'_>, value: &Value<'_>) -> rquickjs::Error {
+    let kind = match value.type_of() {
+        Type::Symbol => "Symbol",
+        Type::Constructor | Type::Function => "Function",
+        _ => "Value",
+    };
+
+    Exception::throw_type(ctx, &format!("{} could not be cloned", kind))
+}
+
+fn is_instance_of<'js>(ctx: &Ctx<'js>, object: &Object<'js>, class: &str) -> QuickJsResult<bool> {
+    let constructor: Value = ctx.globals().get(class)?;
+
+    Ok(constructor.is_function() && object.is_instance_of(constructor))
+}
+
+fn get_constructor<'js>(ctx: &Ctx<'js>, class: &str) -> QuickJsResult<Constructor<'js>> {
+    ctx.globals().get(class)
+}
+
+fn clone_entries<'js>(
+    ctx: &Ctx<'js>,
+    object: &Object<'js>,
+    memory: &mut HashMap<Value<'js>, Value<'js>>,
+) -> QuickJsResult<Vec<Value<'js>>> {
+    let array: Object = ctx.globals().get("Array")?;
+    let from: Function = array.get("from")?;
+
+    let entries: Array = from.call((object.clone(),))?;
+
+    let mut result = Vec::new();
+
+    for entry in entries.iter::<Value>() {
+        result.push(clone_value(ctx, entry?, memory)?);
+    }
+
+    Ok(result)
+}
+
+/// Deeply copy `value`, keeping shared and circular references intact through `memory`
+fn clone_value<'js>(
+    ctx: &Ctx<'js>,
+    value: Value<'js>,
+    memory: &mut HashMap<Value<'js>, Value<'js>>,
+) -> QuickJsResult<Value<'js>> {
+    match value.type_of() {
+        Type::Symbol | Type::Function | Type::Constructor | Type::Module | Type::Unknown => {
+            return Err(throw_data_clone_error(ctx, &value))
+        }
+
+        Type::Array | Type::Object | Type::Exception => (),
+
+        _ => return Ok(value),
+    }
+
+    if let Some(cloned) = memory.get(&value) {
+        return Ok(cloned.clone());
+    }
+
+    let object = value.as_object().unwrap().clone();
+
+    if let Some(array) = value.as_array() {
+        let result = Array::new(ctx.clone())?;
+
+        memory.insert(value.clone(), result.clone().into_value());
+
+        for (i, item) in array.iter::<Value>().enumerate() {
+            result.set(i, clone_value(ctx, item?, memory)?)?;
+        }
+
+        return Ok(result.into_value());
+    }
+
+    if let Some(buffer) = ArrayBuffer::from_object(object.clone()) {
+        let bytes = buffer.as_bytes().unwrap_or_default();
+
+        let result = ArrayBuffer::new_copy(ctx.clone(), bytes)?.into_value();
+
+        memory.insert(value, result.clone());
+
+        return Ok(result);
+    }
+
+    if is_instance_of(ctx, &object, "Date")? {
+        let time: Function = object.get("getTime")?;
+        let time: f64 = time.call((This(object.clone()),))?;
+
+        let result: Value = get_constructor(ctx, "Date")?.construct((time,))?;
+
+        memory.insert(value, result.clone());
+
+        return Ok(result);
+    }
+
+    if is_instance_of(ctx, &object, "RegExp")? {
+        let source: String = object.get("source")?;
+        let flags: String = object.get("flags")?;
+
+        let result: Value = get_constructor(ctx, "RegExp")?.construct((source, flags))?;
+
+        memory.insert(value, result.clone());
+
+        return Ok(result);
+    }
+
+    if is_instance_of(ctx, &object, "Map")? {
+        let result: Object = get_constructor(ctx, "Map")?.construct(())?;
+
+        memory.insert(value, result.clone().into_value());
+
+        let set: Function = result.get("set")?;
+
+        for entry in clone_entries(ctx, &object, memory)? {
+            let entry = entry.into_array().unwrap();
+
+            set.call::<_, Value>((
+                This(result.clone()),
+                entry.get::<Value>(0)?,
+                entry.get::<Value>(1)?,
+            ))?;
+        }
+
+        return Ok(result.into_value());
+    }
+
+    if is_instance_of(ctx, &object, "Set")? {
+        let result: Object = get_constructor(ctx, "Set")?.construct(())?;
+
+        memory.insert(value, result.clone().into_value());
+
+        let add: Function = result.get("add")?;
+
+        for entry in clone_entries(ctx, &object, memory)? {
+            add.call::<_, Value>((This(result.clone()), entry))?;
+        }
+
+        return Ok(result.into_value());
+    }
+
+    let array_buffer: Object = ctx.globals().get("ArrayBuffer")?;
+    let is_view: Function = array_buffer.get("isView")?;
+
+    if is_view.call::<_, bool>((object.clone(),))? {
+        let constructor: Constructor = object.get("constructor")?;
+
+        let result: Value = if is_instance_of(ctx, &object, "DataView")? {
+            let buffer = clone_value(ctx, object.get("buffer")?, memory)?;
+            let byte_offset: Value = object.get("byteOffset")?;
+            let byte_length: Value = object.get("byteLength")?;
+
+            constructor.construct((buffer, byte_offset, byte_length))?
+        } else {
+            // Typed arrays copy the contents of the array given to their constructor
+            constructor.construct((object.clone(),))?
+        };
+
+        memory.insert(value, result.clone());
+
+        return Ok(result);
+    }
+
+    if value.is_error() {
+        let name: String = object.get("name")?;
+        let message: String = object.get("message")?;
+
+        let class = match name.as_str() {
+            "EvalError" | "RangeError" | "ReferenceError" | "SyntaxError" | "TypeError"
+            | "URIError" => name.as_str(),
+            _ => "Error",
+        };
+
+        let result: Object = get_constructor(ctx, class)?.construct((message,))?;
+
+        if let Some(stack) = crate::source_map::get_mapped_stack(ctx, &object)? {
+            crate::source_map::set_mapped_stack(ctx, &result, &stack)?;
+        }
+
+        memory.insert(value, result.clone().into_value());
+
+        if object.contains_key("cause")? {
+            result.set("cause", clone_value(ctx, object.get("cause")?, memory)?)?;
+        }
+
+        return Ok(result.into_value());
+    }
+
+    let result = Object::new(ctx.clone())?;
+
+    memory.insert(value, result.clone().into_value());
+
+    for key in object.keys::<String>() {
+        let key = key?;
+
+        result.set(&key, clone_value(ctx, object.get(&key)?, memory)?)?;
+    }
+
+    Ok(result.into_value())
+}
@@ -0,0 +1,1824 @@
+use once_cell::sync::OnceCell;
+use rquickjs::function::{Opt, Rest, This};
+use rquickjs::{
+    qjs, Array, ArrayBuffer, Coerced, Ctx, Filter, Function, Object, Result as QuickJsResult, Type,
+    Value,
+};
+
+use std::collections::HashMap;
+use std::ffi::{c_int, c_void};
+
+const MAX_ARRAY_LENGTH: usize = 100;
+const MAX_BUFFER_LENGTH: usize = 50;
+
+/// How many levels of nested objects can be printed on a single line
+const COMPACT: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct InspectOptions {
+    /// How many levels of nested objects are shown, `f64::INFINITY` shows all of them
+    pub depth: f64,
+    /// Show non-enumerable properties as well
+    pub show_hidden: bool,
+    /// Sort the properties of objects and the entries of maps and sets
+    pub sorted: bool,
+    /// The length at which objects are split over multiple lines
+    pub break_length: usize,
+    /// Call the `Symbol.for('nodejs.util.inspect.custom')` method of objects that have one
+    pub custom_inspect: bool,
+    /// Style values with ANSI escape codes
+    pub colors: bool,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        InspectOptions {
+            depth: 2.0,
+            show_hidden: false,
+            sorted: false,
+            break_length: 80,
+            custom_inspect: true,
+            colors: false,
+        }
+    }
+}
+
+impl InspectOptions {
+    pub fn from_object(options: &Object<'_>) -> QuickJsResult<Self> {
+        let mut result = InspectOptions::default();
+
+        let depth: Value = options.get("depth")?;
+
+        if depth.is_null() {
+            result.depth = f64::INFINITY;
+        } else if let Some(depth) = depth.as_number() {
+            result.depth = depth;
+        }
+
+        if let Some(show_hidden) = options.get::<_, Option<bool>>("showHidden")? {
+            result.show_hidden = show_hidden;
+        }
+
+        if let Some(sorted) = options.get::<_, Option<bool>>("sorted")? {
+            result.sorted = sorted;
+        }
+
+        if let Some(break_length) = options.get::<_, Option<f64>>("breakLength")? {
+            result.break_length = break_length.max(0.0).min(usize::MAX as f64) as usize;
+        }
+
+        if let Some(custom_inspect) = options.get::<_, Option<bool>>("customInspect")? {
+            result.custom_inspect = custom_inspect;
+        }
+
+        if let Some(colors) = options.get::<_, Option<bool>>("colors")? {
+            result.colors = colors;
+        }
+
+        Ok(result)
+    }
+
+    fn to_object<'js>(&self, ctx: &Ctx<'js>, depth: f64) -> QuickJsResult<Object<'js>> {
+        let options = Object::new(ctx.clone())?;
+
+        options.set("depth", depth)?;
+        options.set("showHidden", self.show_hidden)?;
+        options.set("sorted", self.sorted)?;
+        options.set("breakLength", self.break_length as f64)?;
+        options.set("customInspect", self.custom_inspect)?;
+        options.set("colors", self.colors)?;
+
+        let colors = self.colors;
+
+        options.set(
+            "stylize",
+            Function::new(
+                ctx.clone(),
+                move |text: Coerced<String>, style: Opt<String>| match style.0 {
+                    Some(style) if colors => stylize(&text.0, &style),
+
+                    _ => text.0,
+                },
+            )?,
+        )?;
+
+        Ok(options)
+    }
+}
+
+/// The ANSI codes that turn each color or modifier on and off, the names of `util.inspect.colors`
+pub const COLORS: &[(&str, u8, u8)] = &[
+    ("reset", 0, 0),
+    ("bold", 1, 22),
+    ("dim", 2, 22),
+    ("italic", 3, 23),
+    ("underline", 4, 24),
+    ("blink", 5, 25),
+    ("inverse", 7, 27),
+    ("hidden", 8, 28),
+    ("strikethrough", 9, 29),
+    ("doubleunderline", 21, 24),
+    ("black", 30, 39),
+    ("red", 31, 39),
+    ("green", 32, 39),
+    ("yellow", 33, 39),
+    ("blue", 34, 39),
+    ("magenta", 35, 39),
+    ("cyan", 36, 39),
+    ("white", 37, 39),
+    ("bgBlack", 40, 49),
+    ("bgRed", 41, 49),
+    ("bgGreen", 42, 49),
+    ("bgYellow", 43, 49),
+    ("bgBlue", 44, 49),
+    ("bgMagenta", 45, 49),
+    ("bgCyan", 46, 49),
+    ("bgWhite", 47, 49),
+    ("framed", 51, 54),
+    ("overlined", 53, 55),
+    ("gray", 90, 39),
+    ("grey", 90, 39),
+    ("redBright", 91, 39),
+    ("greenBright", 92, 39),
+    ("yellowBright", 93, 39),
+    ("blueBright", 94, 39),
+    ("magentaBright", 95, 39),
+    ("cyanBright", 96, 39),
+    ("whiteBright", 97, 39),
+    ("bgGray", 100, 49),
+    ("bgRedBright", 101, 49),
+    ("bgGreenBright", 102, 49),
+    ("bgYellowBright", 103, 49),
+    ("bgBlueBright", 104, 49),
+    ("bgMagentaBright", 105, 49),
+    ("bgCyanBright", 106, 49),
+    ("bgWhiteBright", 107, 49),
+];
+
+/// The color used for each kind of value, the entries of `util.inspect.styles`
+pub const STYLES: &[(&str, &str)] = &[
+    ("special", "cyan"),
+    ("number", "yellow"),
+    ("bigint", "yellow"),
+    ("boolean", "yellow"),
+    ("undefined", "grey"),
+    ("null", "bold"),
+    ("string", "green"),
+    ("symbol", "green"),
+    ("date", "magenta"),
+    ("regexp", "red"),
+    ("module", "underline"),
+];
+
+/// Wrap `text` in the escape codes of the color named `color`, leaving it as is if it is unknown
+pub fn colorize(text: &str, color: &str) -> String {
+    match COLORS.iter().find(|(name, _, _)| *name == color) {
+        Some((_, open, close)) => format!("\x1b[{}m{}\x1b[{}m", open, text, close),
+
+        None => text.to_string(),
+    }
+}
+
+/// Color `text` with the color `util.inspect.styles` gives to `style`
+pub fn stylize(text: &str, style: &str) -> String {
+    match STYLES.iter().find(|(name, _)| *name == style) {
+        Some((_, color)) => colorize(text, color),
+
+        None => text.to_string(),
+    }
+}
+
+/// The number of characters of `text` a terminal shows, leaving out ANSI escape codes
+pub fn visible_length(text: &str) -> usize {
+    let mut length = 0;
+    let mut chars = text.chars();
+
+    while let Some(char) = chars.next() {
+        if char == '\x1b' {
+            for char in chars.by_ref() {
+                if char.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+
+            continue;
+        }
+
+        length += 1;
+    }
+
+    length
+}
+
+/// The symbol objects can define a method under to customize how they are inspected
+pub fn get_custom_inspect_symbol<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Value<'js>> {
+    let symbol: Object = ctx.globals().get("Symbol")?;
+    let symbol_for: Function = symbol.get("for")?;
+
+    symbol_for.call(("nodejs.util.inspect.custom",))
+}
+
+/// Turn `value` into a readable representation the way Node.js' `util.inspect` does
+pub fn inspect<'js>(
+    ctx: &Ctx<'js>,
+    value: &Value<'js>,
+    options: &InspectOptions,
+) -> QuickJsResult<String> {
+    let globals = ctx.globals();
+
+    let object: Object = globals.get("Object")?;
+    let reflect: Object = globals.get("Reflect")?;
+    let symbol: Object = globals.get("Symbol")?;
+
+    let mut inspector = Inspector {
+        ctx: ctx.clone(),
+        options,
+        get_own_property_descriptor: object.get("getOwnPropertyDescriptor")?,
+        own_keys: reflect.get("ownKeys")?,
+        to_string: globals.get("String")?,
+        to_string_tag: symbol.get("toStringTag")?,
+        custom_inspect: get_custom_inspect_symbol(ctx)?,
+        seen: Vec::new(),
+        circular: HashMap::new(),
+        indentation: 0,
+        current_depth: 0,
+    };
+
+    inspector.format_value(value, 0)
+}
+
+/// `util.inspect(value, options)`, also accepting the legacy `(value, showHidden, depth)` form
+pub fn js_inspect<'js>(
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    options: Opt<Value<'js>>,
+    depth: Opt<Value<'js>>,
+) -> QuickJsResult<String> {
+    let mut inspect_options = InspectOptions::default();
+
+    if let Some(options) = options.0 {
+        if let Some(show_hidden) = options.as_bool() {
+            inspect_options.show_hidden = show_hidden;
+
+            match depth.0 {
+                Some(depth) if depth.is_null() => inspect_options.depth = f64::INFINITY,
+
+                Some(depth) => {
+                    if let Some(depth) = depth.as_number() {
+                        inspect_options.depth = depth;
+                    }
+                }
+
+                None => (),
+            }
+        } else if let Some(options) = options.as_object() {
+            inspect_options = InspectOptions::from_object(options)?;
+        }
+    }
+
+    inspect(&ctx, &value, &inspect_options)
+}
+
+fn format_number<'js>(ctx: &Ctx<'js>, number: f64) -> QuickJsResult<String> {
+    if number == 0.0 && number.is_sign_negative() {
+        return Ok(String::from("-0"));
+    }
+
+    inspect(
+        ctx,
+        &Value::new_number(ctx.clone(), number),
+        &InspectOptions::default(),
+    )
+}
+
+/// Whether the `toString` method of `object` comes from a builtin class rather than user code
+fn has_built_in_to_string<'js>(ctx: &Ctx<'js>, object: &Object<'js>) -> QuickJsResult<bool> {
+    let to_string: Value = object.get("toString")?;
+
+    if !to_string.is_function() {
+        return Ok(true);
+    }
+
+    let mut current = object.clone();
+
+    loop {
+        let keys: Vec<String> = current
+            .own_keys(Filter::new().string())
+            .collect::<QuickJsResult<_>>()?;
+
+        if keys.iter().any(|key| key == "toString") {
+            break;
+        }
+
+        match current.get_prototype() {
+            Some(prototype) => current = prototype,
+
+            None => return Ok(true),
+        }
+    }
+
+    if current == *object {
+        return Ok(false);
+    }
+
+    let constructor: Value = current.get("constructor")?;
+
+    let Some(constructor) = constructor.as_function() else {
+        return Ok(false);
+    };
+
+    let name: Value = constructor.get("name")?;
+
+    match name.as_string() {
+        Some(name) => {
+            let global: Value = ctx.globals().get(name.to_string()?)?;
+
+            Ok(global == *constructor.as_value())
+        }
+
+        None => Ok(false),
+    }
+}
+
+fn call_global<'js>(ctx: &Ctx<'js>, name: &str, value: &Value<'js>) -> QuickJsResult<f64> {
+    let function: Function = ctx.globals().get(name)?;
+
+    function.call((value.clone(),))
+}
+
+fn format_json<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> QuickJsResult<String> {
+    let json: Object = ctx.globals().get("JSON")?;
+    let stringify: Function = json.get("stringify")?;
+
+    match stringify.call::<_, Option<String>>((value.clone(),)) {
+        Ok(json) => Ok(json.unwrap_or_else(|| String::from("undefined"))),
+
+        Err(rquickjs::Error::Exception) => {
+            let error = ctx.catch();
+
+            let message = match error.as_exception() {
+                Some(exception) => exception.message().unwrap_or_default(),
+
+                None => String::new(),
+            };
+
+            if message.contains("circular") {
+                Ok(String::from("[Circular]"))
+            } else {
+                Err(ctx.throw(error))
+            }
+        }
+
+        Err(err) => Err(err),
+    }
+}
+
+/// Format a single `%` specifier with `arg`, `None` if the specifier is not one
+fn format_specifier<'js>(
+    ctx: &Ctx<'js>,
+    specifier: char,
+    arg: &Value<'js>,
+    options: &InspectOptions,
+) -> QuickJsResult<Option<String>> {
+    let is_symbol = arg.is_symbol();
+    let is_big_int = arg.type_of() == Type::BigInt;
+
+    let result = match specifier {
+        's' if arg.is_string() => arg.as_string().unwrap().to_string()?,
+
+        's' if arg.is_object() && !has_built_in_to_string(ctx, arg.as_object().unwrap())? => ctx
+            .globals()
+            .get::<_, Function>("String")?
+            .call((arg.clone(),))?,
+
+        's' if arg.is_object() => {
+            let options = InspectOptions {
+                depth: 0.0,
+                ..InspectOptions::default()
+            };
+
+            inspect(ctx, arg, &options)?
+        }
+
+        's' => inspect(ctx, arg, &InspectOptions::default())?,
+
+        'd' | 'i' | 'f' if is_symbol => String::from("NaN"),
+
+        'd' | 'i' if is_big_int => inspect(ctx, arg, &InspectOptions::default())?,
+
+        'd' => format_number(ctx, call_global(ctx, "Number", arg)?)?,
+
+        'i' => format_number(ctx, call_global(ctx, "parseInt", arg)?)?,
+
+        'f' => format_number(ctx, call_global(ctx, "parseFloat", arg)?)?,
+
+        'j' => format_json(ctx, arg)?,
+
+        'o' => {
+            let options = InspectOptions {
+                depth: 4.0,
+                show_hidden: true,
+                ..options.clone()
+            };
+
+            inspect(ctx, arg, &options)?
+        }
+
+        'O' => inspect(ctx, arg, options)?,
+
+        'c' => String::new(),
+
+        _ => return Ok(None),
+    };
+
+    Ok(Some(result))
+}
+
+/// Join `args` the way `console.log` and `util.format` do, substituting the printf-style
+/// specifiers of the first argument when it is a string
+pub fn format<'js>(ctx: &Ctx<'js>, args: &[Value<'js>]) -> QuickJsResult<String> {
+    format_with_options(ctx, args, &InspectOptions::default())
+}
+
+/// Like `format`, inspecting the values that are not strings with `options`
+pub fn format_with_options<'js>(
+    ctx: &Ctx<'js>,
+    args: &[Value<'js>],
+    options: &InspectOptions,
+) -> QuickJsResult<String> {
+    let mut parts = Vec::new();
+    let mut rest = args;
+
+    if let Some(template) = args.first().and_then(|arg| arg.as_string()) {
+        let template = template.to_string()?;
+
+        let mut result = String::new();
+
+        rest = &args[1..];
+
+        let mut chars = template.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            if char != '%' {
+                result.push(char);
+
+                continue;
+            }
+
+            match chars.peek().copied() {
+                Some('%') => {
+                    chars.next();
+
+                    result.push('%');
+                }
+
+                Some(specifier) if !rest.is_empty() => {
+                    match format_specifier(ctx, specifier, &rest[0], options)? {
+                        Some(formatted) => {
+                            chars.next();
+
+                            result.push_str(&formatted);
+
+                            rest = &rest[1..];
+                        }
+
+                        None => result.push('%'),
+                    }
+                }
+
+                _ => result.push('%'),
+            }
+        }
+
+        parts.push(result);
+    }
+
+    for arg in rest {
+        match arg.as_string() {
+            Some(string) => parts.push(string.to_string()?),
+
+            None => parts.push(inspect(ctx, arg, options)?),
+        }
+    }
+
+    Ok(parts.join(" "))
+}
+
+/// `util.format(format, ...args)`
+pub fn js_format<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> QuickJsResult<String> {
+    format(&ctx, &args)
+}
+
+/// `util.formatWithOptions(options, format, ...args)`
+pub fn js_format_with_options<'js>(
+    ctx: Ctx<'js>,
+    options: Object<'js>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<String> {
+    format_with_options(&ctx, &args, &InspectOptions::from_object(&options)?)
+}
+
+/// The `util.inspect.colors` and `util.inspect.styles` objects
+pub fn create_colors_objects<'js>(ctx: &Ctx<'js>) -> QuickJsResult<(Object<'js>, Object<'js>)> {
+    let colors = Object::new(ctx.clone())?;
+
+    for (name, open, close) in COLORS {
+        colors.set(*name, vec![*open, *close])?;
+    }
+
+    let styles = Object::new(ctx.clone())?;
+
+    for (name, color) in STYLES {
+        styles.set(*name, *color)?;
+    }
+
+    Ok((colors, styles))
+}
+
+/// Mirror of QuickJS' internal `JSPromiseData`, the opaque data of promise objects
+#[repr(C)]
+struct PromiseData {
+    state: c_int,
+    reactions: [[*mut c_void; 2]; 2],
+    is_handled: c_int,
+    result: qjs::JSValue,
+}
+
+static PROMISE_CLASS_ID: OnceCell<Option<qjs::JSClassID>> = OnceCell::new();
+
+/// Class ids are fixed when QuickJS is built, find the one of promises by probing a promise
+fn get_promise_class_id(ctx: &Ctx<'_>) -> Option<qjs::JSClassID> {
+    *PROMISE_CLASS_ID.get_or_init(|| {
+        let promise: Object = ctx.globals().get("Promise").ok()?;
+        let resolve: Function = promise.get("resolve").ok()?;
+        let promise: Value = resolve.call((This(promise),)).ok()?;
+
+        (1..256)
+            .find(|&class_id| unsafe { !qjs::JS_GetOpaque(promise.as_raw(), class_id).is_null() })
+    })
+}
+
+/// The state of a promise and what it settled with
+pub enum PromiseState<'js> {
+    Pending,
+    Fulfilled(Value<'js>),
+    Rejected(Value<'js>),
+}
+
+/// The state of `promise`, or `None` if it isn't a promise
+pub fn get_promise_state<'js>(ctx: &Ctx<'js>, promise: &Object<'js>) -> Option<PromiseState<'js>> {
+    let class_id = get_promise_class_id(ctx)?;
+
+    let data = unsafe { qjs::JS_GetOpaque(promise.as_raw(), class_id) } as *const PromiseData;
+
+    if data.is_null() {
+        return None;
+    }
+
+    let (state, result) = unsafe { ((*data).state, (*data).result) };
+
+    let result = || unsafe { Value::from_raw(ctx.clone(), qjs::JS_DupValue(result)) };
+
+    match state {
+        0 => Some(PromiseState::Pending),
+        1 => Some(PromiseState::Fulfilled(result())),
+        2 => Some(PromiseState::Rejected(result())),
+        _ => None,
+    }
+}
+
+/// Quote `string` with the first of `'`, `"` and `` ` `` it does not contain, escaping what is
+/// needed to read it back
+pub fn quote_string(string: &str) -> String {
+    let quote = if !string.contains('\'') {
+        '\''
+    } else if !string.contains('"') {
+        '"'
+    } else if !string.contains('`') && !string.contains("${") {
+        '`'
+    } else {
+        '\''
+    };
+
+    let mut result = String::with_capacity(string.len() + 2);
+
+    result.push(quote);
+
+    for char in string.chars() {
+        match char {
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            '\\' => result.push_str("\\\\"),
+            char if char == quote => {
+                result.push('\\');
+                result.push(char);
+            }
+            char if (char as u32) < 0x20 || char as u32 == 0x7f => {
+                result.push_str(&format!("\\x{:02X}", char as u32))
+            }
+            char => result.push(char),
+        }
+    }
+
+    result.push(quote);
+
+    result
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+
+    match chars.next() {
+        Some(char) if char.is_ascii_alphabetic() || char == '_' => {
+            chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+        }
+
+        _ => false,
+    }
+}
+
+fn is_index(key: &str, length: usize) -> bool {
+    match key.parse::<usize>() {
+        Ok(index) => index < length && index.to_string() == key,
+
+        Err(_) => false,
+    }
+}
+
+fn get_prefix(constructor: &Option<String>, tag: &str, fallback: &str, size: &str) -> String {
+    match constructor {
+        None if !tag.is_empty() && fallback != tag => {
+            format!("[{}{}: null prototype] [{}] ", fallback, size, tag)
+        }
+
+        None => format!("[{}{}: null prototype] ", fallback, size),
+
+        Some(constructor) if !tag.is_empty() && constructor != tag => {
+            format!("{}{} [{}] ", constructor, size, tag)
+        }
+
+        Some(constructor) => format!("{}{} ", constructor, size),
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Arrange many short array entries in aligned columns
+fn group_array_elements(
+    output: Vec<String>,
+    indentation: usize,
+    break_length: usize,
+    has_more: bool,
+    pad_start: bool,
+) -> Vec<String> {
+    let separator_space = 2;
+
+    let output_length = if has_more {
+        output.len() - 1
+    } else {
+        output.len()
+    };
+
+    let data_length: Vec<usize> = output.iter().map(|entry| visible_length(entry)).collect();
+
+    let total_length: usize = data_length[..output_length]
+        .iter()
+        .map(|length| length + separator_space)
+        .sum();
+
+    let max_length = data_length[..output_length]
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0);
+
+    let actual_max = max_length + separator_space;
+
+    if actual_max * 3 + indentation >= break_length
+        || (total_length as f64 / actual_max as f64 <= 5.0 && max_length > 6)
+    {
+        return output;
+    }
+
+    let average_bias = (actual_max as f64 - total_length as f64 / output.len() as f64).sqrt();
+    let biased_max = (actual_max as f64 - 3.0 - average_bias).max(1.0);
+
+    let columns = ((2.5 * biased_max * output_length as f64).sqrt() / biased_max).round() as usize;
+
+    let columns = columns
+        .min(break_length.saturating_sub(indentation) / actual_max)
+        .min(COMPACT * 4)
+        .min(15);
+
+    if columns <= 1 {
+        return output;
+    }
+
+    let max_line_length: Vec<usize> = (0..columns)
+        .map(|column| {
+            let line_length = (column..output_length)
+                .step_by(columns)
+                .map(|i| data_length[i])
+                .max()
+                .unwrap_or(0);
+
+            line_length + separator_space
+        })
+        .collect();
+
+    let mut result = Vec::new();
+
+    for start in (0..output_length).step_by(columns) {
+        let end = (start + columns).min(output_length);
+
+        let mut line = String::new();
+
+        for i in start..end {
+            let entry = if i < end - 1 {
+                format!("{}, ", output[i])
+            } else {
+                output[i].clone()
+            };
+
+            let width = if i < end - 1 {
+                max_line_length[i - start]
+            } else {
+                max_line_length[i - start] - separator_space
+            };
+
+            let padding = " ".repeat(width.saturating_sub(visible_length(&entry)));
+
+            if pad_start {
+                line.push_str(&padding);
+                line.push_str(&entry);
+            } else if i < end - 1 {
+                line.push_str(&entry);
+                line.push_str(&padding);
+            } else {
+                line.push_str(&entry);
+            }
+        }
+
+        result.push(line);
+    }
+
+    if has_more {
+        result.push(output[output_length].clone());
+    }
+
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Extras {
+    Object,
+    Array,
+}
+
+struct Inspector<'js, 'a> {
+    ctx: Ctx<'js>,
+    options: &'a InspectOptions,
+    get_own_property_descriptor: Function<'js>,
+    own_keys: Function<'js>,
+    to_string: Function<'js>,
+    to_string_tag: Value<'js>,
+    custom_inspect: Value<'js>,
+    /// The objects currently being formatted, to detect circular references
+    seen: Vec<Value<'js>>,
+    /// The objects referenced circularly, with the index used to refer to them
+    circular: HashMap<Value<'js>, usize>,
+    indentation: usize,
+    current_depth: usize,
+}
+
+impl<'js, 'a> Inspector<'js, 'a> {
+    fn format_value(&mut self, value: &Value<'js>, level: usize) -> QuickJsResult<String> {
+        match value.as_object() {
+            Some(object) if value.type_of() != Type::Module => {
+                self.format_object(object.clone(), level)
+            }
+
+            _ => self.format_primitive(value),
+        }
+    }
+
+    fn stylize(&self, text: &str, style: &str) -> String {
+        if self.options.colors {
+            stylize(text, style)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn format_primitive(&self, value: &Value<'js>) -> QuickJsResult<String> {
+        let style = match value.type_of() {
+            Type::String => "string",
+            Type::Int | Type::Float => "number",
+            Type::BigInt => "bigint",
+            Type::Bool => "boolean",
+            Type::Uninitialized | Type::Undefined => "undefined",
+            Type::Null => "null",
+            Type::Symbol => "symbol",
+            _ => "special",
+        };
+
+        Ok(self.stylize(&self.format_plain_primitive(value)?, style))
+    }
+
+    fn format_plain_primitive(&self, value: &Value<'js>) -> QuickJsResult<String> {
+        match value.type_of() {
+            Type::String => {
+                let string = value.as_string().unwrap().to_string()?;
+
+                Ok(quote_string(&string))
+            }
+
+            Type::Float if value.as_float() == Some(0.0) => {
+                if value.as_float().unwrap().is_sign_negative() {
+                    Ok(String::from("-0"))
+                } else {
+                    Ok(String::from("0"))
+                }
+            }
+
+            Type::BigInt => Ok(format!("{}n", self.stringify(value)?)),
+
+            Type::Uninitialized => Ok(String::from("undefined")),
+
+            Type::Module => Ok(String::from("[Module]")),
+
+            Type::Unknown => Ok(String::from("{unknown}")),
+
+            _ => self.stringify(value),
+        }
+    }
+
+    fn stringify(&self, value: &Value<'js>) -> QuickJsResult<String> {
+        self.to_string.call((value.clone(),))
+    }
+
+    fn get_descriptor(
+        &self,
+        object: &Object<'js>,
+        key: &Value<'js>,
+    ) -> QuickJsResult<Option<Object<'js>>> {
+        self.get_own_property_descriptor
+            .call((object.clone(), key.clone()))
+    }
+
+    fn is_instance_of(&self, object: &Object<'js>, class: &str) -> QuickJsResult<bool> {
+        let constructor: Value = self.ctx.globals().get(class)?;
+
+        Ok(constructor.is_function() && object.is_instance_of(constructor))
+    }
+
+    /// Call the `valueOf` method `class` has for its instances on `object`
+    fn primitive_value_of(&self, object: &Object<'js>, class: &str) -> QuickJsResult<Value<'js>> {
+        let constructor: Object = self.ctx.globals().get(class)?;
+        let prototype: Object = constructor.get("prototype")?;
+        let value_of: Function = prototype.get("valueOf")?;
+
+        value_of.call((This(object.clone()),))
+    }
+
+    /// The name of the first constructor found on the prototype chain, `None` for objects without
+    /// a prototype
+    fn get_constructor_name(&self, object: &Object<'js>) -> QuickJsResult<Option<String>> {
+        let key = rquickjs::String::from_str(self.ctx.clone(), "constructor")?.into_value();
+
+        let mut current = Some(object.clone());
+
+        while let Some(prototype) = current {
+            if let Some(descriptor) = self.get_descriptor(&prototype, &key)? {
+                let constructor: Value = descriptor.get("value")?;
+
+                if let Some(constructor) = constructor.as_function() {
+                    let name: Value = constructor.get("name")?;
+
+                    if let Some(name) = name.as_string() {
+                        let name = name.to_string()?;
+
+                        if !name.is_empty() && object.is_instance_of(constructor) {
+                            return Ok(Some(name));
+                        }
+                    }
+                }
+            }
+
+            current = prototype.get_prototype();
+        }
+
+        Ok(None)
+    }
+
+    fn get_tag(&self, object: &Object<'js>, constructor: &Option<String>) -> QuickJsResult<String> {
+        let tag: Value = object.get(self.to_string_tag.clone())?;
+
+        match tag.as_string() {
+            Some(tag) => {
+                let tag = tag.to_string()?;
+
+                if constructor.as_deref() == Some(tag.as_str()) {
+                    Ok(String::new())
+                } else {
+                    Ok(tag)
+                }
+            }
+
+            None => Ok(String::new()),
+        }
+    }
+
+    /// The own keys of `object` including symbols, `Object::own_keys` turns symbols into strings
+    fn get_keys(&self, object: &Object<'js>) -> QuickJsResult<Vec<Value<'js>>> {
+        let keys: Vec<Value> = self.own_keys.call((object.clone(),))?;
+
+        if self.options.show_hidden {
+            return Ok(keys);
+        }
+
+        let mut result = Vec::new();
+
+        for key in keys {
+            let enumerable = match self.get_descriptor(object, &key)? {
+                Some(descriptor) => descriptor.get::<_, Option<bool>>("enumerable")?,
+
+                None => None,
+            };
+
+            if enumerable.unwrap_or(false) {
+                result.push(key);
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn key_name(&self, key: &Value<'js>) -> QuickJsResult<String> {
+        match key.as_string() {
+            Some(key) => key.to_string(),
+
+            None => self.stringify(key),
+        }
+    }
+
+    fn call_custom_inspect(
+        &mut self,
+        object: &Object<'js>,
+        level: usize,
+    ) -> QuickJsResult<Option<String>> {
+        let custom: Value = object.get(self.custom_inspect.clone())?;
+
+        let Some(custom) = custom.as_function() else {
+            return Ok(None);
+        };
+
+        // Prototypes hold the method for their instances, they are inspected normally
+        let constructor: Value = object.get("constructor")?;
+
+        if let Some(constructor) = constructor.as_object() {
+            let prototype: Value = constructor.get("prototype")?;
+
+            if prototype == *object.as_value() {
+                return Ok(None);
+            }
+        }
+
+        let depth = self.options.depth - level as f64;
+        let options = self.options.to_object(&self.ctx, depth)?;
+        let inspect = Function::new(self.ctx.clone(), js_inspect)?;
+
+        let result: Value = custom.call((This(object.clone()), depth, options, inspect))?;
+
+        if result == *object.as_value() {
+            return Ok(None);
+        }
+
+        if let Some(result) = result.as_string() {
+            let indentation = format!("\n{}", " ".repeat(self.indentation));
+
+            return Ok(Some(result.to_string()?.replace('\n', &indentation)));
+        }
+
+        self.format_value(&result, level).map(Some)
+    }
+
+    fn format_object(&mut self, object: Object<'js>, level: usize) -> QuickJsResult<String> {
+        if self.options.custom_inspect {
+            if let Some(result) = self.call_custom_inspect(&object, level)? {
+                return Ok(result);
+            }
+        }
+
+        let value = object.clone().into_value();
+
+        if self.seen.contains(&value) {
+            let next = self.circular.len() + 1;
+            let index = *self.circular.entry(value).or_insert(next);
+
+            return Ok(self.stylize(&format!("[Circular *{}]", index), "special"));
+        }
+
+        let constructor = self.get_constructor_name(&object)?;
+        let tag = self.get_tag(&object, &constructor)?;
+
+        self.seen.push(value.clone());
+
+        let result = self.format_raw(&object, level, constructor, tag);
+
+        self.seen.pop();
+
+        let mut result = result?;
+
+        if let Some(index) = self.circular.get(&value) {
+            let reference = self.stylize(&format!("<ref *{}>", index), "special");
+
+            result = format!("{} {}", reference, result);
+        }
+
+        Ok(result)
+    }
+
+    /// Start formatting the children of `object`, returning what to print instead when it is
+    /// nested deeper than the depth limit
+    fn enter(
+        &mut self,
+        object: &Object<'js>,
+        level: usize,
+        constructor: &Option<String>,
+        tag: &str,
+    ) -> Option<String> {
+        if level as f64 <= self.options.depth {
+            self.current_depth = level;
+
+            return None;
+        }
+
+        let name = get_prefix(constructor, tag, "Object", "");
+        let name = name.trim_end();
+
+        let name = match constructor {
+            Some(_) if object.as_value().is_array() => String::from("[Array]"),
+
+            Some(_) => format!("[{}]", name),
+
+            None => name.to_string(),
+        };
+
+        Some(self.stylize(&name, "special"))
+    }
+
+    fn format_raw(
+        &mut self,
+        object: &Object<'js>,
+        level: usize,
+        constructor: Option<String>,
+        tag: String,
+    ) -> QuickJsResult<String> {
+        let mut keys = self.get_keys(object)?;
+        let mut base = String::new();
+        let mut braces = (String::from("{"), String::from("}"));
+        let mut output = Vec::new();
+        let mut extras = Extras::Object;
+        let mut pad_start = false;
+        let mut has_more = false;
+        let mut entered = false;
+
+        let array_buffer: Object = self.ctx.globals().get("ArrayBuffer")?;
+        let is_view: Function = array_buffer.get("isView")?;
+
+        if let Some(array) = object.as_array() {
+            let length = array.len();
+
+            keys.retain(|key| match key.as_string() {
+                Some(key) => !is_index(&key.to_string().unwrap_or_default(), length),
+
+                None => true,
+            });
+
+            let prefix = if constructor.as_deref() != Some("Array") || !tag.is_empty() {
+                get_prefix(&constructor, &tag, "Array", &format!("({})", length))
+            } else {
+                String::new()
+            };
+
+            braces = (format!("{}[", prefix), String::from("]"));
+
+            if length == 0 && keys.is_empty() {
+                return Ok(format!("{}]", braces.0));
+            }
+
+            extras = Extras::Array;
+
+            if let Some(name) = self.enter(object, level, &constructor, &tag) {
+                return Ok(name);
+            }
+
+            entered = true;
+
+            (output, pad_start, has_more) = self.format_array(array, level)?;
+        } else if object.as_value().is_function() {
+            base = self.format_function_base(object, &constructor, &tag)?;
+            base = self.stylize(&base, "special");
+
+            keys.retain(|key| {
+                let name = key.as_string().and_then(|key| key.to_string().ok());
+
+                !matches!(name.as_deref(), Some("length" | "name" | "prototype"))
+            });
+
+            if keys.is_empty() {
+                return Ok(base);
+            }
+        } else if object.as_value().is_error() {
+            base = self.format_error(object)?;
+
+            keys.retain(|key| {
+                let name = key.as_string().and_then(|key| key.to_string().ok());
+
+                !matches!(name.as_deref(), Some("name" | "message" | "stack"))
+            });
+
+            for extra in ["cause", "errors"] {
+                let key = rquickjs::String::from_str(self.ctx.clone(), extra)?.into_value();
+
+                let included = keys.iter().any(|key| {
+                    key.as_string()
+                        .and_then(|key| key.to_string().ok())
+                        .is_some_and(|name| name == extra)
+                });
+
+                if !included && self.get_descriptor(object, &key)?.is_some() {
+                    keys.push(key);
+                }
+            }
+
+            if keys.is_empty() {
+                return Ok(base);
+            }
+        } else if is_view.call::<_, bool>((object.clone(),))?
+            && !self.is_instance_of(object, "DataView")?
+        {
+            let length: usize = object.get("length")?;
+
+            keys.retain(|key| match key.as_string() {
+                Some(key) => !is_index(&key.to_string().unwrap_or_default(), length),
+
+                None => true,
+            });
+
+            let fallback = constructor
+                .clone()
+                .unwrap_or_else(|| String::from("TypedArray"));
+            let prefix = get_prefix(&constructor, &tag, &fallback, &format!("({})", length));
+
+            braces = (format!("{}[", prefix), String::from("]"));
+
+            if length == 0 && keys.is_empty() {
+                return Ok(format!("{}]", braces.0));
+            }
+
+            extras = Extras::Array;
+            pad_start = true;
+
+            for i in 0..length.min(MAX_ARRAY_LENGTH) {
+                output.push(self.format_primitive(&object.get(i as u32)?)?);
+            }
+
+            if length > MAX_ARRAY_LENGTH {
+                let remaining = length - MAX_ARRAY_LENGTH;
+
+                has_more = true;
+
+                output.push(format!("... {} more item{}", remaining, plural(remaining)));
+            }
+        } else if let Some(buffer) = ArrayBuffer::from_object(object.clone()) {
+            let prefix = get_prefix(&constructor, &tag, "ArrayBuffer", "");
+
+            braces.0 = format!("{}{{", prefix);
+
+            let bytes = buffer.as_bytes().unwrap_or_default();
+
+            let mut contents: Vec<String> = bytes
+                .iter()
+                .take(MAX_BUFFER_LENGTH)
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+
+            if bytes.len() > MAX_BUFFER_LENGTH {
+                let remaining = bytes.len() - MAX_BUFFER_LENGTH;
+
+                contents.push(format!("... {} more byte{}", remaining, plural(remaining)));
+            }
+
+            output.push(format!("[Uint8Contents]: <{}>", contents.join(" ")));
+            output.push(format!("byteLength: {}", bytes.len()));
+        } else if self.is_instance_of(object, "Map")? {
+            let size: usize = object.get("size")?;
+
+            braces.0 = format!(
+                "{}{{",
+                get_prefix(&constructor, &tag, "Map", &format!("({})", size))
+            );
+
+            if size == 0 && keys.is_empty() {
+                return Ok(format!("{}}}", braces.0));
+            }
+
+            self.indentation += 2;
+
+            if let Some(name) = self.enter(object, level, &constructor, &tag) {
+                return Ok(name);
+            }
+
+            entered = true;
+
+            let entries = self.format_map(object, level);
+
+            self.indentation -= 2;
+
+            output = entries?;
+        } else if self.is_instance_of(object, "Set")? {
+            let size: usize = object.get("size")?;
+
+            braces.0 = format!(
+                "{}{{",
+                get_prefix(&constructor, &tag, "Set", &format!("({})", size))
+            );
+
+            if size == 0 && keys.is_empty() {
+                return Ok(format!("{}}}", braces.0));
+            }
+
+            self.indentation += 2;
+
+            if let Some(name) = self.enter(object, level, &constructor, &tag) {
+                return Ok(name);
+            }
+
+            entered = true;
+
+            let entries = self.format_set(object, level);
+
+            self.indentation -= 2;
+
+            output = entries?;
+        } else if self.is_instance_of(object, "WeakMap")?
+            || self.is_instance_of(object, "WeakSet")?
+        {
+            let fallback = constructor
+                .clone()
+                .unwrap_or_else(|| String::from("Object"));
+
+            braces.0 = format!("{}{{", get_prefix(&constructor, &tag, &fallback, ""));
+
+            output.push(self.stylize("<items unknown>", "special"));
+        } else if self.is_instance_of(object, "Date")? {
+            let time: f64 = self
+                .primitive_value_of(object, "Date")?
+                .as_number()
+                .unwrap_or(f64::NAN);
+
+            base = if time.is_nan() {
+                String::from("Invalid Date")
+            } else {
+                let to_iso_string: Function = object.get("toISOString")?;
+
+                to_iso_string.call((This(object.clone()),))?
+            };
+
+            base = self.stylize(&base, "date");
+
+            if keys.is_empty() {
+                return Ok(base);
+            }
+        } else if self.is_instance_of(object, "RegExp")? {
+            let source: String = object.get("source")?;
+            let flags: String = object.get("flags")?;
+
+            base = self.stylize(&format!("/{}/{}", source, flags), "regexp");
+
+            if keys.is_empty() {
+                return Ok(base);
+            }
+        } else if self.is_instance_of(object, "Promise")? {
+            braces.0 = format!("{}{{", get_prefix(&constructor, &tag, "Promise", ""));
+
+            self.indentation += 2;
+
+            if let Some(name) = self.enter(object, level, &constructor, &tag) {
+                return Ok(name);
+            }
+
+            entered = true;
+
+            let state = match get_promise_state(&self.ctx, object) {
+                Some(PromiseState::Pending) => Ok(self.stylize("<pending>", "special")),
+
+                Some(PromiseState::Fulfilled(result)) => self.format_value(&result, level + 1),
+
+                Some(PromiseState::Rejected(result)) => self
+                    .format_value(&result, level + 1)
+                    .map(|result| format!("{} {}", self.stylize("<rejected>", "special"), result)),
+
+                None => Ok(String::from("<unknown>")),
+            };
+
+            self.indentation -= 2;
+
+            output.push(state?);
+        } else if let Some(class) = self.get_boxed_class(object)? {
+            let primitive = self.primitive_value_of(object, class)?;
+
+            let style = match class {
+                "Number" => "number",
+                "String" => "string",
+                "Boolean" => "boolean",
+                "BigInt" => "bigint",
+                _ => "symbol",
+            };
+
+            base = format!("[{}: {}]", class, self.format_plain_primitive(&primitive)?);
+            base = self.stylize(&base, style);
+
+            if class == "String" {
+                let length: usize = object.get("length")?;
+
+                keys.retain(|key| match key.as_string() {
+                    Some(key) => {
+                        let key = key.to_string().unwrap_or_default();
+
+                        key != "length" && !is_index(&key, length)
+                    }
+
+                    None => true,
+                });
+            }
+
+            if keys.is_empty() {
+                return Ok(base);
+            }
+        } else if constructor.is_none() && tag == "Module" {
+            braces.0 = String::from("[Module: null prototype] {");
+        } else if constructor.as_deref() == Some("Object") {
+            if !tag.is_empty() {
+                braces.0 = format!("{}{{", get_prefix(&constructor, &tag, "Object", ""));
+            }
+
+            if keys.is_empty() {
+                return Ok(format!("{}}}", braces.0));
+            }
+        } else {
+            braces.0 = format!("{}{{", get_prefix(&constructor, &tag, "Object", ""));
+
+            if keys.is_empty() {
+                return Ok(format!("{}}}", braces.0));
+            }
+        }
+
+        if !entered {
+            if let Some(name) = self.enter(object, level, &constructor, &tag) {
+                return Ok(name);
+            }
+        }
+
+        let key_count = keys.len();
+
+        for key in keys {
+            output.push(self.format_property(object, &key, level)?);
+        }
+
+        if self.options.sorted {
+            if extras == Extras::Object {
+                output.sort();
+            } else if key_count > 1 {
+                let start = output.len() - key_count;
+
+                output[start..].sort();
+            }
+        }
+
+        Ok(self.reduce_to_single_string(output, base, braces, extras, level, pad_start, has_more))
+    }
+
+    fn get_boxed_class(&self, object: &Object<'js>) -> QuickJsResult<Option<&'static str>> {
+        for class in ["Number", "String", "Boolean", "BigInt", "Symbol"] {
+            if self.is_instance_of(object, class)? {
+                // Instances created without the constructor, such as `Object.create(Number.prototype)`,
+                // do not hold a primitive
+                match self.primitive_value_of(object, class) {
+                    Ok(_) => return Ok(Some(class)),
+
+                    Err(rquickjs::Error::Exception) => {
+                        self.ctx.catch();
+                    }
+
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn format_function_base(
+        &self,
+        function: &Object<'js>,
+        constructor: &Option<String>,
+        tag: &str,
+    ) -> QuickJsResult<String> {
+        let function_constructor: Object = self.ctx.globals().get("Function")?;
+        let prototype: Object = function_constructor.get("prototype")?;
+        let to_string: Function = prototype.get("toString")?;
+
+        let source: String = to_string.call((This(function.clone()),))?;
+
+        let name: Value = function.get("name")?;
+
+        let name = match name.as_string() {
+            Some(name) => name.to_string()?,
+
+            None => String::new(),
+        };
+
+        if source.starts_with("class") && source.ends_with('}') {
+            let mut base = String::from("[class ");
+
+            if name.is_empty() {
+                base.push_str("(anonymous)");
+            } else {
+                base.push_str(&name);
+            }
+
+            match constructor.as_deref() {
+                Some("Function") => (),
+
+                Some(constructor) => base.push_str(&format!(" [{}]", constructor)),
+
+                None => base.push_str(" [null prototype]"),
+            }
+
+            if let Some(parent) = function.get_prototype() {
+                let parent_name: Value = parent.get("name")?;
+
+                if let Some(parent_name) = parent_name.as_string() {
+                    let parent_name = parent_name.to_string()?;
+
+                    if !parent_name.is_empty() {
+                        base.push_str(&format!(" extends {}", parent_name));
+                    }
+                }
+            }
+
+            base.push(']');
+
+            return Ok(base);
+        }
+
+        let kind = constructor
+            .as_deref()
+            .filter(|constructor| {
+                matches!(
+                    *constructor,
+                    "AsyncFunction" | "GeneratorFunction" | "AsyncGeneratorFunction"
+                )
+            })
+            .unwrap_or("Function");
+
+        let mut base = format!("[{}", kind);
+
+        if constructor.is_none() {
+            base.push_str(" (null prototype)");
+        }
+
+        if name.is_empty() {
+            base.push_str(" (anonymous)");
+        } else {
+            base.push_str(&format!(": {}", name));
+        }
+
+        base.push(']');
+
+        if let Some(constructor) = constructor {
+            if constructor != kind && constructor != "Function" {
+                base.push_str(&format!(" {}", constructor));
+            }
+        }
+
+        if !tag.is_empty() && constructor.as_deref() != Some(tag) {
+            base.push_str(&format!(" [{}]", tag));
+        }
+
+        Ok(base)
+    }
+
+    fn format_error(&self, error: &Object<'js>) -> QuickJsResult<String> {
+        let name: Value = error.get("name")?;
+        let message: Value = error.get("message")?;
+
+        let name = match name.is_undefined() {
+            true => String::from("Error"),
+
+            false => self.stringify(&name)?,
+        };
+
+        let message = match message.is_undefined() {
+            true => String::new(),
+
+            false => self.stringify(&message)?,
+        };
+
+        let header = match (name.is_empty(), message.is_empty()) {
+            (_, true) => name,
+
+            (true, false) => message,
+
+            (false, false) => format!("{}: {}", name, message),
+        };
+
+        let stack = match error.get::<_, Value>("stack")?.is_string() {
+            true => crate::source_map::get_mapped_stack(&self.ctx, error)?.unwrap_or_default(),
+
+            false => String::new(),
+        };
+
+        let stack = stack.trim_end();
+
+        let result = if stack.is_empty() {
+            format!("[{}]", header)
+        } else {
+            format!("{}\n{}", header, stack)
+        };
+
+        if self.indentation == 0 {
+            return Ok(result);
+        }
+
+        let indentation = format!("\n{}", " ".repeat(self.indentation));
+
+        Ok(result.replace('\n', &indentation))
+    }
+
+    /// Format the items of `array`, collapsing holes, returning the entries, whether they are all
+    /// numbers and whether some were left out
+    fn format_array(
+        &mut self,
+        array: &Array<'js>,
+        level: usize,
+    ) -> QuickJsResult<(Vec<String>, bool, bool)> {
+        let length = array.len();
+
+        let mut output = Vec::new();
+        let mut numeric = true;
+        let mut index = 0;
+
+        while index < length && output.len() < MAX_ARRAY_LENGTH {
+            if !array.as_object().contains_key(index as u32)? {
+                let start = index;
+
+                while index < length && !array.as_object().contains_key(index as u32)? {
+                    index += 1;
+                }
+
+                let holes = index - start;
+
+                let empty = format!("<{} empty item{}>", holes, plural(holes));
+
+                output.push(self.stylize(&empty, "undefined"));
+
+                numeric = false;
+
+                continue;
+            }
+
+            let item: Value = array.get(index)?;
+
+            if !item.is_number() && item.type_of() != Type::BigInt {
+                numeric = false;
+            }
+
+            self.indentation += 2;
+
+            let item = self.format_value(&item, level + 1);
+
+            self.indentation -= 2;
+
+            output.push(item?);
+
+            index += 1;
+        }
+
+        let has_more = index < length;
+
+        if has_more {
+            let remaining = length - index;
+
+            output.push(format!("... {} more item{}", remaining, plural(remaining)));
+        }
+
+        Ok((output, numeric, has_more))
+    }
+
+    fn get_entries(&self, object: &Object<'js>) -> QuickJsResult<Array<'js>> {
+        let array: Object = self.ctx.globals().get("Array")?;
+        let from: Function = array.get("from")?;
+
+        from.call((object.clone(),))
+    }
+
+    fn format_map(&mut self, map: &Object<'js>, level: usize) -> QuickJsResult<Vec<String>> {
+        let entries = self.get_entries(map)?;
+
+        let mut output = Vec::new();
+
+        for (i, entry) in entries.iter::<Array>().enumerate() {
+            if i == MAX_ARRAY_LENGTH {
+                let remaining = entries.len() - i;
+
+                output.push(format!("... {} more item{}", remaining, plural(remaining)));
+
+                break;
+            }
+
+            let entry = entry?;
+
+            let key = self.format_value(&entry.get(0)?, level + 1)?;
+            let value = self.format_value(&entry.get(1)?, level + 1)?;
+
+            output.push(format!("{} => {}", key, value));
+        }
+
+        Ok(output)
+    }
+
+    fn format_set(&mut self, set: &Object<'js>, level: usize) -> QuickJsResult<Vec<String>> {
+        let entries = self.get_entries(set)?;
+
+        let mut output = Vec::new();
+
+        for (i, entry) in entries.iter::<Value>().enumerate() {
+            if i == MAX_ARRAY_LENGTH {
+                let remaining = entries.len() - i;
+
+                output.push(format!("... {} more item{}", remaining, plural(remaining)));
+
+                break;
+            }
+
+            output.push(self.format_value(&entry?, level + 1)?);
+        }
+
+        Ok(output)
+    }
+
+    fn format_property(
+        &mut self,
+        object: &Object<'js>,
+        key: &Value<'js>,
+        level: usize,
+    ) -> QuickJsResult<String> {
+        let descriptor = self.get_descriptor(object, key)?;
+
+        let enumerable = match &descriptor {
+            Some(descriptor) => descriptor
+                .get::<_, Option<bool>>("enumerable")?
+                .unwrap_or(false),
+
+            None => true,
+        };
+
+        let value = match &descriptor {
+            Some(descriptor) if !descriptor.contains_key("value")? => {
+                let getter: Value = descriptor.get("get")?;
+                let setter: Value = descriptor.get("set")?;
+
+                match (getter.is_undefined(), setter.is_undefined()) {
+                    (false, false) => self.stylize("[Getter/Setter]", "special"),
+
+                    (false, true) => self.stylize("[Getter]", "special"),
+
+                    (true, false) => self.stylize("[Setter]", "special"),
+
+                    (true, true) => self.stylize("undefined", "undefined"),
+                }
+            }
+
+            _ => {
+                let value: Value = match &descriptor {
+                    Some(descriptor) => descriptor.get("value")?,
+
+                    None => object.get(key.clone())?,
+                };
+
+                self.indentation += 2;
+
+                let value = self.format_value(&value, level + 1);
+
+                self.indentation -= 2;
+
+                value?
+            }
+        };
+
+        let name = if key.is_symbol() {
+            format!("[{}]", self.stylize(&self.stringify(key)?, "symbol"))
+        } else {
+            let key = self.key_name(key)?;
+
+            if !enumerable {
+                format!("[{}]", key)
+            } else if is_identifier(&key) {
+                key
+            } else {
+                self.stylize(&quote_string(&key), "string")
+            }
+        };
+
+        Ok(format!("{}: {}", name, value))
+    }
+
+    fn is_below_break_length(&self, output: &[String], start: usize, base: &str) -> bool {
+        let mut total_length = output.len() + start;
+
+        if total_length + output.len() > self.options.break_length {
+            return false;
+        }
+
+        for entry in output {
+            total_length += visible_length(entry);
+
+            if total_length > self.options.break_length {
+                return false;
+            }
+        }
+
+        base.is_empty() || !base.contains('\n')
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn reduce_to_single_string(
+        &self,
+        mut output: Vec<String>,
+        base: String,
+        braces: (String, String),
+        extras: Extras,
+        level: usize,
+        pad_start: bool,
+        has_more: bool,
+    ) -> String {
+        let base = if base.is_empty() {
+            base
+        } else {
+            format!("{} ", base)
+        };
+
+        let entries = output.len();
+
+        if extras == Extras::Array && entries > 6 {
+            output = group_array_elements(
+                output,
+                self.indentation,
+                self.options.break_length,
+                has_more,
+                pad_start,
+            );
+        }
+
+        if self.current_depth.saturating_sub(level) < COMPACT && entries == output.len() {
+            let start = output.len()
+                + self.indentation
+                + braces.0.chars().count()
+                + visible_length(&base)
+                + 10;
+
+            if self.is_below_break_length(&output, start, &base) {
+                let joined = output.join(", ");
+
+                if !joined.contains('\n') {
+                    return format!("{}{} {} {}", base, braces.0, joined, braces.1);
+                }
+            }
+        }
+
+        let indentation = format!("\n{}", " ".repeat(self.indentation));
+
+        format!(
+            "{}{}{}  {}{}{}",
+            base,
+            braces.0,
+            indentation,
+            output.join(&format!(",{}  ", indentation)),
+            indentation,
+            braces.1
+        )
+    }
+}
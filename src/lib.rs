@@ -1,8 +1,18 @@
 pub mod cli;
 pub mod console;
+pub mod error;
 pub mod fs;
+pub mod globals;
+pub mod inspect;
+pub mod module;
 pub mod os;
 pub mod path;
 pub mod process;
+pub mod report;
+pub mod resolver;
+pub mod source_map;
+pub mod stdio;
+pub mod timers;
+pub mod util;
 pub mod utils;
 pub mod vm;
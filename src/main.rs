@@ -1,29 +1,74 @@
-use yaso::cli::{Command, CLI};
+use yaso::cli::{get_program_argv, Command, CLI};
+use yaso::error::YasoError;
+use yaso::stdio::write_stderr;
 use yaso::vm::VirtualMachine;
 
-use clap::Parser;
-
-use std::process::exit;
+use std::process::ExitCode;
 
 #[tokio::main]
-pub async fn main() {
-    let cli = CLI::parse();
+pub async fn main() -> ExitCode {
+    let (cli, exec_argv) = CLI::parse_with_exec_argv();
 
     match cli.command {
-        Command::Run { file_path, .. } => {
+        Command::Run {
+            unhandled_rejections,
+            no_node_globals,
+            color,
+            log_format,
+            enable_source_maps,
+            ..
+        } => {
+            let (file_path, args) = cli.command.get_script();
+
             if !file_path.exists() {
                 eprintln!("{}: No such file or directory", file_path.display());
 
-                exit(1);
+                return ExitCode::FAILURE;
             }
 
-            let vm = VirtualMachine::new().await;
+            let vm = VirtualMachine::builder()
+                .unhandled_rejections(unhandled_rejections)
+                .node_globals(!no_node_globals)
+                .color(color)
+                .log_format(log_format)
+                .source_maps(enable_source_maps)
+                .argv(get_program_argv(&file_path, args))
+                .exec_argv(exec_argv)
+                .build()
+                .await;
+
+            let vm = match vm {
+                Ok(vm) => vm,
+
+                Err(err) => {
+                    eprintln!("{}", err);
+
+                    return ExitCode::FAILURE;
+                }
+            };
 
-            vm.init().await;
+            match vm.run_module(&file_path).await {
+                // What the event loop doesn't catch is reported as it happens
+                Ok(()) => {
+                    let _ = vm.idle().await;
+                }
 
-            vm.run_module(&file_path).await;
+                Err(YasoError::Exception(exception)) => {
+                    let _ = write_stderr(exception.report().as_bytes());
+
+                    vm.set_exit_code(1).await;
+                }
+
+                Err(YasoError::Exit(_)) => (),
+
+                Err(err) => {
+                    let _ = write_stderr(format!("{}\n", err).as_bytes());
+
+                    vm.set_exit_code(1).await;
+                }
+            }
 
-            vm.idle().await;
+            ExitCode::from(vm.exit().await as u8)
         }
-    };
+    }
 }
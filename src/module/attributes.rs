@@ -0,0 +1,167 @@
+use super::lexer::{tokenize, Token};
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Appended to specifiers and module names to carry the `type` import attribute, which QuickJS
+/// can't parse, from the importing module to the resolver and the loaders
+const TYPE_MARKER: &str = "?yaso-type=";
+
+/// Split the `type` import attribute off a specifier or module name
+pub fn split_type(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once(TYPE_MARKER) {
+        Some((name, module_type)) => (name, Some(module_type)),
+
+        None => (name, None),
+    }
+}
+
+pub fn with_type(name: &str, module_type: &str) -> String {
+    format!("{}{}{}", name, TYPE_MARKER, module_type)
+}
+
+/// The import attributes of a `with { ... }` clause
+struct Attributes<'a> {
+    module_type: Option<&'a str>,
+    /// Where the clause ends in the source
+    end: usize,
+}
+
+/// Parse the attributes of the `with { ... }` clause whose opening brace is at `index`, or return
+/// `None` for invalid clauses
+fn parse_attributes<'a>(
+    tokens: &[(Token<'a>, Range<usize>)],
+    mut index: usize,
+) -> Result<Option<Attributes<'a>>, String> {
+    let mut module_type = None;
+
+    index += 1;
+
+    loop {
+        match tokens.get(index..) {
+            Some([(Token::Punctuator("}"), span), ..]) => {
+                return Ok(Some(Attributes {
+                    module_type,
+                    end: span.end,
+                }))
+            }
+
+            Some(
+                [(Token::Identifier(key) | Token::String(key), _), (Token::Punctuator(":"), _), (Token::String(value), _), ..],
+            ) => {
+                if *key != "type" {
+                    return Err(format!(
+                        "Import attribute \"{}\" with value \"{}\" is not supported",
+                        key, value
+                    ));
+                }
+
+                module_type = Some(*value);
+
+                index += 3;
+
+                if let Some((Token::Punctuator(","), _)) = tokens.get(index) {
+                    index += 1;
+                }
+            }
+
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Rewrite the `with { type: "..." }` clauses of the imports and re-exports in `source` into
+/// their specifiers, failing with a message for attributes other than `type`
+pub fn rewrite_import_attributes(source: &str) -> Result<Cow<'_, str>, String> {
+    let tokens = tokenize(source);
+
+    let mut rewritten = String::new();
+    let mut copied = 0;
+
+    for index in 0..tokens.len() {
+        let [(Token::String(specifier), span), (Token::Identifier("with"), _), (Token::Punctuator("{"), _), ..] =
+            &tokens[index..]
+        else {
+            continue;
+        };
+
+        let Some(attributes) = parse_attributes(&tokens, index + 2)? else {
+            continue;
+        };
+
+        let quote = &source[span.start..span.start + 1];
+
+        rewritten.push_str(&source[copied..span.start]);
+        rewritten.push_str(quote);
+
+        match attributes.module_type {
+            Some(module_type) => rewritten.push_str(&with_type(specifier, module_type)),
+
+            None => rewritten.push_str(specifier),
+        }
+
+        rewritten.push_str(quote);
+
+        copied = attributes.end;
+    }
+
+    if copied == 0 {
+        return Ok(Cow::Borrowed(source));
+    }
+
+    rewritten.push_str(&source[copied..]);
+
+    Ok(Cow::Owned(rewritten))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_type_attributes_into_specifiers() {
+        let source = r#"import data from "./a.json" with { type: "json" };
+export * from './b.txt' with { 'type': 'text', };
+import "./c.js" with {};
+import d from "./d.js";"#;
+
+        assert_eq!(
+            rewrite_import_attributes(source).unwrap(),
+            r#"import data from "./a.json?yaso-type=json";
+export * from './b.txt?yaso-type=text';
+import "./c.js";
+import d from "./d.js";"#
+        );
+    }
+
+    #[test]
+    fn leaves_sources_without_clauses_alone() {
+        let source = r#"const s = '"./a.json" with { type: "json" }';
+const m = import("./b.json", { with: { type: "json" } });"#;
+
+        assert!(matches!(
+            rewrite_import_attributes(source),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_unsupported_attributes() {
+        assert_eq!(
+            rewrite_import_attributes(
+                r#"import a from "./a.json" with { type: "json", integrity: "sha384-x" };"#
+            )
+            .unwrap_err(),
+            "Import attribute \"integrity\" with value \"sha384-x\" is not supported"
+        );
+    }
+
+    #[test]
+    fn splits_types_off_names() {
+        assert_eq!(
+            split_type(&with_type("./a.json", "json")),
+            ("./a.json", Some("json"))
+        );
+        assert_eq!(split_type("./a.json"), ("./a.json", None));
+    }
+}
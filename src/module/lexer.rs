@@ -0,0 +1,426 @@
+use std::ops::Range;
+
+/// A token of JavaScript source, only as detailed as finding exports, import attributes and
+/// TypeScript types needs
+#[derive(Debug, PartialEq)]
+pub enum Token<'a> {
+    Identifier(&'a str),
+    String(&'a str),
+    /// A part of a template literal up to the start of a substitution or the end of the
+    /// literal, the code of substitutions is split into tokens of its own
+    Template(&'a str),
+    Punctuator(&'a str),
+}
+
+/// The punctuators longer than one character that are told apart, `<` and `>` are kept alone so
+/// type arguments nest the same way brackets do
+const PUNCTUATORS: &[&str] = &[
+    "===", "!==", "...", "=>", "==", "!=", "&&", "||", "??", "?.",
+];
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || byte >= 0x80
+}
+
+/// Whether a `/` following `previous` starts a regular expression rather than being a division
+fn starts_regex(previous: Option<&Token<'_>>) -> bool {
+    match previous {
+        Some(Token::Identifier(keyword)) => {
+            matches!(
+                *keyword,
+                "return" | "typeof" | "case" | "do" | "else" | "in" | "void"
+            )
+        }
+
+        Some(Token::Punctuator(punctuator)) => !matches!(*punctuator, ")" | "]" | "}"),
+
+        Some(Token::Template(part)) => part.ends_with("${"),
+
+        Some(Token::String(_)) => false,
+
+        None => true,
+    }
+}
+
+/// The index right after the part of a template literal starting at `index`, on its opening
+/// backtick or on the brace closing a substitution, and whether a substitution follows it
+fn skip_template(bytes: &[u8], mut index: usize) -> (usize, bool) {
+    index += 1;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+
+            b'`' => return (index + 1, false),
+
+            b'$' if bytes.get(index + 1) == Some(&b'{') => return (index + 2, true),
+
+            _ => index += 1,
+        }
+    }
+
+    (bytes.len(), false)
+}
+
+/// Split `source` into tokens along with where they are, skipping comments and regular
+/// expressions
+pub fn tokenize(source: &str) -> Vec<(Token<'_>, Range<usize>)> {
+    let bytes = source.as_bytes();
+
+    let mut tokens: Vec<(Token, Range<usize>)> = Vec::new();
+    // How deep in braces the code of each template substitution being split is
+    let mut substitutions: Vec<usize> = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let next = bytes.get(index + 1).copied();
+        let start = index;
+
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\r' => index += 1,
+
+            b'/' if next == Some(b'/') => {
+                index = source[index..]
+                    .find('\n')
+                    .map_or(bytes.len(), |end| index + end);
+            }
+
+            b'/' if next == Some(b'*') => {
+                index = source[index + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| index + end + 4);
+            }
+
+            b'/' if starts_regex(tokens.last().map(|(token, _)| token)) => {
+                let mut in_class = false;
+
+                index += 1;
+
+                while index < bytes.len() && bytes[index] != b'\n' {
+                    match bytes[index] {
+                        b'\\' => index += 1,
+
+                        b'[' => in_class = true,
+
+                        b']' => in_class = false,
+
+                        b'/' if !in_class => break,
+
+                        _ => (),
+                    }
+
+                    index += 1;
+                }
+
+                index += 1;
+            }
+
+            b'`' => {
+                let (end, opens) = skip_template(bytes, index);
+
+                index = end;
+
+                if opens {
+                    substitutions.push(0);
+                }
+
+                tokens.push((Token::Template(&source[start..index]), start..index));
+            }
+
+            b'}' if substitutions.last() == Some(&0) => {
+                let (end, opens) = skip_template(bytes, index);
+
+                index = end;
+
+                if !opens {
+                    substitutions.pop();
+                }
+
+                tokens.push((Token::Template(&source[start..index]), start..index));
+            }
+
+            b'\'' | b'"' => {
+                index += 1;
+
+                while index < bytes.len() && bytes[index] != byte && bytes[index] != b'\n' {
+                    index += if bytes[index] == b'\\' { 2 } else { 1 };
+                }
+
+                let end = index.min(bytes.len());
+
+                tokens.push((
+                    Token::String(&source[start + 1..end]),
+                    start..(end + 1).min(bytes.len()),
+                ));
+
+                index = end + 1;
+            }
+
+            byte if is_identifier_byte(byte) => {
+                while index < bytes.len() && is_identifier_byte(bytes[index]) {
+                    index += 1;
+                }
+
+                tokens.push((Token::Identifier(&source[start..index]), start..index));
+            }
+
+            _ => {
+                match (byte, substitutions.last_mut()) {
+                    (b'{', Some(depth)) => *depth += 1,
+
+                    (b'}', Some(depth)) => *depth -= 1,
+
+                    _ => (),
+                }
+
+                let length = PUNCTUATORS
+                    .iter()
+                    .find(|punctuator| source[index..].starts_with(*punctuator))
+                    .map_or(1, |punctuator| punctuator.len());
+
+                index += length;
+
+                tokens.push((Token::Punctuator(&source[start..index]), start..index));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// The names a CommonJS module exports, and the specifiers of the modules it re-exports
+#[derive(Debug, Default)]
+pub struct Exports {
+    pub names: Vec<String>,
+    pub reexports: Vec<String>,
+}
+
+impl Exports {
+    fn add_name(&mut self, name: &str) {
+        if name == "default" || name == "__esModule" {
+            return;
+        }
+
+        if !self.names.iter().any(|existing| existing == name) {
+            self.names.push(name.to_string());
+        }
+    }
+}
+
+/// Find the names a CommonJS module exports without running it, from assignments to `exports`
+/// and `module.exports`, `Object.defineProperty(exports, ...)`, object literals assigned to
+/// `module.exports` and re-exports through `module.exports = require(...)` and `__exportStar`
+pub fn detect_exports(source: &str) -> Exports {
+    let tokens: Vec<Token> = tokenize(source)
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+
+    let mut exports = Exports::default();
+
+    for index in 0..tokens.len() {
+        if let Some((end, is_module)) = match_exports_object(&tokens, index) {
+            detect_assignment(&tokens, end, is_module, &mut exports);
+
+            continue;
+        }
+
+        match &tokens[index..] {
+            [Token::Identifier("Object"), Token::Punctuator("."), Token::Identifier("defineProperty"), Token::Punctuator("("), ..] => {
+                if let Some((end, _)) = match_exports_object(&tokens, index + 4) {
+                    if let [Token::Punctuator(","), Token::String(name), ..] = &tokens[end..] {
+                        exports.add_name(name);
+                    }
+                }
+            }
+
+            [Token::Identifier("__exportStar"), Token::Punctuator("("), Token::Identifier("require"), Token::Punctuator("("), Token::String(specifier), ..] =>
+            {
+                exports.reexports.push(specifier.to_string());
+            }
+
+            _ => (),
+        }
+    }
+
+    exports
+}
+
+/// Match `exports` or `module.exports` at `index`, returning where it ends and which one it is
+fn match_exports_object(tokens: &[Token<'_>], index: usize) -> Option<(usize, bool)> {
+    if index > 0 && tokens[index - 1] == Token::Punctuator(".") {
+        return None;
+    }
+
+    match tokens.get(index..)? {
+        [Token::Identifier("exports"), ..] => Some((index + 1, false)),
+
+        [Token::Identifier("module"), Token::Punctuator("."), Token::Identifier("exports"), ..] => {
+            Some((index + 3, true))
+        }
+
+        _ => None,
+    }
+}
+
+fn detect_assignment(tokens: &[Token<'_>], index: usize, is_module: bool, exports: &mut Exports) {
+    match &tokens[index..] {
+        [Token::Punctuator("."), Token::Identifier(name), Token::Punctuator("="), ..]
+        | [Token::Punctuator("["), Token::String(name), Token::Punctuator("]"), Token::Punctuator("="), ..] => {
+            exports.add_name(name)
+        }
+
+        [Token::Punctuator("="), Token::Punctuator("{"), ..] if is_module => {
+            detect_object_keys(tokens, index + 2, exports)
+        }
+
+        [Token::Punctuator("="), Token::Identifier("require"), Token::Punctuator("("), Token::String(specifier), Token::Punctuator(")"), ..]
+            if is_module =>
+        {
+            exports.reexports.push(specifier.to_string())
+        }
+
+        _ => (),
+    }
+}
+
+/// Add the keys of the object literal starting at `index`, up to its closing brace
+fn detect_object_keys(tokens: &[Token<'_>], mut index: usize, exports: &mut Exports) {
+    while index < tokens.len() {
+        match &tokens[index..] {
+            [Token::Punctuator("}"), ..] => return,
+
+            [Token::Identifier("get" | "set" | "async"), Token::Identifier(name), ..] => {
+                exports.add_name(name)
+            }
+
+            [Token::Identifier(name) | Token::String(name), Token::Punctuator(":" | "," | "}" | "("), ..] => {
+                exports.add_name(name)
+            }
+
+            _ => (),
+        }
+
+        // Skip the value of the property
+        let mut depth = 0;
+
+        while index < tokens.len() {
+            match tokens[index] {
+                Token::Punctuator("(" | "[" | "{") => depth += 1,
+
+                Token::Punctuator(")" | "]") => depth -= 1,
+
+                Token::Punctuator("}") if depth == 0 => return,
+
+                Token::Punctuator("}") => depth -= 1,
+
+                Token::Punctuator(",") if depth == 0 => break,
+
+                _ => (),
+            }
+
+            index += 1;
+        }
+
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_assigned_exports() {
+        let exports = detect_exports(
+            r#"
+            exports.a = 1;
+            module.exports.b = 2;
+            exports['c'] = 3;
+            Object.defineProperty(exports, "d", { get() { return 4; } });
+            exports.a = 5;
+            exports.default = 6;
+            Object.defineProperty(exports, '__esModule', { value: true });
+            other.exports.e = 7;
+            exports.f == 8;
+            "#,
+        );
+
+        assert_eq!(exports.names, ["a", "b", "c", "d"]);
+        assert!(exports.reexports.is_empty());
+    }
+
+    #[test]
+    fn detects_object_literal_keys() {
+        let exports = detect_exports(
+            r#"
+            module.exports = {
+                a,
+                b: call(1, [2, 3]),
+                c() { return { d: 1 }; },
+                get e() { return 1; },
+                async f() {},
+                'g': `h`,
+                i: { j: 1 },
+            };
+            "#,
+        );
+
+        assert_eq!(exports.names, ["a", "b", "c", "e", "f", "g", "i"]);
+    }
+
+    #[test]
+    fn detects_reexports() {
+        let exports = detect_exports(
+            r#"
+            module.exports = require("./a");
+            __exportStar(require('./b'), exports);
+            exports = require("./c");
+            "#,
+        );
+
+        assert_eq!(exports.reexports, ["./a", "./b"]);
+        assert!(exports.names.is_empty());
+    }
+
+    #[test]
+    fn skips_strings_comments_regexes_and_templates() {
+        let exports = detect_exports(
+            r#"
+            const s = "exports.a = 1" + 'exports.b = 2';
+            // exports.c = 3;
+            /* exports.d = 4; */
+            const r = /exports.e = [/]/g;
+            const q = x / y; exports.f = q / 2;
+            const t = `exports.g = ${exports.h = { i: `${1}` }} exports.j = 5`;
+            exports.k = 6;
+            "#,
+        );
+
+        assert_eq!(exports.names, ["f", "h", "k"]);
+    }
+
+    #[test]
+    fn tokenizes_templates_with_substitutions() {
+        let tokens: Vec<Token> = tokenize("`a${ {b: `c${d}`} }e`")
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            [
+                Token::Template("`a${"),
+                Token::Punctuator("{"),
+                Token::Identifier("b"),
+                Token::Punctuator(":"),
+                Token::Template("`c${"),
+                Token::Identifier("d"),
+                Token::Template("}`"),
+                Token::Punctuator("}"),
+                Token::Template("}e`"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use super::attributes::{rewrite_import_attributes, split_type};
+use super::lexer::detect_exports;
+use crate::resolver::{get_base_dir, resolve, REQUIRE};
+use crate::utils::{throw_io_error, throw_type_error};
+
+use rquickjs::loader::{Loader, RawLoader};
+use rquickjs::module::ModuleData;
+use rquickjs::{Ctx, Error, Module, Object, Result as QuickJsResult};
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Load CommonJS files as ES modules whose default export is `module.exports`, with a named
+/// export for each name the file is detected to export
+#[derive(Debug, Default)]
+pub struct CommonJsLoader;
+
+impl Loader for CommonJsLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> QuickJsResult<ModuleData> {
+        if !super::is_commonjs(ctx, Path::new(name))? {
+            return Err(Error::new_loading(name));
+        }
+
+        let mut names = Vec::new();
+
+        collect_exports(ctx, Path::new(name), &mut names, &mut HashSet::new())?;
+
+        Ok(ModuleData::source(name, create_wrapper(ctx, name, &names)?))
+    }
+}
+
+/// Add the names exported by the file at `path` and the CommonJS files it re-exports
+fn collect_exports(
+    ctx: &Ctx<'_>,
+    path: &Path,
+    names: &mut Vec<String>,
+    visited: &mut HashSet<PathBuf>,
+) -> QuickJsResult<()> {
+    if !visited.insert(path.to_path_buf()) {
+        return Ok(());
+    }
+
+    let exports = detect_exports(&super::read_source(ctx, &path.to_string_lossy())?);
+
+    for name in exports.names {
+        if is_identifier_name(&name) && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let base_dir = get_base_dir(ctx, &path.to_string_lossy());
+
+    for specifier in exports.reexports {
+        let Ok(reexport) = resolve(ctx, &base_dir, &specifier, &REQUIRE) else {
+            continue;
+        };
+
+        if super::is_commonjs(ctx, &reexport)? {
+            collect_exports(ctx, &reexport, names, visited)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `name` can be exported as is, without being a string literal
+fn is_identifier_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|char| char.is_ascii_alphabetic() || char == '_' || char == '$')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '$')
+}
+
+/// The source of the ES module standing for a CommonJS file, which is only required once the
+/// module is evaluated
+fn create_wrapper(ctx: &Ctx<'_>, name: &str, names: &[String]) -> QuickJsResult<String> {
+    super::get_state(ctx)?;
+
+    // Everything is on the first line, whose frames are left out of stack traces
+    let mut source = format!(
+        "const exports = {}.load({}); export default exports;",
+        super::STATE_KEY,
+        stringify(ctx, name)?
+    );
+
+    for (index, name) in names.iter().enumerate() {
+        source.push_str(&format!(
+            " const __yaso_export_{0} = exports.{1}; export {{ __yaso_export_{0} as {1} }};",
+            index, name
+        ));
+    }
+
+    Ok(source)
+}
+
+/// Load ES modules from `.js`, `.mjs`, `.ts` and `.mts` files, stripping the types of TypeScript
+/// and rewriting import attributes into something QuickJS can parse
+#[derive(Debug, Default)]
+pub struct EsmLoader;
+
+// Declare the module itself to fill in its `import.meta`
+unsafe impl RawLoader for EsmLoader {
+    unsafe fn raw_load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> QuickJsResult<Module<'js>> {
+        if !matches!(get_extension(name), Some("js" | "mjs" | "ts" | "mts")) {
+            return Err(Error::new_loading(name));
+        }
+
+        let source = std::fs::read_to_string(name).map_err(|err| throw_io_error(ctx, name, err))?;
+
+        let module = ModuleData::source(name, prepare_source(ctx, name, source)?)
+            .unsafe_declare(ctx.clone())?;
+
+        let meta: Object = module.meta()?;
+
+        meta.set("url", format!("file://{}", name))?;
+        meta.set("filename", name)?;
+        meta.set(
+            "dirname",
+            get_base_dir(ctx, name).to_string_lossy().to_string(),
+        )?;
+
+        Ok(module)
+    }
+}
+
+/// Turn the source of the ES module `name` into what QuickJS can parse, stripping its types if
+/// it is TypeScript and rewriting its import attributes
+pub fn prepare_source(ctx: &Ctx<'_>, name: &str, source: String) -> QuickJsResult<String> {
+    let source = super::strip_types(ctx, name, source)?;
+
+    let source = rewrite_import_attributes(&source)
+        .map_err(|message| throw_type_error(ctx, "ERR_IMPORT_ATTRIBUTE_UNSUPPORTED", &message))?;
+
+    Ok(source.into_owned())
+}
+
+/// Load the modules of `.json` files and of files imported with `type: "text"` or
+/// `type: "bytes"`, whose default export is the parsed JSON, a string or a `Uint8Array`
+#[derive(Debug, Default)]
+pub struct DataLoader;
+
+impl Loader for DataLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> QuickJsResult<ModuleData> {
+        let (path, module_type) = split_type(name);
+
+        let source = match module_type {
+            Some("text") => format!("export default {};", stringify(ctx, &read(path)?)?),
+
+            // The bytes are read into the array natively once the module is evaluated
+            Some("bytes") => {
+                super::get_state(ctx)?;
+
+                format!(
+                    "export default {}.loadBytes({});",
+                    super::STATE_KEY,
+                    stringify(ctx, path)?
+                )
+            }
+
+            None if get_extension(path) == Some("json") => {
+                let source = read(path)?;
+
+                super::parse_json(ctx, path, &source)?;
+
+                format!("export default JSON.parse({});", stringify(ctx, &source)?)
+            }
+
+            _ => return Err(Error::new_loading(name)),
+        };
+
+        Ok(ModuleData::source(name, source))
+    }
+}
+
+fn get_extension(name: &str) -> Option<&str> {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+}
+
+fn read(path: &str) -> QuickJsResult<String> {
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Quote `string` as a JavaScript string literal
+fn stringify(ctx: &Ctx<'_>, string: &str) -> QuickJsResult<String> {
+    match ctx.json_stringify(string)? {
+        Some(string) => string.to_string(),
+
+        None => Ok(String::from("\"\"")),
+    }
+}
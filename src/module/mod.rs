@@ -0,0 +1,482 @@
+pub mod attributes;
+mod lexer;
+pub mod loader;
+pub mod typescript;
+
+use crate::resolver::{get_base_dir, get_package_type, resolve, ResolveError, REQUIRE};
+use crate::utils::{
+    eval_script, export_default, throw_error, throw_io_error, throw_syntax_error, throw_type_error,
+};
+
+use rquickjs::function::{Func, This};
+use rquickjs::module::{Declarations, Exports, ModuleDef};
+use rquickjs::{
+    Ctx, Error, Exception, Function, Module, Object, Result as QuickJsResult, TypedArray, Value,
+};
+
+use std::path::Path;
+
+const STATE_KEY: &str = "__yaso_commonjs";
+
+/// The state of the modules, holding the cache `require.cache` exposes, the module `require.main`
+/// refers to and the names of the modules that aren't files
+fn get_state<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Object<'js>> {
+    let globals = ctx.globals();
+
+    if let Some(state) = globals.get::<_, Option<Object>>(STATE_KEY)? {
+        return Ok(state);
+    }
+
+    let state = Object::new(ctx.clone())?;
+
+    state.set("cache", Object::new(ctx.clone())?)?;
+    state.set("load", Func::from(load))?;
+    state.set("loadBytes", Func::from(load_bytes))?;
+
+    globals.prop(STATE_KEY, state.clone())?;
+
+    Ok(state)
+}
+
+/// Read the file at `path` into the `Uint8Array` exported by a `type: "bytes"` import
+fn load_bytes(ctx: Ctx<'_>, path: String) -> QuickJsResult<TypedArray<'_, u8>> {
+    let bytes = std::fs::read(&path).map_err(|err| throw_io_error(&ctx, &path, err))?;
+
+    TypedArray::new(ctx, bytes)
+}
+
+/// Make the module at `path` the one `require.main` refers to, if it is a CommonJS module
+pub fn set_main(ctx: &Ctx<'_>, path: &Path) -> QuickJsResult<()> {
+    let path = std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+
+    get_state(ctx)?.set("mainFilename", path.to_string_lossy().to_string())
+}
+
+/// Set the names of the builtin and embedder modules, which `require` loads by name and
+/// `module.builtinModules` lists
+pub fn set_builtin_modules(ctx: &Ctx<'_>, names: Vec<String>) -> QuickJsResult<()> {
+    get_state(ctx)?.set("builtinModules", names)
+}
+
+fn get_builtin_modules(ctx: &Ctx<'_>) -> QuickJsResult<Vec<String>> {
+    Ok(get_state(ctx)?
+        .get::<_, Option<Vec<String>>>("builtinModules")?
+        .unwrap_or_default())
+}
+
+fn is_builtin(ctx: &Ctx<'_>, name: &str) -> QuickJsResult<bool> {
+    Ok(get_builtin_modules(ctx)?
+        .iter()
+        .any(|module| module == name))
+}
+
+/// The builtin module `name` refers to, with or without the `node:` scheme, if it is one
+pub fn get_builtin_name<'a>(ctx: &Ctx<'_>, name: &'a str) -> QuickJsResult<Option<&'a str>> {
+    let name = name.strip_prefix("node:").unwrap_or(name);
+
+    Ok(is_builtin(ctx, name)?.then_some(name))
+}
+
+/// Whether the file at `filename` runs as a CommonJS module, inside a function whose header
+/// takes up the first line
+pub fn is_wrapped(ctx: &Ctx<'_>, filename: &str) -> QuickJsResult<bool> {
+    Ok(get_state(ctx)?
+        .get::<_, Option<Object>>("wrapped")?
+        .map(|wrapped| wrapped.contains_key(filename))
+        .transpose()?
+        .unwrap_or(false))
+}
+
+fn set_wrapped(ctx: &Ctx<'_>, filename: &str) -> QuickJsResult<()> {
+    let state = get_state(ctx)?;
+
+    let wrapped = match state.get::<_, Option<Object>>("wrapped")? {
+        Some(wrapped) => wrapped,
+
+        None => {
+            let wrapped = Object::new(ctx.clone())?;
+
+            state.set("wrapped", wrapped.clone())?;
+
+            // Frames of wrapped modules are shifted by their header from now on
+            crate::source_map::wrap_error_constructors(ctx)?;
+
+            wrapped
+        }
+    };
+
+    wrapped.set(filename, true)
+}
+
+/// Record that the module `name` was evaluated from a source the host gave, so that it is
+/// imported by its name rather than resolved to a file
+pub fn add_source_module(ctx: &Ctx<'_>, name: &str) -> QuickJsResult<()> {
+    let state = get_state(ctx)?;
+
+    let mut names = state
+        .get::<_, Option<Vec<String>>>("sourceModules")?
+        .unwrap_or_default();
+
+    names.push(name.to_string());
+
+    state.set("sourceModules", names)
+}
+
+pub fn is_source_module(ctx: &Ctx<'_>, name: &str) -> QuickJsResult<bool> {
+    Ok(get_state(ctx)?
+        .get::<_, Option<Vec<String>>>("sourceModules")?
+        .is_some_and(|names| names.iter().any(|module| module == name)))
+}
+
+fn is_typescript(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "ts" || extension == "mts" || extension == "cts")
+}
+
+/// Whether the file at `path` is a CommonJS module, `.cjs` and `.cts` files always are and `.js`
+/// and `.ts` ones are when the closest `package.json` says so, or else when they don't use ES
+/// module syntax
+fn is_commonjs(ctx: &Ctx<'_>, path: &Path) -> QuickJsResult<bool> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("cjs" | "cts") => return Ok(true),
+
+        Some("js" | "ts") | None => (),
+
+        _ => return Ok(false),
+    }
+
+    match get_package_type(ctx, path)?.as_deref() {
+        Some("commonjs") => Ok(true),
+
+        Some("module") => Ok(false),
+
+        _ => {
+            let source = read_source(ctx, &path.to_string_lossy())?;
+
+            if compile(ctx, &path.to_string_lossy(), &source).is_ok() {
+                return Ok(true);
+            }
+
+            // Drop the syntax error, the file is compiled again as an ES module
+            ctx.catch();
+
+            Ok(false)
+        }
+    }
+}
+
+/// Compile the source of a CommonJS module into the function its code runs in
+fn compile<'js>(ctx: &Ctx<'js>, filename: &str, source: &str) -> QuickJsResult<Function<'js>> {
+    let source = match source.strip_prefix("#!") {
+        Some(source) => format!("//{}", source),
+
+        None => source.to_string(),
+    };
+
+    // Keep the header on a line of its own so columns stay the same, stack traces take one
+    // off the line numbers of files that are `is_wrapped`
+    let source = format!(
+        "(function (exports, require, module, __filename, __dirname) {{\n{}\n}})",
+        source
+    );
+
+    let value = eval_script(ctx, &source, filename)?;
+
+    value.into_function().ok_or(Error::Exception)
+}
+
+/// Parse the JSON file at `filename`, prefixing syntax errors with where in the file they are
+fn parse_json<'js>(ctx: &Ctx<'js>, filename: &str, source: &str) -> QuickJsResult<Value<'js>> {
+    match ctx.json_parse(source) {
+        Err(Error::Exception) => (),
+
+        result => return result,
+    }
+
+    let error = ctx.catch();
+
+    let Some(exception) = error.as_exception() else {
+        return Err(ctx.throw(error));
+    };
+
+    let message = exception.message().unwrap_or_default();
+
+    // The stack of the error holds the position as `<input>:line:column`
+    let position = exception.stack().and_then(|stack| {
+        let start = stack.find("<input>:")? + "<input>:".len();
+
+        stack[start..].lines().next().map(str::to_string)
+    });
+
+    let message = match position {
+        Some(position) => format!("{}:{}: {}", filename, position, message),
+
+        None => format!("{}: {}", filename, message),
+    };
+
+    Err(Exception::throw_syntax(ctx, &message))
+}
+
+/// Read the source of the module at `filename`, with the types of TypeScript files stripped
+fn read_source(ctx: &Ctx<'_>, filename: &str) -> QuickJsResult<String> {
+    let source =
+        std::fs::read_to_string(filename).map_err(|err| throw_io_error(ctx, filename, err))?;
+
+    strip_types(ctx, filename, source)
+}
+
+/// Strip the types of `source` when `filename` is a TypeScript file
+fn strip_types(ctx: &Ctx<'_>, filename: &str, source: String) -> QuickJsResult<String> {
+    if !is_typescript(Path::new(filename)) {
+        return Ok(source);
+    }
+
+    typescript::strip_types(&source).map_err(|err| {
+        let (line, column) = err.line_and_column(&source);
+
+        throw_syntax_error(
+            ctx,
+            "ERR_UNSUPPORTED_TYPESCRIPT_SYNTAX",
+            &format!("{}:{}:{}: {}", filename, line, column, err.message),
+        )
+    })
+}
+
+/// Create the `module` object of the file at `filename`
+fn create_module<'js>(
+    ctx: &Ctx<'js>,
+    state: &Object<'js>,
+    filename: &str,
+) -> QuickJsResult<Object<'js>> {
+    let module = Object::new(ctx.clone())?;
+
+    let is_main = state.get::<_, Option<String>>("mainFilename")?.as_deref() == Some(filename);
+
+    module.set("id", if is_main { "." } else { filename })?;
+    module.set("filename", filename)?;
+    module.set(
+        "path",
+        get_base_dir(ctx, filename).to_string_lossy().to_string(),
+    )?;
+    module.set("exports", Object::new(ctx.clone())?)?;
+    module.set("loaded", false)?;
+
+    if is_main {
+        state.set("main", module.clone())?;
+    }
+
+    module.set("require", create_require(ctx, filename)?)?;
+
+    Ok(module)
+}
+
+/// Run the file at `filename` unless it is cached, returning its `module.exports`, ES modules
+/// are imported instead and their namespace returned
+fn load<'js>(ctx: Ctx<'js>, filename: String) -> QuickJsResult<Value<'js>> {
+    let state = get_state(&ctx)?;
+    let cache: Object = state.get("cache")?;
+
+    if let Some(module) = cache.get::<_, Option<Object>>(filename.as_str())? {
+        return module.get("exports");
+    }
+
+    let path = Path::new(&filename);
+
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension == "json");
+
+    if !is_json && !is_commonjs(&ctx, path)? {
+        let namespace: Object = Module::import(&ctx, filename.as_str())?;
+
+        return Ok(namespace.into_value());
+    }
+
+    let source = read_source(&ctx, &filename)?;
+
+    if !is_json {
+        set_wrapped(&ctx, &filename)?;
+    }
+
+    let module = create_module(&ctx, &state, &filename)?;
+
+    // Cache the module before running it, so cyclic requires get its exports so far
+    cache.set(filename.as_str(), module.clone())?;
+
+    let result = if is_json {
+        parse_json(&ctx, &filename, &source).and_then(|exports| module.set("exports", exports))
+    } else {
+        compile(&ctx, &filename, &source).and_then(|function| {
+            let exports: Value = module.get("exports")?;
+            let require: Function = module.get("require")?;
+            let dirname: String = module.get("path")?;
+
+            function.call::<_, ()>((
+                This(exports.clone()),
+                exports,
+                require,
+                module.clone(),
+                filename.as_str(),
+                dirname,
+            ))
+        })
+    };
+
+    if let Err(err) = result {
+        cache.remove(filename.as_str())?;
+
+        return Err(err);
+    }
+
+    module.set("loaded", true)?;
+
+    module.get("exports")
+}
+
+fn get_id(ctx: &Ctx<'_>, id: Value<'_>) -> QuickJsResult<String> {
+    let Some(id) = id.as_string() else {
+        return Err(throw_type_error(
+            ctx,
+            "ERR_INVALID_ARG_TYPE",
+            "The \"id\" argument must be of type string",
+        ));
+    };
+
+    let id = id.to_string()?;
+
+    if id.is_empty() {
+        return Err(throw_type_error(
+            ctx,
+            "ERR_INVALID_ARG_VALUE",
+            "The argument 'id' must be a non-empty string. Received ''",
+        ));
+    }
+
+    Ok(id)
+}
+
+/// Resolve `id` required from the file at `filename` to the path of a file, or the name of a
+/// builtin module
+fn resolve_id(ctx: &Ctx<'_>, filename: &str, id: &str) -> QuickJsResult<String> {
+    if get_builtin_name(ctx, id)?.is_some() {
+        return Ok(id.to_string());
+    }
+
+    match resolve(ctx, &get_base_dir(ctx, filename), id, &REQUIRE) {
+        Ok(path) => Ok(path.to_string_lossy().to_string()),
+
+        Err(ResolveError::NotFound) => Err(throw_error(
+            ctx,
+            "MODULE_NOT_FOUND",
+            &format!(
+                "Cannot find module '{}'\nRequire stack:\n- {}",
+                id, filename
+            ),
+        )),
+
+        Err(ResolveError::NotExported(message)) => {
+            Err(throw_error(ctx, "ERR_PACKAGE_PATH_NOT_EXPORTED", &message))
+        }
+
+        Err(ResolveError::NotImported(message)) => Err(throw_type_error(
+            ctx,
+            "ERR_PACKAGE_IMPORT_NOT_DEFINED",
+            &message,
+        )),
+
+        Err(ResolveError::Js(err)) => Err(err),
+    }
+}
+
+fn require<'js>(ctx: &Ctx<'js>, filename: &str, id: Value<'js>) -> QuickJsResult<Value<'js>> {
+    let id = get_id(ctx, id)?;
+
+    if let Some(name) = get_builtin_name(ctx, &id)? {
+        let namespace: Object = Module::import(ctx, name)?;
+
+        return namespace.get("default");
+    }
+
+    load(ctx.clone(), resolve_id(ctx, filename, &id)?)
+}
+
+/// Create the `require` function of the file at `filename`
+fn create_require<'js>(ctx: &Ctx<'js>, filename: &str) -> QuickJsResult<Function<'js>> {
+    let state = get_state(ctx)?;
+
+    let require = Function::new(ctx.clone(), {
+        let filename = filename.to_string();
+
+        move |ctx: Ctx<'js>, id: Value<'js>| require(&ctx, &filename, id)
+    })?
+    .with_name("require")?;
+
+    let resolve = Function::new(ctx.clone(), {
+        let filename = filename.to_string();
+
+        move |ctx: Ctx<'js>, id: Value<'js>| resolve_id(&ctx, &filename, &get_id(&ctx, id)?)
+    })?
+    .with_name("resolve")?;
+
+    require.set("resolve", resolve)?;
+    require.set("cache", state.get::<_, Object>("cache")?)?;
+    require.set("main", state.get::<_, Value>("main")?)?;
+
+    Ok(require)
+}
+
+/// `createRequire(filename)`, a `require` function resolving relative to `filename`, which can
+/// also be a `file:` URL or a directory ending with a slash
+fn create_require_from<'js>(ctx: Ctx<'js>, filename: Value<'js>) -> QuickJsResult<Function<'js>> {
+    let filename = match filename.as_string() {
+        Some(filename) => filename.to_string()?,
+
+        None => String::new(),
+    };
+
+    let path = filename.strip_prefix("file://").unwrap_or(&filename);
+
+    if !Path::new(path).is_absolute() {
+        return Err(throw_type_error(
+            &ctx,
+            "ERR_INVALID_ARG_VALUE",
+            &format!(
+                "The argument 'filename' must be a file URL object, file URL string, or absolute path string. Received '{}'",
+                filename
+            ),
+        ));
+    }
+
+    if path.ends_with('/') {
+        return create_require(&ctx, &format!("{}noop.js", path));
+    }
+
+    create_require(&ctx, path)
+}
+
+fn is_builtin_module(ctx: Ctx<'_>, name: String) -> QuickJsResult<bool> {
+    Ok(get_builtin_name(&ctx, &name)?.is_some())
+}
+
+pub struct ModuleModule;
+
+impl ModuleDef for ModuleModule {
+    fn declare(declare: &mut Declarations) -> QuickJsResult<()> {
+        declare.declare("builtinModules")?;
+        declare.declare("createRequire")?;
+        declare.declare("isBuiltin")?;
+        declare.declare("default")?;
+
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &mut Exports<'js>) -> QuickJsResult<()> {
+        export_default(ctx, exports, |default| {
+            default.set("builtinModules", get_builtin_modules(ctx)?)?;
+            default.set("createRequire", Func::from(create_require_from))?;
+            default.set("isBuiltin", Func::from(is_builtin_module))?;
+
+            Ok(())
+        })
+    }
+}
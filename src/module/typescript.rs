@@ -0,0 +1,1565 @@
+use super::lexer::{tokenize, Token};
+
+use std::ops::Range;
+
+/// TypeScript syntax that has no JavaScript equivalent once its types are stripped, and where in
+/// the source it is
+#[derive(Debug)]
+pub struct UnsupportedSyntax {
+    pub message: &'static str,
+    pub position: usize,
+}
+
+impl UnsupportedSyntax {
+    /// The line and column, both starting at 1, of the syntax in `source`
+    pub fn line_and_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.position];
+
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .chars()
+            .rev()
+            .take_while(|&char| char != '\n')
+            .count()
+            + 1;
+
+        (line, column)
+    }
+}
+
+/// Turn TypeScript into JavaScript by replacing its types with whitespace, so every line and
+/// column of the result is where it was in `source` and errors point to the original code,
+/// enums are turned into objects in place
+pub fn strip_types(source: &str) -> Result<String, UnsupportedSyntax> {
+    let mut stripper = Stripper::new(source);
+
+    stripper.strip()?;
+
+    Ok(stripper.apply())
+}
+
+/// What the code between a pair of brackets is, which decides what a colon means there
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    /// A block, an object literal or the whole file
+    Block,
+    ClassBody,
+    Params,
+    Other,
+}
+
+struct Context {
+    scope: Scope,
+    /// The index of the token closing the scope
+    close: usize,
+    /// The index of the token to carry on from once the scope is closed
+    resume: usize,
+    /// How many `?` of conditional expressions and `case` clauses wait for their colon
+    pending_colons: u32,
+    /// Whether a `let`, `const` or `var` declaration is going on, so commas start new bindings
+    declaration: bool,
+}
+
+impl Context {
+    fn new(scope: Scope, close: usize) -> Self {
+        Self {
+            scope,
+            close,
+            resume: close + 1,
+            pending_colons: 0,
+            declaration: false,
+        }
+    }
+}
+
+/// Keywords after which an opening parenthesis or angle bracket isn't part of a function
+const KEYWORDS: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "switch",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Class member modifiers that only exist in TypeScript
+const TYPESCRIPT_MODIFIERS: &[&str] = &[
+    "public",
+    "private",
+    "protected",
+    "readonly",
+    "override",
+    "declare",
+    "abstract",
+];
+
+const MODIFIERS: &[&str] = &["static", "accessor", "async", "get", "set"];
+
+struct Stripper<'a> {
+    source: &'a str,
+    tokens: Vec<(Token<'a>, Range<usize>)>,
+    /// The index of the bracket closing each opening bracket
+    matching: Vec<Option<usize>>,
+    /// Replacements of parts of the source, which are blanked out when there is no replacement
+    edits: Vec<(Range<usize>, Option<String>)>,
+    /// The opening parentheses already known to be parameter lists
+    params: Vec<usize>,
+}
+
+impl<'a> Stripper<'a> {
+    fn new(source: &'a str) -> Self {
+        let tokens = tokenize(source);
+
+        let mut matching = vec![None; tokens.len()];
+        let mut open = Vec::new();
+
+        for (index, (token, _)) in tokens.iter().enumerate() {
+            match token {
+                Token::Punctuator("(" | "[" | "{") => open.push(index),
+
+                Token::Punctuator(")" | "]" | "}") => {
+                    if let Some(start) = open.pop() {
+                        matching[start] = Some(index);
+                    }
+                }
+
+                // The parts of a template literal pair up around each substitution
+                Token::Template(part) => {
+                    if part.starts_with('}') {
+                        if let Some(start) = open.pop() {
+                            matching[start] = Some(index);
+                        }
+                    }
+
+                    if part.ends_with("${") {
+                        open.push(index);
+                    }
+                }
+
+                _ => (),
+            }
+        }
+
+        Self {
+            source,
+            tokens,
+            matching,
+            edits: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    fn token(&self, index: usize) -> Option<&Token<'a>> {
+        self.tokens.get(index).map(|(token, _)| token)
+    }
+
+    fn is(&self, index: usize, punctuator: &str) -> bool {
+        self.token(index) == Some(&Token::Punctuator(punctuator))
+    }
+
+    fn is_identifier(&self, index: usize, name: &str) -> bool {
+        self.token(index) == Some(&Token::Identifier(name))
+    }
+
+    fn is_name(&self, index: usize) -> bool {
+        matches!(self.token(index), Some(Token::Identifier(name)) if !KEYWORDS.contains(name))
+    }
+
+    fn start(&self, index: usize) -> usize {
+        self.tokens
+            .get(index)
+            .map_or(self.source.len(), |(_, span)| span.start)
+    }
+
+    fn end(&self, index: usize) -> usize {
+        self.tokens[index].1.end
+    }
+
+    fn newline_before(&self, index: usize) -> bool {
+        index > 0
+            && index < self.tokens.len()
+            && self.source[self.end(index - 1)..self.start(index)].contains('\n')
+    }
+
+    /// The index after the bracket matching the one at `index`
+    fn after_matching(&self, index: usize) -> usize {
+        self.matching[index].map_or(self.tokens.len(), |close| close + 1)
+    }
+
+    /// The index after the template literal whose first part is at `index`, along with its
+    /// substitutions
+    fn after_template(&self, mut index: usize) -> usize {
+        while matches!(self.token(index), Some(Token::Template(part)) if part.ends_with("${")) {
+            index = self.matching[index].unwrap_or(self.tokens.len());
+        }
+
+        index + 1
+    }
+
+    /// Blank out the tokens from `start` up to but excluding `end`
+    fn blank(&mut self, start: usize, end: usize) {
+        if start < end {
+            let range = self.start(start)..self.end(end - 1);
+
+            self.edits.push((range, None));
+        }
+    }
+
+    fn replace(&mut self, start: usize, end: usize, replacement: String) {
+        let range = self.start(start)..self.end(end - 1);
+
+        self.edits.push((range, Some(replacement)));
+    }
+
+    fn unsupported(&self, index: usize, message: &'static str) -> UnsupportedSyntax {
+        UnsupportedSyntax {
+            message,
+            position: self.start(index),
+        }
+    }
+
+    /// The index after the angle brackets starting at `index`
+    fn skip_angle(&self, mut index: usize) -> usize {
+        let mut depth = 0;
+
+        while index < self.tokens.len() {
+            match self.token(index) {
+                Some(Token::Punctuator("<")) => depth += 1,
+
+                Some(Token::Punctuator(">")) => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return index + 1;
+                    }
+                }
+
+                Some(Token::Punctuator("(" | "[" | "{")) => {
+                    index = self.after_matching(index);
+
+                    continue;
+                }
+
+                Some(Token::Template(_)) => {
+                    index = self.after_template(index);
+
+                    continue;
+                }
+
+                _ => (),
+            }
+
+            index += 1;
+        }
+
+        index
+    }
+
+    /// The index after the type arguments starting at `index`, if they are followed by the
+    /// arguments of a call, like `f<T>(x)` but unlike `a < b || c > (d)`
+    fn skip_type_arguments(&self, mut index: usize) -> Option<usize> {
+        let mut depth = 0;
+
+        while index < self.tokens.len() {
+            match self.token(index)? {
+                Token::Punctuator("<") => depth += 1,
+
+                Token::Punctuator(">") => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return self.is(index + 1, "(").then_some(index + 1);
+                    }
+                }
+
+                Token::Punctuator("(" | "[" | "{") => {
+                    index = self.after_matching(index);
+
+                    continue;
+                }
+
+                Token::Template(_) => {
+                    index = self.after_template(index);
+
+                    continue;
+                }
+
+                Token::Punctuator("," | "." | "|" | "&" | ":" | "?" | "=>" | "-")
+                | Token::Identifier(_)
+                | Token::String(_) => (),
+
+                _ => return None,
+            }
+
+            index += 1;
+        }
+
+        None
+    }
+
+    /// The index after the type starting at `index`
+    fn skip_type(&self, mut index: usize, conditional: bool) -> usize {
+        if self.is(index, "|") || self.is(index, "&") {
+            index += 1;
+        }
+
+        loop {
+            index = self.skip_type_operand(index);
+
+            if self.is(index, "|") || self.is(index, "&") {
+                index += 1;
+
+                continue;
+            }
+
+            if conditional && self.is_identifier(index, "extends") && !self.newline_before(index) {
+                index = self.skip_type(index + 1, false);
+
+                if self.is(index, "?") {
+                    index = self.skip_type(index + 1, true);
+
+                    if self.is(index, ":") {
+                        index = self.skip_type(index + 1, true);
+                    }
+                }
+            }
+
+            return index;
+        }
+    }
+
+    fn skip_type_operand(&self, mut index: usize) -> usize {
+        while let Some(Token::Identifier(
+            "keyof" | "typeof" | "readonly" | "unique" | "infer" | "asserts" | "abstract" | "new",
+        )) = self.token(index)
+        {
+            if !self.is_type_start(index + 1) {
+                break;
+            }
+
+            index += 1;
+        }
+
+        match self.token(index) {
+            Some(Token::Punctuator("<")) => {
+                index = self.skip_angle(index);
+
+                if self.is(index, "(") {
+                    index = self.after_matching(index);
+                }
+
+                if self.is(index, "=>") {
+                    return self.skip_type(index + 1, true);
+                }
+            }
+
+            Some(Token::Punctuator("(")) => {
+                index = self.after_matching(index);
+
+                if self.is(index, "=>") {
+                    return self.skip_type(index + 1, true);
+                }
+            }
+
+            Some(Token::Punctuator("{" | "[")) => index = self.after_matching(index),
+
+            Some(Token::Punctuator("-")) => index += 2,
+
+            Some(Token::String(_)) => index += 1,
+
+            Some(Token::Template(_)) => index = self.after_template(index),
+
+            Some(Token::Identifier(_)) => {
+                index += 1;
+
+                while self.is(index, ".")
+                    && matches!(self.token(index + 1), Some(Token::Identifier(_)))
+                {
+                    index += 2;
+                }
+
+                if self.is(index, "(") && self.is_identifier(index - 1, "import") {
+                    index = self.after_matching(index);
+
+                    return self.skip_type_operand_postfix(index);
+                }
+
+                if self.is(index, "<") && !self.newline_before(index) {
+                    index = self.skip_angle(index);
+                }
+
+                if self.is_identifier(index, "is") && !self.newline_before(index) {
+                    return self.skip_type(index + 1, true);
+                }
+            }
+
+            _ => return index,
+        }
+
+        self.skip_type_operand_postfix(index)
+    }
+
+    /// Skip the array types and indexed accesses following a type
+    fn skip_type_operand_postfix(&self, mut index: usize) -> usize {
+        while self.is(index, "[") && !self.newline_before(index) {
+            index = self.after_matching(index);
+        }
+
+        if self.is(index, ".") && matches!(self.token(index + 1), Some(Token::Identifier(_))) {
+            return self.skip_type_operand(index + 1);
+        }
+
+        index
+    }
+
+    fn is_type_start(&self, index: usize) -> bool {
+        matches!(
+            self.token(index),
+            Some(
+                Token::Identifier(_)
+                    | Token::String(_)
+                    | Token::Template(_)
+                    | Token::Punctuator("(" | "[" | "{" | "<" | "-")
+            )
+        )
+    }
+
+    /// Whether the token before `index` ends an expression, so what follows can't start one
+    fn ends_expression(&self, index: usize) -> bool {
+        index > 0
+            && match self.token(index - 1) {
+                Some(Token::Identifier(name)) => !KEYWORDS.contains(name) || *name == "this",
+
+                Some(Token::String(_)) => true,
+
+                Some(Token::Template(part)) => !part.ends_with("${"),
+
+                Some(Token::Punctuator(punctuator)) => matches!(*punctuator, ")" | "]" | "}"),
+
+                None => false,
+            }
+    }
+
+    fn is_statement_start(&self, index: usize) -> bool {
+        index == 0
+            || self.newline_before(index)
+            || matches!(
+                self.token(index - 1),
+                Some(Token::Punctuator(";" | "{" | "}"))
+            )
+    }
+
+    /// The index after the optional semicolon at `index`
+    fn skip_semicolon(&self, index: usize) -> usize {
+        if self.is(index, ";") {
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    /// The index after a statement without a body, ending with a semicolon or a line break
+    fn skip_statement(&self, mut index: usize) -> usize {
+        let start = index;
+
+        while index < self.tokens.len() {
+            match self.token(index) {
+                Some(Token::Punctuator(";")) => return index + 1,
+
+                Some(Token::Punctuator("}")) => return index,
+
+                _ if index > start && self.newline_before(index) && !self.continues(index) => {
+                    return index
+                }
+
+                Some(Token::Punctuator("(" | "[" | "{")) => index = self.after_matching(index),
+
+                Some(Token::Template(_)) => index = self.after_template(index),
+
+                _ => index += 1,
+            }
+        }
+
+        index
+    }
+
+    /// Whether the statement before `index` goes on at `index` although a line break is between
+    fn continues(&self, index: usize) -> bool {
+        let continuations = ["|", "&", ".", ",", ":", "=", "=>", "?", "<", ">"];
+
+        continuations
+            .iter()
+            .any(|punctuator| self.is(index, punctuator))
+            || continuations
+                .iter()
+                .any(|punctuator| self.is(index - 1, punctuator))
+    }
+
+    fn strip(&mut self) -> Result<(), UnsupportedSyntax> {
+        let mut stack = vec![Context::new(Scope::Block, usize::MAX - 1)];
+        let mut index = 0;
+
+        while index < self.tokens.len() {
+            let context = stack.last_mut().unwrap();
+
+            if index == context.close {
+                index = context.resume;
+
+                stack.pop();
+
+                continue;
+            }
+
+            match context.scope {
+                Scope::Block if self.is_statement_start(index) => {
+                    if index > 0 && !self.is(index - 1, ",") {
+                        context.declaration = false;
+                    }
+
+                    if let Some(next) = self.statement(index)? {
+                        index = next;
+
+                        continue;
+                    }
+                }
+
+                Scope::ClassBody if self.is_statement_start(index) => {
+                    if let Some(next) = self.member(index, &mut stack)? {
+                        index = next;
+
+                        continue;
+                    }
+                }
+
+                _ => (),
+            }
+
+            index = self.expression(index, &mut stack)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle the declarations at the start of a statement that only exist in TypeScript,
+    /// returning where to carry on if there is one at `start`
+    fn statement(&mut self, start: usize) -> Result<Option<usize>, UnsupportedSyntax> {
+        let mut index = start;
+
+        let export = self.is_identifier(index, "export");
+        let mut default = false;
+
+        if export {
+            index += 1;
+
+            default = self.is_identifier(index, "default");
+
+            if default {
+                index += 1;
+            }
+        }
+
+        let next_is_name = matches!(self.token(index + 1), Some(Token::Identifier(_)))
+            && !self.newline_before(index + 1);
+
+        match self.token(index) {
+            Some(Token::Identifier("import")) if !export => self.import(start),
+
+            Some(Token::Punctuator("{" | "*")) if export && !default => {
+                Ok(Some(self.specifiers(index)))
+            }
+
+            Some(Token::Punctuator("=")) if export => {
+                Err(self.unsupported(start, "TypeScript 'export =' is not supported"))
+            }
+
+            Some(Token::Identifier("as")) if export => {
+                let end = self.skip_statement(index);
+
+                self.blank(start, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier("type"))
+                if export && (self.is(index + 1, "{") || self.is(index + 1, "*")) =>
+            {
+                let end = self.specifiers(index + 1);
+
+                self.blank(start, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier("type"))
+                if next_is_name && (self.is(index + 2, "=") || self.is(index + 2, "<")) =>
+            {
+                let mut end = index + 2;
+
+                if self.is(end, "<") {
+                    end = self.skip_angle(end);
+                }
+
+                end = self.skip_semicolon(self.skip_type(end + 1, true));
+
+                self.blank(start, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier("interface")) if next_is_name => {
+                let mut end = index + 2;
+
+                while end < self.tokens.len() && !self.is(end, "{") {
+                    end = if self.is(end, "<") {
+                        self.skip_angle(end)
+                    } else {
+                        end + 1
+                    };
+                }
+
+                end = self.after_matching(end);
+
+                self.blank(start, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier("declare")) if next_is_name => {
+                let end = self.declared_end(index + 1);
+
+                self.blank(start, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier("abstract")) if self.is_identifier(index + 1, "class") => {
+                self.blank(index, index + 1);
+
+                Ok(Some(index + 1))
+            }
+
+            Some(Token::Identifier("enum")) if next_is_name => {
+                self.enumeration(start, index, export)
+            }
+
+            Some(Token::Identifier("const"))
+                if self.is_identifier(index + 1, "enum") && !self.newline_before(index + 1) =>
+            {
+                self.enumeration(start, index + 1, export)
+            }
+
+            Some(Token::Identifier("namespace" | "module"))
+                if !self.newline_before(index + 1)
+                    && matches!(
+                        self.token(index + 1),
+                        Some(Token::Identifier(_) | Token::String(_))
+                    )
+                    && (self.is(index + 2, "{") || self.is(index + 2, ".")) =>
+            {
+                Err(self.unsupported(index, "TypeScript namespaces are not supported"))
+            }
+
+            Some(Token::Identifier("function")) => Ok(self.overload(start, index)),
+
+            Some(Token::Identifier("async")) if self.is_identifier(index + 1, "function") => {
+                Ok(self.overload(start, index + 1))
+            }
+
+            _ if export => {
+                // What is exported can still be a declaration to handle
+                let next = self.statement(index)?;
+
+                Ok(Some(next.unwrap_or(index)))
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    /// The index after what `declare` at `index - 1` declares
+    fn declared_end(&self, index: usize) -> usize {
+        let has_body = matches!(
+            self.token(index),
+            Some(Token::Identifier(
+                "class" | "module" | "namespace" | "global" | "enum" | "abstract" | "interface"
+            ))
+        ) || self.is_identifier(index + 1, "enum");
+
+        if !has_body {
+            return self.skip_statement(index);
+        }
+
+        let mut end = index;
+
+        while end < self.tokens.len() && !self.is(end, "{") && !self.is(end, ";") {
+            end = if self.is(end, "<") {
+                self.skip_angle(end)
+            } else {
+                end + 1
+            };
+        }
+
+        if self.is(end, ";") {
+            return end + 1;
+        }
+
+        self.after_matching(end)
+    }
+
+    /// Blank out the overload signature of the function declared at `function`, which has no
+    /// body
+    fn overload(&mut self, start: usize, function: usize) -> Option<usize> {
+        let mut index = function + 1;
+
+        if self.is(index, "*") {
+            index += 1;
+        }
+
+        if matches!(self.token(index), Some(Token::Identifier(_))) {
+            index += 1;
+        }
+
+        if self.is(index, "<") {
+            index = self.skip_angle(index);
+        }
+
+        if !self.is(index, "(") {
+            return None;
+        }
+
+        index = self.after_matching(index);
+
+        if self.is(index, ":") {
+            index = self.skip_type(index + 1, true);
+        }
+
+        if self.is(index, "{") {
+            return None;
+        }
+
+        let end = self.skip_semicolon(index);
+
+        self.blank(start, end);
+
+        Some(end)
+    }
+
+    /// Blank out the type-only imports of the import declaration at `start`
+    fn import(&mut self, start: usize) -> Result<Option<usize>, UnsupportedSyntax> {
+        let index = start + 1;
+
+        if self.is(index, "(") || self.is(index, ".") {
+            return Ok(None);
+        }
+
+        if self.is_identifier(index, "type")
+            && !self.is_identifier(index + 1, "from")
+            && !self.is(index + 1, ",")
+        {
+            let end = self.specifiers(index + 1);
+
+            self.blank(start, end);
+
+            return Ok(Some(end));
+        }
+
+        if self.is(index + 1, "=") || self.is(index + 2, "=") {
+            return Err(self.unsupported(start, "TypeScript 'import =' is not supported"));
+        }
+
+        Ok(Some(self.specifiers(index)))
+    }
+
+    /// Blank out the type-only specifiers of the import or export clause at `index`, returning
+    /// the index after the declaration
+    fn specifiers(&mut self, mut index: usize) -> usize {
+        while index < self.tokens.len() {
+            match self.token(index) {
+                Some(Token::Punctuator("{")) => {
+                    let close = self.matching[index].unwrap_or(self.tokens.len());
+
+                    let mut specifier = index + 1;
+
+                    while specifier < close {
+                        let mut end = specifier;
+
+                        while end < close && !self.is(end, ",") {
+                            end += 1;
+                        }
+
+                        let is_type = self.is_identifier(specifier, "type")
+                            && end > specifier + 1
+                            && !self.is_identifier(specifier + 1, "as");
+
+                        if end < close {
+                            end += 1;
+                        }
+
+                        if is_type {
+                            self.blank(specifier, end);
+                        }
+
+                        specifier = end;
+                    }
+
+                    index = close + 1;
+
+                    if !self.is_identifier(index, "from") {
+                        return self.skip_semicolon(index);
+                    }
+                }
+
+                Some(Token::String(_)) => {
+                    index += 1;
+
+                    if self.is_identifier(index, "with") && self.is(index + 1, "{") {
+                        index = self.after_matching(index + 1);
+                    }
+
+                    return self.skip_semicolon(index);
+                }
+
+                Some(Token::Punctuator(";")) => return index + 1,
+
+                _ => index += 1,
+            }
+        }
+
+        index
+    }
+
+    /// Turn the enum declared at `enumeration` into an object with a property for each member,
+    /// and one for each numeric value mapping it back to the member's name
+    fn enumeration(
+        &mut self,
+        start: usize,
+        enumeration: usize,
+        export: bool,
+    ) -> Result<Option<usize>, UnsupportedSyntax> {
+        let Some(Token::Identifier(name)) = self.token(enumeration + 1) else {
+            return Ok(None);
+        };
+
+        let name = name.to_string();
+        let open = enumeration + 2;
+
+        let Some(close) = self.is(open, "{").then(|| self.matching[open]).flatten() else {
+            return Err(self.unsupported(enumeration, "Invalid enum declaration"));
+        };
+
+        self.replace(
+            start,
+            open + 1,
+            format!(
+                "{}var {1}; (function ({1}) {{",
+                if export { "export " } else { "" },
+                name
+            ),
+        );
+
+        let mut previous: Option<String> = None;
+        let mut index = open + 1;
+
+        while index < close {
+            let key = match self.token(index) {
+                Some(Token::Identifier(key) | Token::String(key)) => key.to_string(),
+
+                _ => return Err(self.unsupported(index, "Invalid enum member")),
+            };
+
+            let member = index;
+
+            index += 1;
+
+            let mut value = None;
+
+            if self.is(index, "=") {
+                let value_start = index + 1;
+
+                index = value_start;
+
+                while index < close && !self.is(index, ",") {
+                    index = match self.token(index) {
+                        Some(Token::Punctuator("(" | "[" | "{")) => self.after_matching(index),
+
+                        Some(Token::Template(_)) => self.after_template(index),
+
+                        _ => index + 1,
+                    };
+                }
+
+                let is_string = index == self.after_template(value_start)
+                    && matches!(
+                        self.token(value_start),
+                        Some(Token::String(_) | Token::Template(_))
+                    );
+
+                value = Some((
+                    &self.source[self.start(value_start)..self.end(index - 1)],
+                    is_string,
+                ));
+            }
+
+            let code = match value {
+                Some((value, true)) => format!("{}[\"{}\"] = {};", name, key, value),
+
+                Some((value, false)) => {
+                    format!("{0}[{0}[\"{1}\"] = {2}] = \"{1}\";", name, key, value)
+                }
+
+                None => {
+                    let value = match &previous {
+                        Some(previous) => format!("{}[\"{}\"] + 1", name, previous),
+
+                        None => String::from("0"),
+                    };
+
+                    format!("{0}[{0}[\"{1}\"] = {2}] = \"{1}\";", name, key, value)
+                }
+            };
+
+            if self.is(index, ",") {
+                index += 1;
+            }
+
+            self.replace(member, index, code);
+
+            previous = Some(key);
+        }
+
+        self.replace(close, close + 1, format!("}})({0} || ({0} = {{}}));", name));
+
+        Ok(Some(close + 1))
+    }
+
+    /// Handle the class member starting at `start`, returning where to carry on if there is
+    /// one
+    fn member(
+        &mut self,
+        start: usize,
+        stack: &mut Vec<Context>,
+    ) -> Result<Option<usize>, UnsupportedSyntax> {
+        let mut index = start;
+        let mut remove = false;
+
+        if self.is_identifier(index, "static") && self.is(index + 1, "{") {
+            return Ok(None);
+        }
+
+        loop {
+            let is_modifier_followed_by_name = matches!(
+                self.token(index + 1),
+                Some(Token::Identifier(_) | Token::String(_) | Token::Punctuator("[" | "#" | "*"))
+            ) && !self.newline_before(index + 1);
+
+            match self.token(index) {
+                Some(Token::Identifier(modifier))
+                    if TYPESCRIPT_MODIFIERS.contains(modifier) && is_modifier_followed_by_name =>
+                {
+                    remove |= matches!(*modifier, "declare" | "abstract");
+
+                    self.blank(index, index + 1);
+
+                    index += 1;
+                }
+
+                Some(Token::Identifier(modifier))
+                    if MODIFIERS.contains(modifier) && is_modifier_followed_by_name =>
+                {
+                    index += 1
+                }
+
+                Some(Token::Punctuator("*")) => index += 1,
+
+                _ => break,
+            }
+        }
+
+        // Index signatures like `[key: string]: number`
+        if self.is(index, "[")
+            && matches!(self.token(index + 1), Some(Token::Identifier(_)))
+            && self.is(index + 2, ":")
+        {
+            let mut end = self.after_matching(index);
+
+            if self.is(end, ":") {
+                end = self.skip_type(end + 1, true);
+            }
+
+            end = self.skip_semicolon(end);
+
+            self.blank(start, end);
+
+            return Ok(Some(end));
+        }
+
+        match self.token(index) {
+            Some(Token::Identifier(_) | Token::String(_)) => index += 1,
+
+            Some(Token::Punctuator("#")) => index += 2,
+
+            Some(Token::Punctuator("[")) => index = self.after_matching(index),
+
+            _ => return Ok(None),
+        }
+
+        if remove {
+            let end = self.skip_statement(index);
+
+            self.blank(start, end);
+
+            return Ok(Some(end));
+        }
+
+        if self.is(index, "?") || self.is(index, "!") {
+            self.blank(index, index + 1);
+
+            index += 1;
+        }
+
+        if self.is(index, "<") {
+            let end = self.skip_angle(index);
+
+            self.blank(index, end);
+
+            index = end;
+        }
+
+        if self.is(index, "(") {
+            let close = self.matching[index].unwrap_or(self.tokens.len());
+
+            let mut end = close + 1;
+
+            if self.is(end, ":") {
+                end = self.skip_type(end + 1, true);
+            }
+
+            if !self.is(end, "{") {
+                // An overload or an abstract method, without a body
+                end = self.skip_semicolon(end);
+
+                self.blank(start, end);
+
+                return Ok(Some(end));
+            }
+
+            self.blank(close + 1, end);
+
+            let mut context = Context::new(Scope::Params, close);
+
+            context.resume = end;
+
+            stack.push(context);
+
+            return Ok(Some(index + 1));
+        }
+
+        if self.is(index, ":") {
+            let end = self.skip_type(index + 1, true);
+
+            self.blank(index, end);
+
+            return Ok(Some(end));
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Handle the token at `index` inside an expression or a statement, returning the index to
+    /// carry on from
+    fn expression(
+        &mut self,
+        index: usize,
+        stack: &mut Vec<Context>,
+    ) -> Result<usize, UnsupportedSyntax> {
+        let context = stack.last_mut().unwrap();
+
+        if context.scope == Scope::Params {
+            if let Some(next) = self.parameter(index, stack)? {
+                return Ok(next);
+            }
+        }
+
+        let context = stack.last_mut().unwrap();
+
+        // Keywords can be property names
+        if index > 0 && (self.is(index - 1, ".") || self.is(index - 1, "?.")) {
+            return Ok(index + 1);
+        }
+
+        match self.token(index) {
+            Some(Token::Punctuator("(")) => {
+                let close = self.matching[index].unwrap_or(self.tokens.len());
+
+                if self.is_params(index, close, context.pending_colons) {
+                    let mut context = Context::new(Scope::Params, close);
+
+                    if self.is(close + 1, ":") {
+                        context.resume = self.skip_type(close + 2, true);
+
+                        self.blank(close + 1, context.resume);
+                    }
+
+                    stack.push(context);
+                } else {
+                    stack.push(Context::new(Scope::Other, close));
+                }
+            }
+
+            Some(Token::Punctuator("[")) => {
+                let close = self.matching[index].unwrap_or(self.tokens.len());
+
+                stack.push(Context::new(Scope::Other, close));
+            }
+
+            Some(Token::Punctuator("{")) => {
+                let close = self.matching[index].unwrap_or(self.tokens.len());
+
+                stack.push(Context::new(Scope::Block, close));
+            }
+
+            Some(Token::Template(part)) if part.ends_with("${") => {
+                let close = self.matching[index].unwrap_or(self.tokens.len());
+                let mut context = Context::new(Scope::Other, close);
+
+                // The part closing the substitution may open the next one
+                context.resume = close;
+
+                stack.push(context);
+            }
+
+            Some(Token::Punctuator("?")) => context.pending_colons += 1,
+
+            Some(Token::Identifier("case")) => context.pending_colons += 1,
+
+            Some(Token::Punctuator(":")) => {
+                context.pending_colons = context.pending_colons.saturating_sub(1)
+            }
+
+            Some(Token::Punctuator(",")) if context.declaration => {
+                return Ok(self.binding(index + 1));
+            }
+
+            Some(Token::Punctuator(";")) => context.declaration = false,
+
+            Some(Token::Identifier("let" | "const" | "var"))
+                if matches!(
+                    self.token(index + 1),
+                    Some(Token::Identifier(_) | Token::Punctuator("{" | "["))
+                ) =>
+            {
+                context.declaration = true;
+
+                return Ok(self.binding(index + 1));
+            }
+
+            Some(Token::Identifier("class")) => return Ok(self.class(index, stack)),
+
+            Some(Token::Identifier("function")) => return Ok(self.function(index)),
+
+            Some(Token::Identifier("as" | "satisfies"))
+                if self.ends_expression(index)
+                    && !self.newline_before(index)
+                    && self.is_type_start(index + 1) =>
+            {
+                let end = self.skip_type(index + 1, true);
+
+                self.blank(index, end);
+
+                return Ok(end);
+            }
+
+            Some(Token::Punctuator("!"))
+                if self.ends_expression(index)
+                    && !self.is(index - 1, "}")
+                    && self.end(index - 1) == self.start(index) =>
+            {
+                self.blank(index, index + 1);
+            }
+
+            Some(Token::Punctuator("<")) => {
+                if self.ends_expression(index) {
+                    if let Some(end) = self.skip_type_arguments(index) {
+                        self.blank(index, end);
+
+                        return Ok(end);
+                    }
+                } else {
+                    // Type parameters of an arrow function, or a type assertion
+                    let end = self.skip_angle(index);
+
+                    self.blank(index, end);
+
+                    return Ok(end);
+                }
+            }
+
+            _ => (),
+        }
+
+        Ok(index + 1)
+    }
+
+    fn is_keyword(&self, index: usize) -> bool {
+        matches!(self.token(index), Some(Token::Identifier(name)) if KEYWORDS.contains(name))
+    }
+
+    /// Handle what only TypeScript has at `index` in a parameter list
+    fn parameter(
+        &mut self,
+        index: usize,
+        stack: &mut [Context],
+    ) -> Result<Option<usize>, UnsupportedSyntax> {
+        let context = stack.last_mut().unwrap();
+
+        match self.token(index) {
+            Some(Token::Punctuator("?"))
+                if [":", ",", ")", "="]
+                    .iter()
+                    .any(|next| self.is(index + 1, next)) =>
+            {
+                self.blank(index, index + 1);
+
+                Ok(Some(index + 1))
+            }
+
+            Some(Token::Punctuator(":")) if context.pending_colons == 0 => {
+                let end = self.skip_type(index + 1, true);
+
+                self.blank(index, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier("this"))
+                if self.is(index - 1, "(") && self.is(index + 1, ":") =>
+            {
+                let mut end = self.skip_type(index + 2, true);
+
+                if self.is(end, ",") {
+                    end += 1;
+                }
+
+                self.blank(index, end);
+
+                Ok(Some(end))
+            }
+
+            Some(Token::Identifier(modifier))
+                if TYPESCRIPT_MODIFIERS.contains(modifier)
+                    && matches!(
+                        self.token(index + 1),
+                        Some(Token::Identifier(_) | Token::Punctuator("{" | "["))
+                    ) =>
+            {
+                Err(self.unsupported(index, "TypeScript parameter properties are not supported"))
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether the parenthesis at `index`, closed at `close`, starts the parameters of a
+    /// function
+    fn is_params(&self, index: usize, close: usize, pending_colons: u32) -> bool {
+        if self.params.contains(&index) || (index > 0 && self.is_identifier(index - 1, "catch")) {
+            return true;
+        }
+
+        if self.is(close + 1, "=>") {
+            return true;
+        }
+
+        if index == 0 || self.is_keyword(index - 1) {
+            return false;
+        }
+
+        if self.is(close + 1, ":") && pending_colons == 0 {
+            let end = self.skip_type(close + 2, true);
+
+            return self.is(end, "=>") || self.is(end, "{");
+        }
+
+        // Methods of object literals
+        self.is(close + 1, "{")
+            && !self.newline_before(close + 1)
+            && matches!(
+                self.token(index - 1),
+                Some(Token::Identifier(_) | Token::String(_) | Token::Punctuator("]"))
+            )
+    }
+
+    /// Blank out the type of the binding of a declaration at `index`, returning the index after
+    /// it
+    fn binding(&mut self, mut index: usize) -> usize {
+        match self.token(index) {
+            Some(Token::Identifier(_)) => index += 1,
+
+            Some(Token::Punctuator("{" | "[")) => index = self.after_matching(index),
+
+            _ => return index,
+        }
+
+        if self.is(index, "!") && self.is(index + 1, ":") {
+            self.blank(index, index + 1);
+
+            index += 1;
+        }
+
+        if self.is(index, ":") {
+            let end = self.skip_type(index + 1, true);
+
+            self.blank(index, end);
+
+            index = end;
+        }
+
+        index
+    }
+
+    /// Blank out the type parameters of the function whose keyword is at `index`, and remember
+    /// where its parameters are
+    fn function(&mut self, mut index: usize) -> usize {
+        index += 1;
+
+        if self.is(index, "*") {
+            index += 1;
+        }
+
+        if matches!(self.token(index), Some(Token::Identifier(_))) {
+            index += 1;
+        }
+
+        if self.is(index, "<") {
+            let end = self.skip_angle(index);
+
+            self.blank(index, end);
+
+            index = end;
+        }
+
+        if self.is(index, "(") {
+            self.params.push(index);
+        }
+
+        index
+    }
+
+    /// Blank out the type parameters, type arguments and `implements` clause of the class whose
+    /// keyword is at `index`, then enter its body
+    fn class(&mut self, mut index: usize, stack: &mut Vec<Context>) -> usize {
+        index += 1;
+
+        if self.is_name(index) && !self.is_identifier(index, "implements") {
+            index += 1;
+        }
+
+        if self.is(index, "<") {
+            let end = self.skip_angle(index);
+
+            self.blank(index, end);
+
+            index = end;
+        }
+
+        if self.is_identifier(index, "extends") {
+            index += 1;
+
+            while index < self.tokens.len()
+                && !self.is(index, "{")
+                && !self.is_identifier(index, "implements")
+            {
+                index = match self.token(index) {
+                    Some(Token::Punctuator("<")) => {
+                        let end = self.skip_angle(index);
+
+                        self.blank(index, end);
+
+                        end
+                    }
+
+                    Some(Token::Punctuator("(" | "[")) => self.after_matching(index),
+
+                    _ => index + 1,
+                };
+            }
+        }
+
+        if self.is_identifier(index, "implements") {
+            let start = index;
+
+            while index < self.tokens.len() && !self.is(index, "{") {
+                index = if self.is(index, "<") {
+                    self.skip_angle(index)
+                } else {
+                    index + 1
+                };
+            }
+
+            self.blank(start, index);
+        }
+
+        if let Some(Some(close)) = self.is(index, "{").then(|| self.matching[index]) {
+            stack.push(Context::new(Scope::ClassBody, close));
+
+            return index + 1;
+        }
+
+        index
+    }
+
+    /// The source with the edits made to it
+    fn apply(mut self) -> String {
+        self.edits
+            .sort_by(|(a, _), (b, _)| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        let mut result = String::with_capacity(self.source.len());
+        let mut copied = 0;
+
+        for (range, replacement) in &self.edits {
+            // Edits inside an edit already made, like a modifier of a removed member
+            if range.start < copied {
+                continue;
+            }
+
+            result.push_str(&self.source[copied..range.start]);
+
+            match replacement {
+                Some(replacement) => result.push_str(replacement),
+
+                None => result.extend(self.source[range.clone()].chars().map(|char| {
+                    if char == '\n' || char == '\r' {
+                        char
+                    } else {
+                        ' '
+                    }
+                })),
+            }
+
+            copied = range.end;
+        }
+
+        result.push_str(&self.source[copied..]);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_types;
+
+    fn strip(source: &str) -> String {
+        let stripped = strip_types(source).unwrap();
+
+        assert_eq!(stripped.len(), source.len());
+
+        stripped
+    }
+
+    #[test]
+    fn strips_annotations() {
+        assert_eq!(
+            strip("let a: number = 1;\nfunction f(x: string, y?: T[]): void {}"),
+            "let a         = 1;\nfunction f(x        , y      )       {}"
+        );
+    }
+
+    #[test]
+    fn strips_generics() {
+        assert_eq!(
+            strip("function id<T>(x: T): T { return x; }\nid<string>('a');"),
+            "function id   (x   )    { return x; }\nid        ('a');"
+        );
+
+        assert_eq!(strip("a < b || c > (d)"), "a < b || c > (d)");
+        assert_eq!(strip("(a < b) > (c as T)"), "(a < b) > (c     )");
+    }
+
+    #[test]
+    fn strips_assertions() {
+        assert_eq!(
+            strip("const a = (b as number) + (c satisfies T);\nd!.e;"),
+            "const a = (b          ) + (c            );\nd .e;"
+        );
+    }
+
+    #[test]
+    fn strips_declarations() {
+        assert_eq!(
+            strip("type A = string;\ninterface B {\n  c: A;\n}\nlet d;"),
+            "                \n             \n       \n \nlet d;"
+        );
+    }
+
+    #[test]
+    fn turns_enums_into_objects() {
+        assert_eq!(
+            strip_types("enum E { A, B = 5, C = 'c' }").unwrap(),
+            "var E; (function (E) { E[E[\"A\"] = 0] = \"A\"; E[E[\"B\"] = 5] = \"B\"; \
+             E[\"C\"] = 'c'; })(E || (E = {}));"
+        );
+    }
+
+    #[test]
+    fn strips_template_substitutions() {
+        assert_eq!(
+            strip("`${g<string>('t')}${(1 as number) + 1}`"),
+            "`${g        ('t')}${(1          ) + 1}`"
+        );
+
+        assert_eq!(
+            strip("`a${`b${f<T>(x)}c`}d${ {e: 1}.e }`"),
+            "`a${`b${f   (x)}c`}d${ {e: 1}.e }`"
+        );
+
+        assert_eq!(
+            strip("let a: `x${string}` = `x${y}`;"),
+            "let a               = `x${y}`;"
+        );
+    }
+
+    #[test]
+    fn keeps_positions() {
+        let source =
+            "class A<T> {\n  private b: T;\n  constructor(c: T) {\n    this.b = c as T;\n  }\n}\n";
+        let stripped = strip(source);
+
+        assert_eq!(
+            stripped.match_indices('\n').collect::<Vec<_>>(),
+            source.match_indices('\n').collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            stripped,
+            "class A    {\n          b   ;\n  constructor(c   ) {\n    this.b = c     ;\n  }\n}\n"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::ffi::c_int;
+
+macro_rules! libc_constants {
+    ($($(#[$meta:meta])* $name:ident),* $(,)?) => {
+        &[$($(#[$meta])* (stringify!($name), libc::$name)),*]
+    };
+}
+
+pub const SIGNALS: &[(&str, c_int)] = libc_constants!(
+    SIGHUP,
+    SIGINT,
+    SIGQUIT,
+    SIGILL,
+    SIGTRAP,
+    SIGABRT,
+    SIGIOT,
+    SIGBUS,
+    SIGFPE,
+    SIGKILL,
+    SIGUSR1,
+    SIGSEGV,
+    SIGUSR2,
+    SIGPIPE,
+    SIGALRM,
+    SIGTERM,
+    #[cfg(target_os = "linux")]
+    SIGSTKFLT,
+    SIGCHLD,
+    SIGCONT,
+    SIGSTOP,
+    SIGTSTP,
+    SIGTTIN,
+    SIGTTOU,
+    SIGURG,
+    SIGXCPU,
+    SIGXFSZ,
+    SIGVTALRM,
+    SIGPROF,
+    SIGWINCH,
+    SIGIO,
+    #[cfg(target_os = "linux")]
+    SIGPOLL,
+    #[cfg(target_os = "linux")]
+    SIGPWR,
+    SIGSYS,
+);
+
+pub const ERRNO: &[(&str, c_int)] = libc_constants!(
+    E2BIG,
+    EACCES,
+    EADDRINUSE,
+    EADDRNOTAVAIL,
+    EAFNOSUPPORT,
+    EAGAIN,
+    EALREADY,
+    EBADF,
+    EBADMSG,
+    EBUSY,
+    ECANCELED,
+    ECHILD,
+    ECONNABORTED,
+    ECONNREFUSED,
+    ECONNRESET,
+    EDEADLK,
+    EDESTADDRREQ,
+    EDOM,
+    EDQUOT,
+    EEXIST,
+    EFAULT,
+    EFBIG,
+    EHOSTUNREACH,
+    EIDRM,
+    EILSEQ,
+    EINPROGRESS,
+    EINTR,
+    EINVAL,
+    EIO,
+    EISCONN,
+    EISDIR,
+    ELOOP,
+    EMFILE,
+    EMLINK,
+    EMSGSIZE,
+    EMULTIHOP,
+    ENAMETOOLONG,
+    ENETDOWN,
+    ENETRESET,
+    ENETUNREACH,
+    ENFILE,
+    ENOBUFS,
+    ENODATA,
+    ENODEV,
+    ENOENT,
+    ENOEXEC,
+    ENOLCK,
+    ENOLINK,
+    ENOMEM,
+    ENOMSG,
+    ENOPROTOOPT,
+    ENOSPC,
+    ENOSR,
+    ENOSTR,
+    ENOSYS,
+    ENOTCONN,
+    ENOTDIR,
+    ENOTEMPTY,
+    ENOTSOCK,
+    ENOTSUP,
+    ENOTTY,
+    ENXIO,
+    EOPNOTSUPP,
+    EOVERFLOW,
+    EPERM,
+    EPIPE,
+    EPROTO,
+    EPROTONOSUPPORT,
+    EPROTOTYPE,
+    ERANGE,
+    EROFS,
+    ESPIPE,
+    ESRCH,
+    ESTALE,
+    ETIME,
+    ETIMEDOUT,
+    ETXTBSY,
+    EWOULDBLOCK,
+    EXDEV,
+);
+
+pub const PRIORITY: &[(&str, c_int)] = &[
+    ("PRIORITY_LOW", 19),
+    ("PRIORITY_BELOW_NORMAL", 10),
+    ("PRIORITY_NORMAL", 0),
+    ("PRIORITY_ABOVE_NORMAL", -7),
+    ("PRIORITY_HIGH", -14),
+    ("PRIORITY_HIGHEST", -20),
+];
+
+/// The name of an errno value, such as `ENOENT`
+pub fn errno_name(errno: c_int) -> Option<&'static str> {
+    ERRNO
+        .iter()
+        .find(|(_, value)| *value == errno)
+        .map(|(name, _)| *name)
+}
@@ -0,0 +1,700 @@
+pub mod constants;
+
+use crate::utils::{export_default, throw_io_error};
+
+use rquickjs::function::{Func, Opt};
+use rquickjs::module::{Declarations, Exports, ModuleDef};
+use rquickjs::{Array, Ctx, Object, Result as QuickJsResult};
+
+use libc::utsname;
+
+use once_cell::sync::Lazy;
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_uint, CStr};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[derive(Debug, Clone)]
+struct Uname {
+    sysname: String,
+    release: String,
+    version: String,
+    machine: String,
+}
+
+impl Uname {
+    pub fn new() -> io::Result<Uname> {
+        let mut name = unsafe { std::mem::zeroed() };
+
+        let result = unsafe { libc::uname(&mut name) };
+
+        if result == 0 {
+            Ok(Uname::from(name))
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+impl From<utsname> for Uname {
+    fn from(x: utsname) -> Self {
+        fn to_cstr(buf: &[c_char]) -> &CStr {
+            unsafe { CStr::from_ptr(buf.as_ptr()) }
+        }
+
+        Uname {
+            sysname: to_cstr(&x.sysname).to_string_lossy().to_string(),
+            release: to_cstr(&x.release).to_string_lossy().to_string(),
+            version: to_cstr(&x.version).to_string_lossy().to_string(),
+            machine: to_cstr(&x.machine).to_string_lossy().to_string(),
+        }
+    }
+}
+
+static OS_INFO: Lazy<(String, String, String, String)> = Lazy::new(|| match Uname::new() {
+    Ok(uname) => (uname.sysname, uname.release, uname.version, uname.machine),
+
+    Err(_) => (
+        String::from("N/A"),
+        String::from("N/A"),
+        String::from("N/A"),
+        String::from("N/A"),
+    ),
+});
+
+fn get_type() -> &'static str {
+    &OS_INFO.0
+}
+
+fn get_release() -> &'static str {
+    &OS_INFO.1
+}
+
+fn get_version() -> &'static str {
+    &OS_INFO.2
+}
+
+fn get_machine() -> &'static str {
+    &OS_INFO.3
+}
+
+fn get_tmp_dir() -> String {
+    std::env::temp_dir().to_string_lossy().to_string()
+}
+
+#[derive(Debug, Clone, Default)]
+struct CpuInfo {
+    model: String,
+    speed: u64,
+    times: CpuTimes,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct CpuTimes {
+    user: u64,
+    nice: u64,
+    sys: u64,
+    idle: u64,
+    irq: u64,
+}
+
+impl CpuTimes {
+    /// Parse the tick counters of a `cpuN` line of /proc/stat into milliseconds
+    fn from_stat_line(line: &str) -> CpuTimes {
+        let clock_ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+
+        let ticks: Vec<u64> = line
+            .split_whitespace()
+            .skip(1)
+            .map(|tick| tick.parse().unwrap_or_default())
+            .collect();
+
+        let to_millis = |i: usize| ticks.get(i).copied().unwrap_or_default() * 1000 / clock_ticks;
+
+        CpuTimes {
+            user: to_millis(0),
+            nice: to_millis(1),
+            sys: to_millis(2),
+            idle: to_millis(3),
+            irq: to_millis(5),
+        }
+    }
+}
+
+fn read_cpu_infos() -> Vec<CpuInfo> {
+    let mut cpus: Vec<CpuInfo> = Vec::new();
+
+    if let Ok(cpuinfo) = std::fs::read_to_string("/proc/cpuinfo") {
+        for line in cpuinfo.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            let value = value.trim();
+
+            match key.trim() {
+                "processor" => cpus.push(CpuInfo::default()),
+
+                "model name" => {
+                    if let Some(cpu) = cpus.last_mut() {
+                        cpu.model = value.to_string();
+                    }
+                }
+
+                "cpu MHz" => {
+                    if let Some(cpu) = cpus.last_mut() {
+                        cpu.speed = value.parse::<f64>().unwrap_or_default() as u64;
+                    }
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    if let Ok(stat) = std::fs::read_to_string("/proc/stat") {
+        let lines = stat
+            .lines()
+            .filter(|line| line.starts_with("cpu") && !line.starts_with("cpu "));
+
+        for (i, line) in lines.enumerate() {
+            if cpus.len() <= i {
+                cpus.push(CpuInfo::default());
+            }
+
+            cpus[i].times = CpuTimes::from_stat_line(line);
+        }
+    }
+
+    cpus
+}
+
+fn get_cpus(ctx: Ctx<'_>) -> QuickJsResult<Vec<Object<'_>>> {
+    let mut result = Vec::new();
+
+    for cpu in read_cpu_infos() {
+        let object = Object::new(ctx.clone())?;
+        let times = Object::new(ctx.clone())?;
+
+        times.set("user", cpu.times.user)?;
+        times.set("nice", cpu.times.nice)?;
+        times.set("sys", cpu.times.sys)?;
+        times.set("idle", cpu.times.idle)?;
+        times.set("irq", cpu.times.irq)?;
+
+        object.set("model", cpu.model)?;
+        object.set("speed", cpu.speed)?;
+        object.set("times", times)?;
+
+        result.push(object);
+    }
+
+    Ok(result)
+}
+
+fn get_available_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map(|parallelism| parallelism.get())
+        .unwrap_or(1)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SysInfo {
+    uptime: u64,
+    loads: [f64; 3],
+    total_memory: u64,
+    free_memory: u64,
+}
+
+#[cfg(target_os = "linux")]
+fn get_sys_info() -> SysInfo {
+    let mut info: libc::sysinfo = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::sysinfo(&mut info) } != 0 {
+        return SysInfo::default();
+    }
+
+    let mem_unit = info.mem_unit.max(1) as u64;
+
+    // Load averages are fixed point numbers with 16 bits of fraction
+    let to_load = |load: libc::c_ulong| load as f64 / 65536.0;
+
+    SysInfo {
+        uptime: info.uptime.max(0) as u64,
+        loads: [
+            to_load(info.loads[0]),
+            to_load(info.loads[1]),
+            to_load(info.loads[2]),
+        ],
+        total_memory: info.totalram as u64 * mem_unit,
+        free_memory: info.freeram as u64 * mem_unit,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_sys_info() -> SysInfo {
+    SysInfo::default()
+}
+
+/// Read a field of /proc/meminfo in bytes
+fn read_meminfo_field(field: &str) -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix(field)?.strip_prefix(':')?;
+
+        let kilobytes: u64 = value.trim().trim_end_matches("kB").trim().parse().ok()?;
+
+        Some(kilobytes * 1024)
+    })
+}
+
+fn get_total_memory() -> u64 {
+    read_meminfo_field("MemTotal").unwrap_or_else(|| get_sys_info().total_memory)
+}
+
+fn get_free_memory() -> u64 {
+    read_meminfo_field("MemAvailable").unwrap_or_else(|| get_sys_info().free_memory)
+}
+
+fn get_load_average() -> Vec<f64> {
+    get_sys_info().loads.to_vec()
+}
+
+fn get_uptime() -> u64 {
+    get_sys_info().uptime
+}
+
+fn get_endianness() -> &'static str {
+    if cfg!(target_endian = "big") {
+        "BE"
+    } else {
+        "LE"
+    }
+}
+
+#[cfg(windows)]
+const EOL: &str = "\r\n";
+#[cfg(not(windows))]
+const EOL: &str = "\n";
+
+#[cfg(windows)]
+const DEV_NULL: &str = "\\\\.\\nul";
+#[cfg(not(windows))]
+const DEV_NULL: &str = "/dev/null";
+
+fn get_hostname(ctx: Ctx<'_>) -> QuickJsResult<String> {
+    let mut buf = [0 as c_char; 256];
+
+    if unsafe { libc::gethostname(buf.as_mut_ptr(), buf.len()) } != 0 {
+        return Err(throw_io_error(
+            &ctx,
+            "Could not get hostname",
+            io::Error::last_os_error(),
+        ));
+    }
+
+    Ok(unsafe { CStr::from_ptr(buf.as_ptr()) }
+        .to_string_lossy()
+        .to_string())
+}
+
+#[derive(Debug, Clone)]
+struct Passwd {
+    uid: u32,
+    gid: u32,
+    username: String,
+    homedir: String,
+    shell: String,
+}
+
+impl Passwd {
+    /// Look up the password database entry of the effective user
+    pub fn current() -> io::Result<Passwd> {
+        let uid = unsafe { libc::geteuid() };
+
+        let mut buf_size = match unsafe { libc::sysconf(libc::_SC_GETPW_R_SIZE_MAX) } {
+            size if size > 0 => size as usize,
+            _ => 1024,
+        };
+
+        loop {
+            let mut buf = vec![0 as c_char; buf_size];
+            let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+            let mut result = std::ptr::null_mut();
+
+            let code = unsafe {
+                libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result)
+            };
+
+            if code == libc::ERANGE {
+                buf_size *= 2;
+
+                continue;
+            }
+
+            if code != 0 {
+                return Err(io::Error::from_raw_os_error(code));
+            }
+
+            if result.is_null() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "no password database entry for the current user",
+                ));
+            }
+
+            fn to_string(ptr: *const c_char) -> String {
+                if ptr.is_null() {
+                    return String::new();
+                }
+
+                unsafe { CStr::from_ptr(ptr) }.to_string_lossy().to_string()
+            }
+
+            return Ok(Passwd {
+                uid: passwd.pw_uid,
+                gid: passwd.pw_gid,
+                username: to_string(passwd.pw_name),
+                homedir: to_string(passwd.pw_dir),
+                shell: to_string(passwd.pw_shell),
+            });
+        }
+    }
+}
+
+fn get_home_dir(ctx: Ctx<'_>) -> QuickJsResult<String> {
+    match crate::process::env_var(&ctx, "HOME")? {
+        Some(home) if !home.is_empty() => Ok(home.to_string_lossy().to_string()),
+
+        _ => Ok(Passwd::current()
+            .map(|passwd| passwd.homedir)
+            .unwrap_or_default()),
+    }
+}
+
+fn get_user_info(ctx: Ctx<'_>) -> QuickJsResult<Object<'_>> {
+    let passwd = match Passwd::current() {
+        Ok(passwd) => passwd,
+
+        Err(err) => return Err(throw_io_error(&ctx, "Could not get user info", err)),
+    };
+
+    let result = Object::new(ctx)?;
+
+    result.set("uid", passwd.uid)?;
+    result.set("gid", passwd.gid)?;
+    result.set("username", passwd.username)?;
+    result.set("homedir", passwd.homedir)?;
+    result.set("shell", passwd.shell)?;
+
+    Ok(result)
+}
+
+#[derive(Debug, Clone)]
+struct NetworkInterface {
+    name: String,
+    address: IpAddr,
+    netmask: IpAddr,
+    internal: bool,
+    scope_id: Option<u32>,
+}
+
+/// Convert a socket address returned by getifaddrs to an IP address, if it is one
+fn to_ip_addr(address: *const libc::sockaddr) -> Option<(IpAddr, Option<u32>)> {
+    if address.is_null() {
+        return None;
+    }
+
+    match unsafe { (*address).sa_family } as c_int {
+        libc::AF_INET => {
+            let address = unsafe { &*(address as *const libc::sockaddr_in) };
+
+            let ip = Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr));
+
+            Some((IpAddr::V4(ip), None))
+        }
+
+        libc::AF_INET6 => {
+            let address = unsafe { &*(address as *const libc::sockaddr_in6) };
+
+            let ip = Ipv6Addr::from(address.sin6_addr.s6_addr);
+
+            Some((IpAddr::V6(ip), Some(address.sin6_scope_id)))
+        }
+
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn to_mac_addr(address: *const libc::sockaddr) -> Option<String> {
+    if address.is_null() || unsafe { (*address).sa_family } as c_int != libc::AF_PACKET {
+        return None;
+    }
+
+    let address = unsafe { &*(address as *const libc::sockaddr_ll) };
+
+    let mac: Vec<String> = address.sll_addr[..6]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Some(mac.join(":"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn to_mac_addr(_address: *const libc::sockaddr) -> Option<String> {
+    None
+}
+
+fn read_network_interfaces() -> io::Result<(Vec<NetworkInterface>, HashMap<String, String>)> {
+    let mut addresses = std::ptr::null_mut();
+
+    if unsafe { libc::getifaddrs(&mut addresses) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut interfaces = Vec::new();
+    let mut macs = HashMap::new();
+
+    let mut current = addresses;
+
+    while !current.is_null() {
+        let entry = unsafe { &*current };
+
+        current = entry.ifa_next;
+
+        let name = unsafe { CStr::from_ptr(entry.ifa_name) }
+            .to_string_lossy()
+            .to_string();
+
+        if let Some(mac) = to_mac_addr(entry.ifa_addr) {
+            macs.insert(name, mac);
+
+            continue;
+        }
+
+        let Some((address, scope_id)) = to_ip_addr(entry.ifa_addr) else {
+            continue;
+        };
+
+        let netmask = match to_ip_addr(entry.ifa_netmask) {
+            Some((netmask, _)) => netmask,
+
+            None if address.is_ipv4() => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+
+            None => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+
+        interfaces.push(NetworkInterface {
+            name,
+            address,
+            netmask,
+            internal: entry.ifa_flags & libc::IFF_LOOPBACK as c_uint != 0,
+            scope_id,
+        });
+    }
+
+    unsafe { libc::freeifaddrs(addresses) };
+
+    Ok((interfaces, macs))
+}
+
+fn get_network_interfaces(ctx: Ctx<'_>) -> QuickJsResult<Object<'_>> {
+    let (interfaces, macs) = match read_network_interfaces() {
+        Ok(result) => result,
+
+        Err(err) => {
+            return Err(throw_io_error(
+                &ctx,
+                "Could not get network interfaces",
+                err,
+            ))
+        }
+    };
+
+    let result = Object::new(ctx.clone())?;
+
+    for interface in interfaces {
+        let addresses = match result.get::<_, Option<Array>>(interface.name.as_str())? {
+            Some(addresses) => addresses,
+
+            None => {
+                let addresses = Array::new(ctx.clone())?;
+
+                result.set(interface.name.as_str(), addresses.clone())?;
+
+                addresses
+            }
+        };
+
+        let prefix_length = match interface.netmask {
+            IpAddr::V4(netmask) => u32::from(netmask).count_ones(),
+
+            IpAddr::V6(netmask) => u128::from(netmask).count_ones(),
+        };
+
+        let mac = macs
+            .get(&interface.name)
+            .cloned()
+            .unwrap_or_else(|| String::from("00:00:00:00:00:00"));
+
+        let object = Object::new(ctx.clone())?;
+
+        object.set("address", interface.address.to_string())?;
+        object.set("netmask", interface.netmask.to_string())?;
+        object.set(
+            "family",
+            if interface.address.is_ipv4() {
+                "IPv4"
+            } else {
+                "IPv6"
+            },
+        )?;
+        object.set("mac", mac)?;
+        object.set("internal", interface.internal)?;
+        object.set("cidr", format!("{}/{}", interface.address, prefix_length))?;
+
+        if let Some(scope_id) = interface.scope_id {
+            object.set("scopeid", scope_id)?;
+        }
+
+        addresses.set(addresses.len(), object)?;
+    }
+
+    Ok(result)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clear_errno() {
+    unsafe { *libc::__errno_location() = 0 };
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn clear_errno() {
+    unsafe { *libc::__error() = 0 };
+}
+
+fn get_priority(ctx: Ctx<'_>, pid: Opt<i32>) -> QuickJsResult<i32> {
+    let pid = pid.0.unwrap_or(0);
+
+    // getpriority can legitimately return -1, so errors are only told apart through errno
+    clear_errno();
+
+    let priority = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t) };
+
+    let err = io::Error::last_os_error();
+
+    if priority == -1 && err.raw_os_error().unwrap_or(0) != 0 {
+        return Err(throw_io_error(&ctx, "Could not get priority", err));
+    }
+
+    Ok(priority)
+}
+
+/// Set the priority of `pid`, or of the current process when only the priority is given
+fn set_priority(ctx: Ctx<'_>, first: i32, second: Opt<i32>) -> QuickJsResult<()> {
+    let (pid, priority) = match second.0 {
+        Some(priority) => (first, priority),
+
+        None => (0, first),
+    };
+
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, priority) } != 0 {
+        return Err(throw_io_error(
+            &ctx,
+            "Could not set priority",
+            io::Error::last_os_error(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn get_constants<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Object<'js>> {
+    fn to_object<'js>(ctx: &Ctx<'js>, table: &[(&str, c_int)]) -> QuickJsResult<Object<'js>> {
+        let object = Object::new(ctx.clone())?;
+
+        for (name, value) in table {
+            object.set(*name, *value)?;
+        }
+
+        Ok(object)
+    }
+
+    let constants = Object::new(ctx.clone())?;
+
+    constants.set("signals", to_object(ctx, constants::SIGNALS)?)?;
+    constants.set("errno", to_object(ctx, constants::ERRNO)?)?;
+    constants.set("priority", to_object(ctx, constants::PRIORITY)?)?;
+
+    Ok(constants)
+}
+
+pub struct OsModule;
+
+impl ModuleDef for OsModule {
+    fn declare(declare: &mut Declarations) -> QuickJsResult<()> {
+        declare.declare("type")?;
+        declare.declare("release")?;
+        declare.declare("version")?;
+        declare.declare("machine")?;
+        declare.declare("arch")?;
+        declare.declare("platform")?;
+        declare.declare("tmpdir")?;
+        declare.declare("cpus")?;
+        declare.declare("availableParallelism")?;
+        declare.declare("totalmem")?;
+        declare.declare("freemem")?;
+        declare.declare("loadavg")?;
+        declare.declare("uptime")?;
+        declare.declare("endianness")?;
+        declare.declare("hostname")?;
+        declare.declare("homedir")?;
+        declare.declare("userInfo")?;
+        declare.declare("networkInterfaces")?;
+        declare.declare("getPriority")?;
+        declare.declare("setPriority")?;
+        declare.declare("EOL")?;
+        declare.declare("devNull")?;
+        declare.declare("constants")?;
+        declare.declare("default")?;
+
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &mut Exports<'js>) -> QuickJsResult<()> {
+        export_default(ctx, exports, |default| {
+            default.set("type", Func::from(get_type))?;
+            default.set("release", Func::from(get_release))?;
+            default.set("version", Func::from(get_version))?;
+            default.set("machine", Func::from(get_machine))?;
+            default.set("arch", Func::from(crate::process::get_arch))?;
+            default.set("platform", Func::from(crate::process::get_platform))?;
+            default.set("tmpdir", Func::from(get_tmp_dir))?;
+            default.set("cpus", Func::from(get_cpus))?;
+            default.set(
+                "availableParallelism",
+                Func::from(get_available_parallelism),
+            )?;
+            default.set("totalmem", Func::from(get_total_memory))?;
+            default.set("freemem", Func::from(get_free_memory))?;
+            default.set("loadavg", Func::from(get_load_average))?;
+            default.set("uptime", Func::from(get_uptime))?;
+            default.set("endianness", Func::from(get_endianness))?;
+            default.set("hostname", Func::from(get_hostname))?;
+            default.set("homedir", Func::from(get_home_dir))?;
+            default.set("userInfo", Func::from(get_user_info))?;
+            default.set("networkInterfaces", Func::from(get_network_interfaces))?;
+            default.set("getPriority", Func::from(get_priority))?;
+            default.set("setPriority", Func::from(set_priority))?;
+            default.set("EOL", EOL)?;
+            default.set("devNull", DEV_NULL)?;
+            default.set("constants", get_constants(ctx)?)?;
+
+            Ok(())
+        })
+    }
+}
@@ -30,7 +30,7 @@ fn split_name_and_extension(file_name: String) -> (String, String) {
     (name, extension)
 }
 
-fn parse(ctx: Ctx<'_>, path: String) -> QuickJsResult<Object> {
+fn parse(ctx: Ctx<'_>, path: String) -> QuickJsResult<Object<'_>> {
     let result = Object::new(ctx)?;
 
     let path_buf = PathBuf::from(path);
@@ -139,14 +139,19 @@ impl ModuleDef for PathModule {
 
             default.set(
                 "resolve",
-                Func::from(|paths: Rest<String>| {
-                    let mut final_path = PathBuf::from(crate::process::cwd());
+                Func::from(|ctx: Ctx<'_>, paths: Rest<String>| {
+                    let mut final_path = PathBuf::new();
 
                     for path in paths.iter() {
                         final_path.push(path);
                     }
 
-                    final_path.to_string_lossy().to_string()
+                    // The working directory is only needed, and can only fail, for relative paths
+                    if !final_path.is_absolute() {
+                        final_path = PathBuf::from(crate::process::cwd(ctx)?).join(final_path);
+                    }
+
+                    QuickJsResult::Ok(final_path.to_string_lossy().to_string())
                 }),
             )?;
 
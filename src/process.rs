@@ -1,16 +1,359 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use crate::utils::export_default;
+use crate::error::YasoError;
+use crate::utils::{export_object, throw_io_error};
 
-use rquickjs::function::Func;
+use rquickjs::convert::Coerced;
+use rquickjs::function::{Constructor, Func, Opt, Rest};
 use rquickjs::module::{Declarations, Exports, ModuleDef};
-use rquickjs::{Ctx, Result as QuickJsResult};
+use rquickjs::object::Property;
+use rquickjs::{
+    qjs, Array, BigInt, Class, Ctx, Exception, Function, Object, Result as QuickJsResult, Value,
+};
 
-pub fn cwd() -> String {
-    std::env::current_dir()
-        .unwrap()
-        .to_string_lossy()
-        .to_string()
+use once_cell::sync::Lazy;
+
+const PROCESS_KEY: &str = "__yaso_process";
+
+const EXIT_STATE_KEY: &str = "__yaso_exit_state";
+
+const ENV_KEY: &str = "__yaso_env";
+
+const WORKING_DIR_KEY: &str = "__yaso_working_dir";
+
+/// The changes a virtual machine made to its environment through `process.env`, laid over the
+/// environment of the process since changing that one isn't safe once threads are running
+#[rquickjs::class]
+#[derive(rquickjs::class::Trace)]
+struct Environment {
+    /// The changed variables by name, `None` for removed ones
+    #[qjs(skip_trace)]
+    changes: BTreeMap<OsString, Option<OsString>>,
+    /// Whether the variables that weren't changed come from the environment of the process
+    #[qjs(skip_trace)]
+    inherit: bool,
+}
+
+impl Environment {
+    fn var(&self, key: &OsStr) -> Option<OsString> {
+        match self.changes.get(key) {
+            Some(value) => value.clone(),
+
+            None if self.inherit => std::env::var_os(key),
+
+            None => None,
+        }
+    }
+
+    fn vars(&self) -> Vec<(OsString, OsString)> {
+        let mut vars: Vec<(OsString, OsString)> = if self.inherit {
+            std::env::vars_os()
+                .filter(|(key, _)| !self.changes.contains_key(key))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        vars.extend(
+            self.changes
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.clone()?))),
+        );
+
+        vars
+    }
+}
+
+/// Whether a virtual machine is shutting down, shared with the interrupt handler of its runtime
+#[derive(Debug, Default)]
+pub struct ExitState {
+    exiting: AtomicBool,
+    /// Set once the `exit` listeners of `process.exit` ran, for the interrupt handler to unwind
+    /// the stack
+    unwinding: AtomicBool,
+    /// The first error nothing caught, which made the virtual machine exit
+    uncaught_error: Mutex<Option<YasoError>>,
+}
+
+impl ExitState {
+    /// Whether `process.exit` was called or the virtual machine started shutting down
+    pub fn is_exiting(&self) -> bool {
+        self.exiting.load(Ordering::SeqCst)
+    }
+
+    /// Whether `process.exit` is unwinding the stack, which interrupts any code still running
+    pub fn is_unwinding(&self) -> bool {
+        self.unwinding.load(Ordering::SeqCst)
+    }
+
+    pub fn take_uncaught_error(&self) -> Option<YasoError> {
+        self.uncaught_error.lock().unwrap().take()
+    }
+}
+
+#[rquickjs::class]
+#[derive(rquickjs::class::Trace)]
+struct ExitStateHandle {
+    #[qjs(skip_trace)]
+    inner: Arc<ExitState>,
+}
+
+/// The working directory a virtual machine was given instead of sharing the one of the process
+#[rquickjs::class]
+#[derive(rquickjs::class::Trace)]
+struct WorkingDirectory {
+    #[qjs(skip_trace)]
+    path: PathBuf,
+}
+
+static START_TIME: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Give the virtual machine running in `ctx` its own working directory, the absolute `path`
+pub fn set_working_dir(ctx: &Ctx<'_>, path: PathBuf) -> QuickJsResult<()> {
+    ctx.globals().prop(
+        WORKING_DIR_KEY,
+        Class::instance(ctx.clone(), WorkingDirectory { path })?,
+    )
+}
+
+fn get_working_dir<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Option<Class<'js, WorkingDirectory>>> {
+    ctx.globals().get(WORKING_DIR_KEY)
+}
+
+/// Make `path` absolute against the working directory of the virtual machine running in `ctx`
+pub fn absolute_path(ctx: &Ctx<'_>, path: &Path) -> PathBuf {
+    match get_working_dir(ctx).ok().flatten() {
+        Some(directory) => crate::resolver::normalize(&directory.borrow().path.join(path)),
+
+        None => std::path::absolute(path).unwrap_or(path.to_path_buf()),
+    }
+}
+
+/// The current working directory, throwing when it was removed
+pub fn cwd(ctx: Ctx<'_>) -> QuickJsResult<String> {
+    if let Some(directory) = get_working_dir(&ctx)? {
+        return Ok(directory.borrow().path.to_string_lossy().to_string());
+    }
+
+    match std::env::current_dir() {
+        Ok(directory) => Ok(directory.to_string_lossy().to_string()),
+
+        Err(err) => Err(throw_io_error(&ctx, "process.cwd failed", err)),
+    }
+}
+
+fn chdir(ctx: Ctx<'_>, directory: String) -> QuickJsResult<()> {
+    let result = match get_working_dir(&ctx)? {
+        Some(working_dir) => {
+            let path = working_dir.borrow().path.join(&directory);
+
+            std::fs::canonicalize(path).and_then(|path| {
+                if !path.is_dir() {
+                    return Err(io::Error::from_raw_os_error(libc::ENOTDIR));
+                }
+
+                working_dir.borrow_mut().path = path;
+
+                Ok(())
+            })
+        }
+
+        None => std::env::set_current_dir(&directory),
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+
+        Err(err) => Err(throw_io_error(
+            &ctx,
+            &format!("Could not change directory to {}", directory),
+            err,
+        )),
+    }
+}
+
+fn get_exec_path() -> String {
+    std::env::current_exe()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn get_pid() -> u32 {
+    std::process::id()
+}
+
+fn get_ppid() -> i32 {
+    unsafe { libc::getppid() }
+}
+
+fn get_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+fn get_euid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+
+fn get_gid() -> u32 {
+    unsafe { libc::getgid() }
+}
+
+fn get_egid() -> u32 {
+    unsafe { libc::getegid() }
+}
+
+fn get_groups(ctx: Ctx<'_>) -> QuickJsResult<Vec<u32>> {
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+
+    let mut groups = vec![0; count.max(0) as usize];
+
+    let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+
+    if count < 0 {
+        return Err(throw_io_error(
+            &ctx,
+            "Could not get groups",
+            io::Error::last_os_error(),
+        ));
+    }
+
+    groups.truncate(count as usize);
+
+    Ok(groups)
+}
+
+pub fn get_elapsed_nanos() -> u128 {
+    START_TIME.elapsed().as_nanos()
+}
+
+/// The current high resolution time as `[seconds, nanoseconds]`, relative to `previous` if given
+fn hrtime(previous: Opt<Vec<f64>>) -> Vec<f64> {
+    let mut nanos = get_elapsed_nanos();
+
+    if let Some(previous) = previous.0 {
+        let seconds = previous.first().copied().unwrap_or_default() as u128;
+        let previous_nanos = previous.get(1).copied().unwrap_or_default() as u128;
+
+        nanos = nanos.saturating_sub(seconds * 1_000_000_000 + previous_nanos);
+    }
+
+    vec![
+        (nanos / 1_000_000_000) as f64,
+        (nanos % 1_000_000_000) as f64,
+    ]
+}
+
+fn hrtime_bigint(ctx: Ctx<'_>) -> QuickJsResult<BigInt<'_>> {
+    BigInt::from_u64(ctx, get_elapsed_nanos() as u64)
+}
+
+/// Seconds elapsed since the process started
+fn uptime() -> f64 {
+    START_TIME.elapsed().as_secs_f64()
+}
+
+fn get_rss() -> u64 {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+
+    std::fs::read_to_string("/proc/self/statm")
+        .ok()
+        .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<u64>().ok())
+        .map(|pages| pages * page_size)
+        .unwrap_or_else(|| get_resource_usage().ru_maxrss.max(0) as u64 * 1024)
+}
+
+fn memory_usage(ctx: Ctx<'_>) -> QuickJsResult<Object<'_>> {
+    let usage = unsafe {
+        let mut usage = std::mem::zeroed();
+
+        qjs::JS_ComputeMemoryUsage(qjs::JS_GetRuntime(ctx.as_raw().as_ptr()), &mut usage);
+
+        usage
+    };
+
+    let result = Object::new(ctx.clone())?;
+
+    result.set("rss", get_rss())?;
+    result.set("heapTotal", usage.malloc_size)?;
+    result.set("heapUsed", usage.memory_used_size)?;
+    result.set("external", usage.binary_object_size)?;
+    result.set("arrayBuffers", usage.binary_object_size)?;
+
+    let quickjs = Object::new(ctx)?;
+
+    quickjs.set("mallocSize", usage.malloc_size)?;
+    quickjs.set("mallocLimit", usage.malloc_limit)?;
+    quickjs.set("mallocCount", usage.malloc_count)?;
+    quickjs.set("memoryUsedSize", usage.memory_used_size)?;
+    quickjs.set("memoryUsedCount", usage.memory_used_count)?;
+    quickjs.set("atomCount", usage.atom_count)?;
+    quickjs.set("atomSize", usage.atom_size)?;
+    quickjs.set("stringCount", usage.str_count)?;
+    quickjs.set("stringSize", usage.str_size)?;
+    quickjs.set("objectCount", usage.obj_count)?;
+    quickjs.set("objectSize", usage.obj_size)?;
+    quickjs.set("propertyCount", usage.prop_count)?;
+    quickjs.set("propertySize", usage.prop_size)?;
+    quickjs.set("shapeCount", usage.shape_count)?;
+    quickjs.set("shapeSize", usage.shape_size)?;
+    quickjs.set("functionCount", usage.js_func_count)?;
+    quickjs.set("functionSize", usage.js_func_size)?;
+    quickjs.set("functionCodeSize", usage.js_func_code_size)?;
+    quickjs.set("nativeFunctionCount", usage.c_func_count)?;
+    quickjs.set("arrayCount", usage.array_count)?;
+    quickjs.set("fastArrayCount", usage.fast_array_count)?;
+    quickjs.set("fastArrayElements", usage.fast_array_elements)?;
+    quickjs.set("binaryObjectCount", usage.binary_object_count)?;
+    quickjs.set("binaryObjectSize", usage.binary_object_size)?;
+
+    result.set("quickjs", quickjs)?;
+
+    Ok(result)
+}
+
+fn get_resource_usage() -> libc::rusage {
+    unsafe {
+        let mut usage = std::mem::zeroed();
+
+        libc::getrusage(libc::RUSAGE_SELF, &mut usage);
+
+        usage
+    }
+}
+
+fn resource_usage(ctx: Ctx<'_>) -> QuickJsResult<Object<'_>> {
+    fn micros(time: libc::timeval) -> i64 {
+        time.tv_sec * 1_000_000 + time.tv_usec
+    }
+
+    let usage = get_resource_usage();
+
+    let result = Object::new(ctx)?;
+
+    result.set("userCPUTime", micros(usage.ru_utime))?;
+    result.set("systemCPUTime", micros(usage.ru_stime))?;
+    result.set("maxRSS", usage.ru_maxrss)?;
+    result.set("sharedMemorySize", usage.ru_ixrss)?;
+    result.set("unsharedDataSize", usage.ru_idrss)?;
+    result.set("unsharedStackSize", usage.ru_isrss)?;
+    result.set("minorPageFault", usage.ru_minflt)?;
+    result.set("majorPageFault", usage.ru_majflt)?;
+    result.set("swappedOut", usage.ru_nswap)?;
+    result.set("fsRead", usage.ru_inblock)?;
+    result.set("fsWrite", usage.ru_oublock)?;
+    result.set("ipcSent", usage.ru_msgsnd)?;
+    result.set("ipcReceived", usage.ru_msgrcv)?;
+    result.set("signalsCount", usage.ru_nsignals)?;
+    result.set("voluntaryContextSwitches", usage.ru_nvcsw)?;
+    result.set("involuntaryContextSwitches", usage.ru_nivcsw)?;
+
+    Ok(result)
 }
 
 pub fn get_arch() -> &'static str {
@@ -21,38 +364,535 @@ pub fn get_platform() -> &'static str {
     std::env::consts::OS
 }
 
+/// Share the exit state of the virtual machine running in `ctx` with the code it runs
+pub fn set_exit_state(ctx: &Ctx<'_>, state: Arc<ExitState>) -> QuickJsResult<()> {
+    ctx.globals().prop(
+        EXIT_STATE_KEY,
+        Class::instance(ctx.clone(), ExitStateHandle { inner: state })?,
+    )
+}
+
+fn get_exit_state(ctx: &Ctx<'_>) -> QuickJsResult<Arc<ExitState>> {
+    match ctx
+        .globals()
+        .get::<_, Option<Class<ExitStateHandle>>>(EXIT_STATE_KEY)?
+    {
+        Some(handle) => Ok(handle.borrow().inner.clone()),
+
+        None => Ok(Arc::default()),
+    }
+}
+
+/// Keep the error nothing caught that made the virtual machine running in `ctx` exit, unless
+/// one already did
+pub fn set_uncaught_error(ctx: &Ctx<'_>, error: YasoError) -> QuickJsResult<()> {
+    let state = get_exit_state(ctx)?;
+
+    let mut uncaught_error = state.uncaught_error.lock().unwrap();
+
+    if uncaught_error.is_none() {
+        *uncaught_error = Some(error);
+    }
+
+    Ok(())
+}
+
+/// Whether `process.exit` was called or the virtual machine running in `ctx` started shutting
+/// down
+pub fn is_exiting(ctx: &Ctx<'_>) -> bool {
+    get_exit_state(ctx).is_ok_and(|state| state.is_exiting())
+}
+
+/// Get the process object of this context, creating it on first use
+pub fn get_process_object<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Object<'js>> {
+    let globals = ctx.globals();
+
+    if let Some(process) = globals.get::<_, Option<Object>>(PROCESS_KEY)? {
+        return Ok(process);
+    }
+
+    let process = create_process_object(ctx)?;
+
+    globals.prop(PROCESS_KEY, process.clone())?;
+
+    Ok(process)
+}
+
+fn create_process_object<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Object<'js>> {
+    let process = Object::new(ctx.clone())?;
+
+    Lazy::force(&START_TIME);
+
+    let argv = vec![get_exec_path()];
+
+    let hrtime = Function::new(ctx.clone(), hrtime)?;
+
+    hrtime.set("bigint", Func::from(hrtime_bigint))?;
+
+    process.prop("_events", Object::new(ctx.clone())?)?;
+    process.prop(
+        "_rejections",
+        Property::from(Array::new(ctx.clone())?).writable(),
+    )?;
+
+    process.set("argv", argv)?;
+    process.set("execArgv", Vec::<String>::new())?;
+    process.set("env", create_env_object(ctx)?)?;
+    process.set("cwd", Func::from(cwd))?;
+    process.set("chdir", Func::from(chdir))?;
+    process.set("execPath", get_exec_path())?;
+    process.set("pid", get_pid())?;
+    process.set("ppid", get_ppid())?;
+    process.set("getuid", Func::from(get_uid))?;
+    process.set("geteuid", Func::from(get_euid))?;
+    process.set("getgid", Func::from(get_gid))?;
+    process.set("getegid", Func::from(get_egid))?;
+    process.set("getgroups", Func::from(get_groups))?;
+    process.set("hrtime", hrtime)?;
+    process.set("uptime", Func::from(uptime))?;
+    process.set("memoryUsage", Func::from(memory_usage))?;
+    process.set("resourceUsage", Func::from(resource_usage))?;
+    process.set("arch", get_arch())?;
+    process.set("platform", get_platform())?;
+    process.set("exitCode", ())?;
+    process.set("exit", Func::from(exit))?;
+    process.set("on", Func::from(on))?;
+    process.set("once", Func::from(once))?;
+    process.set("off", Func::from(off))?;
+    process.set("emit", Func::from(emit))?;
+    process.set(
+        "stdout",
+        crate::stdio::create_stream_object(ctx, libc::STDOUT_FILENO)?,
+    )?;
+    process.set(
+        "stderr",
+        crate::stdio::create_stream_object(ctx, libc::STDERR_FILENO)?,
+    )?;
+
+    Ok(process)
+}
+
+fn get_environment<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Class<'js, Environment>> {
+    let globals = ctx.globals();
+
+    if let Some(environment) = globals.get::<_, Option<Class<Environment>>>(ENV_KEY)? {
+        return Ok(environment);
+    }
+
+    let environment = Class::instance(
+        ctx.clone(),
+        Environment {
+            changes: BTreeMap::new(),
+            inherit: true,
+        },
+    )?;
+
+    globals.prop(ENV_KEY, environment.clone())?;
+
+    Ok(environment)
+}
+
+/// Give the virtual machine running in `ctx` an environment holding only `vars` instead of the
+/// one of the process
+pub fn set_environment(ctx: &Ctx<'_>, vars: Vec<(OsString, OsString)>) -> QuickJsResult<()> {
+    let environment = Environment {
+        changes: vars
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .collect(),
+        inherit: false,
+    };
+
+    ctx.globals()
+        .prop(ENV_KEY, Class::instance(ctx.clone(), environment)?)
+}
+
+/// The environment variable `key` of the virtual machine running in `ctx`, as changed through
+/// `process.env`
+pub fn env_var(ctx: &Ctx<'_>, key: impl AsRef<OsStr>) -> QuickJsResult<Option<OsString>> {
+    Ok(get_environment(ctx)?.borrow().var(key.as_ref()))
+}
+
+/// Every environment variable of the virtual machine running in `ctx`, as changed through
+/// `process.env`, for spawned processes to get with `Command::env_clear` and `Command::envs`
+pub fn effective_env(ctx: &Ctx<'_>) -> QuickJsResult<Vec<(OsString, OsString)>> {
+    Ok(get_environment(ctx)?.borrow().vars())
+}
+
+/// Create a proxy reading from the environment of the virtual machine and writing to the
+/// changes laid over it
+fn create_env_object<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Object<'js>> {
+    let handler = Object::new(ctx.clone())?;
+
+    handler.set("get", Func::from(env_get))?;
+    handler.set("has", Func::from(env_has))?;
+    handler.set("set", Func::from(env_set))?;
+    handler.set("deleteProperty", Func::from(env_delete))?;
+    handler.set("ownKeys", Func::from(env_own_keys))?;
+    handler.set(
+        "getOwnPropertyDescriptor",
+        Func::from(env_get_own_property_descriptor),
+    )?;
+    handler.set("defineProperty", Func::from(env_define_property))?;
+
+    let proxy: Constructor = ctx.globals().get("Proxy")?;
+
+    proxy.construct((Object::new(ctx.clone())?, handler))
+}
+
+fn env_key(key: &Value<'_>) -> QuickJsResult<Option<String>> {
+    match key.as_string() {
+        Some(key) => Ok(Some(key.to_string()?)),
+
+        None => Ok(None),
+    }
+}
+
+fn env_value(ctx: &Ctx<'_>, key: &Value<'_>) -> QuickJsResult<Option<String>> {
+    let Some(key) = env_key(key)? else {
+        return Ok(None);
+    };
+
+    Ok(env_var(ctx, key)?.map(|value| value.to_string_lossy().to_string()))
+}
+
+fn env_get<'js>(
+    ctx: Ctx<'js>,
+    _target: Object<'js>,
+    key: Value<'js>,
+) -> QuickJsResult<Option<String>> {
+    env_value(&ctx, &key)
+}
+
+fn env_has<'js>(ctx: Ctx<'js>, _target: Object<'js>, key: Value<'js>) -> QuickJsResult<bool> {
+    Ok(env_value(&ctx, &key)?.is_some())
+}
+
+fn env_set<'js>(
+    ctx: Ctx<'js>,
+    _target: Object<'js>,
+    key: Value<'js>,
+    value: Coerced<String>,
+) -> QuickJsResult<bool> {
+    let Some(key) = env_key(&key)? else {
+        return Err(Exception::throw_type(
+            &ctx,
+            "Environment variable names must be strings",
+        ));
+    };
+
+    if key.is_empty() || key.contains(['=', '\0']) || value.contains('\0') {
+        return Err(Exception::throw_type(
+            &ctx,
+            &format!("Invalid environment variable: {}", key),
+        ));
+    }
+
+    get_environment(&ctx)?
+        .borrow_mut()
+        .changes
+        .insert(key.into(), Some(value.0.into()));
+
+    Ok(true)
+}
+
+fn env_delete<'js>(ctx: Ctx<'js>, _target: Object<'js>, key: Value<'js>) -> QuickJsResult<bool> {
+    if let Some(key) = env_key(&key)? {
+        get_environment(&ctx)?
+            .borrow_mut()
+            .changes
+            .insert(key.into(), None);
+    }
+
+    Ok(true)
+}
+
+fn env_own_keys<'js>(ctx: Ctx<'js>, _target: Object<'js>) -> QuickJsResult<Vec<String>> {
+    Ok(effective_env(&ctx)?
+        .into_iter()
+        .map(|(key, _)| key.to_string_lossy().to_string())
+        .collect())
+}
+
+fn env_get_own_property_descriptor<'js>(
+    ctx: Ctx<'js>,
+    _target: Object<'js>,
+    key: Value<'js>,
+) -> QuickJsResult<Option<Object<'js>>> {
+    let Some(value) = env_value(&ctx, &key)? else {
+        return Ok(None);
+    };
+
+    let descriptor = Object::new(ctx)?;
+
+    descriptor.set("value", value)?;
+    descriptor.set("writable", true)?;
+    descriptor.set("enumerable", true)?;
+    descriptor.set("configurable", true)?;
+
+    Ok(Some(descriptor))
+}
+
+fn env_define_property<'js>(
+    ctx: Ctx<'js>,
+    target: Object<'js>,
+    key: Value<'js>,
+    descriptor: Object<'js>,
+) -> QuickJsResult<bool> {
+    let value: Coerced<String> = descriptor.get("value")?;
+
+    env_set(ctx, target, key, value)
+}
+
+fn get_listeners<'js>(ctx: &Ctx<'js>, event: &str) -> QuickJsResult<Array<'js>> {
+    let events: Object = get_process_object(ctx)?.get("_events")?;
+
+    match events.get::<_, Option<Array>>(event)? {
+        Some(listeners) => Ok(listeners),
+
+        None => {
+            let listeners = Array::new(ctx.clone())?;
+
+            events.set(event, listeners.clone())?;
+
+            Ok(listeners)
+        }
+    }
+}
+
+fn add_listener<'js>(
+    ctx: &Ctx<'js>,
+    event: String,
+    listener: Function<'js>,
+    once: bool,
+) -> QuickJsResult<Object<'js>> {
+    let listeners = get_listeners(ctx, &event)?;
+
+    let entry = Object::new(ctx.clone())?;
+
+    entry.set("listener", listener)?;
+    entry.set("once", once)?;
+
+    listeners.set(listeners.len(), entry)?;
+
+    get_process_object(ctx)
+}
+
+fn on<'js>(ctx: Ctx<'js>, event: String, listener: Function<'js>) -> QuickJsResult<Object<'js>> {
+    add_listener(&ctx, event, listener, false)
+}
+
+fn once<'js>(ctx: Ctx<'js>, event: String, listener: Function<'js>) -> QuickJsResult<Object<'js>> {
+    add_listener(&ctx, event, listener, true)
+}
+
+fn off<'js>(ctx: Ctx<'js>, event: String, listener: Function<'js>) -> QuickJsResult<Object<'js>> {
+    let listeners = get_listeners(&ctx, &event)?;
+
+    let remaining = Array::new(ctx.clone())?;
+
+    let mut removed = false;
+
+    for entry in listeners.iter::<Object>() {
+        let entry = entry?;
+
+        if !removed && entry.get::<_, Function>("listener")? == listener {
+            removed = true;
+
+            continue;
+        }
+
+        remaining.set(remaining.len(), entry)?;
+    }
+
+    let events: Object = get_process_object(&ctx)?.get("_events")?;
+
+    events.set(event, remaining)?;
+
+    get_process_object(&ctx)
+}
+
+/// Call every listener registered for `event`, returns whether there was any
+pub fn emit<'js>(ctx: Ctx<'js>, event: String, args: Rest<Value<'js>>) -> QuickJsResult<bool> {
+    let listeners = get_listeners(&ctx, &event)?;
+
+    if listeners.is_empty() {
+        return Ok(false);
+    }
+
+    let remaining = Array::new(ctx.clone())?;
+
+    let mut entries = Vec::new();
+
+    for entry in listeners.iter::<Object>() {
+        let entry = entry?;
+
+        if !entry.get::<_, bool>("once")? {
+            remaining.set(remaining.len(), entry.clone())?;
+        }
+
+        entries.push(entry);
+    }
+
+    let events: Object = get_process_object(&ctx)?.get("_events")?;
+
+    events.set(event, remaining)?;
+
+    for entry in entries {
+        let listener: Function = entry.get("listener")?;
+
+        listener.call::<_, Value>((Rest(args.0.clone()),))?;
+    }
+
+    Ok(true)
+}
+
+/// Whether anything listens to `event`
+pub fn has_listeners(ctx: &Ctx<'_>, event: &str) -> QuickJsResult<bool> {
+    Ok(!get_listeners(ctx, event)?.is_empty())
+}
+
+/// Remember a promise rejected without a handler, or forget it once a handler gets attached
+pub fn track_rejection<'js>(
+    ctx: &Ctx<'js>,
+    promise: Value<'js>,
+    reason: Value<'js>,
+    is_handled: bool,
+) -> QuickJsResult<()> {
+    let process = get_process_object(ctx)?;
+
+    let rejections: Array = process.get("_rejections")?;
+
+    if is_handled {
+        let remaining = Array::new(ctx.clone())?;
+
+        for rejection in rejections.iter::<Array>() {
+            let rejection = rejection?;
+
+            if rejection.get::<Value>(0)? != promise {
+                remaining.set(remaining.len(), rejection)?;
+            }
+        }
+
+        process.set("_rejections", remaining)?;
+    } else {
+        let rejection = Array::new(ctx.clone())?;
+
+        rejection.set(0, promise)?;
+        rejection.set(1, reason)?;
+
+        rejections.set(rejections.len(), rejection)?;
+    }
+
+    Ok(())
+}
+
+/// Take the `(promise, reason)` pairs of every rejection that is still unhandled
+pub fn take_unhandled_rejections<'js>(
+    ctx: &Ctx<'js>,
+) -> QuickJsResult<Vec<(Value<'js>, Value<'js>)>> {
+    let process = get_process_object(ctx)?;
+
+    let rejections: Array = process.get("_rejections")?;
+
+    let mut result = Vec::new();
+
+    for rejection in rejections.iter::<Array>() {
+        let rejection = rejection?;
+
+        result.push((rejection.get(0)?, rejection.get(1)?));
+    }
+
+    process.set("_rejections", Array::new(ctx.clone())?)?;
+
+    Ok(result)
+}
+
+/// The code the process should exit with, taken from `process.exitCode`
+pub fn exit_code(ctx: &Ctx<'_>) -> QuickJsResult<i32> {
+    let process = get_process_object(ctx)?;
+
+    Ok(process.get::<_, Option<i32>>("exitCode")?.unwrap_or(0))
+}
+
+/// Start shutting down, running the `exit` listeners only the first time this is called
+pub fn begin_exit(ctx: &Ctx<'_>, code: Option<i32>) -> QuickJsResult<()> {
+    if let Some(code) = code {
+        get_process_object(ctx)?.set("exitCode", code)?;
+    }
+
+    if get_exit_state(ctx)?.exiting.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let code = exit_code(ctx)?;
+
+    let result = emit(
+        ctx.clone(),
+        String::from("exit"),
+        Rest(vec![Value::new_int(ctx.clone(), code)]),
+    );
+
+    let _ = crate::stdio::flush_stdout();
+
+    result.map(|_| ())
+}
+
+fn exit(ctx: Ctx<'_>, code: Opt<i32>) -> QuickJsResult<()> {
+    terminate(&ctx, code.0)
+}
+
+/// Start exiting and unwind the whole stack, as `process.exit` does
+pub fn terminate(ctx: &Ctx<'_>, code: Option<i32>) -> QuickJsResult<()> {
+    // Only unwind once the `exit` listeners ran, so they aren't interrupted themselves
+    let result = begin_exit(ctx, code);
+
+    get_exit_state(ctx)?.unwinding.store(true, Ordering::SeqCst);
+
+    result?;
+
+    // Spin until the interrupt handler notices we are unwinding, so the engine raises its
+    // uncatchable error and unwinds the whole stack instead of a plain catchable exception
+    ctx.eval::<(), _>("for (;;) {}")
+}
+
 pub struct ProcessModule;
 
 impl ModuleDef for ProcessModule {
     fn declare(declare: &mut Declarations) -> QuickJsResult<()> {
         declare.declare("argv")?;
+        declare.declare("execArgv")?;
         declare.declare("env")?;
         declare.declare("cwd")?;
+        declare.declare("chdir")?;
+        declare.declare("execPath")?;
+        declare.declare("pid")?;
+        declare.declare("ppid")?;
+        declare.declare("getuid")?;
+        declare.declare("geteuid")?;
+        declare.declare("getgid")?;
+        declare.declare("getegid")?;
+        declare.declare("getgroups")?;
+        declare.declare("hrtime")?;
+        declare.declare("uptime")?;
+        declare.declare("memoryUsage")?;
+        declare.declare("resourceUsage")?;
         declare.declare("arch")?;
         declare.declare("platform")?;
+        declare.declare("exitCode")?;
         declare.declare("exit")?;
+        declare.declare("on")?;
+        declare.declare("once")?;
+        declare.declare("off")?;
+        declare.declare("emit")?;
+        declare.declare("stdout")?;
+        declare.declare("stderr")?;
         declare.declare("default")?;
 
         Ok(())
     }
 
     fn evaluate<'js>(ctx: &Ctx<'js>, exports: &mut Exports<'js>) -> QuickJsResult<()> {
-        let argv = crate::cli::get_program_argv();
-
-        let env: HashMap<String, String> = std::env::vars().collect();
-
-        export_default(ctx, exports, |default| {
-            default.set("argv", argv)?;
-            default.set("env", env)?;
-            default.set("cwd", Func::from(cwd))?;
-            default.set("arch", get_arch())?;
-            default.set("platform", get_platform())?;
-            default.set(
-                "exit",
-                Func::from(|status_code: i32| std::process::exit(status_code)),
-            )?;
-
-            Ok(())
-        })
+        export_object(exports, get_process_object(ctx)?)
     }
 }
@@ -0,0 +1,294 @@
+use crate::inspect::{colorize, inspect, InspectOptions};
+
+use rquickjs::{Array, Ctx, Object, Result as QuickJsResult, Value};
+
+/// How deep causes and the errors of `AggregateError`s are followed
+const MAX_DEPTH: usize = 8;
+
+/// Properties shown as part of the report rather than listed after it
+const REPORTED_KEYS: &[&str] = &["name", "message", "stack", "cause", "errors", "code"];
+
+/// Whether `value` is reported as an error rather than inspected like any value
+pub fn is_error(value: &Value<'_>) -> bool {
+    value.is_error()
+        || value.as_object().is_some_and(|object| {
+            object
+                .get::<_, Option<String>>("stack")
+                .is_ok_and(|stack| stack.is_some())
+        })
+}
+
+/// Format an uncaught error the way compilers report errors, its class and message, the line
+/// of source that threw it with a caret under where, its stack, its `cause` chain and the errors
+/// of an `AggregateError`
+pub fn format_report<'js>(
+    ctx: &Ctx<'js>,
+    error: &Object<'js>,
+    colors: bool,
+) -> QuickJsResult<String> {
+    let mut report = Report {
+        ctx,
+        colors,
+        output: String::new(),
+        seen: Vec::new(),
+    };
+
+    report.write_error(error, "", 0)?;
+
+    Ok(report.output)
+}
+
+struct Report<'a, 'js> {
+    ctx: &'a Ctx<'js>,
+    colors: bool,
+    output: String,
+    /// The errors being reported, to stop at cyclic causes
+    seen: Vec<Object<'js>>,
+}
+
+impl<'a, 'js> Report<'a, 'js> {
+    fn paint(&self, text: &str, color: &str) -> String {
+        match self.colors {
+            true => colorize(text, color),
+
+            false => text.to_string(),
+        }
+    }
+
+    fn write_line(&mut self, indentation: usize, line: &str) {
+        self.output.push_str(&" ".repeat(indentation));
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    fn inspect(&self, value: &Value<'js>) -> QuickJsResult<String> {
+        let options = InspectOptions {
+            colors: self.colors,
+            ..InspectOptions::default()
+        };
+
+        inspect(self.ctx, value, &options)
+    }
+
+    /// Write what is reported for `value` after `label`, an error or else the inspected value
+    fn write_value(
+        &mut self,
+        value: &Value<'js>,
+        label: &str,
+        indentation: usize,
+    ) -> QuickJsResult<()> {
+        match value.as_object() {
+            Some(object) if is_error(value) => self.write_error(object, label, indentation),
+
+            _ => {
+                let inspected = self
+                    .inspect(value)?
+                    .replace('\n', &format!("\n{}", " ".repeat(indentation)));
+
+                self.write_line(indentation, &format!("{}{}", label, inspected));
+
+                Ok(())
+            }
+        }
+    }
+
+    fn write_error(
+        &mut self,
+        error: &Object<'js>,
+        label: &str,
+        indentation: usize,
+    ) -> QuickJsResult<()> {
+        if self.seen.contains(error) {
+            let circular = self.paint("[Circular]", "cyan");
+
+            self.write_line(indentation, &format!("{}{}", label, circular));
+
+            return Ok(());
+        }
+
+        self.write_header(error, label, indentation)?;
+
+        let stack = crate::source_map::get_mapped_stack(self.ctx, error)?.unwrap_or_default();
+
+        let frames: Vec<&str> = stack
+            .lines()
+            .map(str::trim)
+            .filter(|frame| frame.starts_with("at "))
+            .collect();
+
+        if let Some(location) = frames.iter().find_map(|frame| get_location(frame)) {
+            self.write_code_frame(&location, indentation);
+        }
+
+        for frame in &frames {
+            let frame = self.paint(frame, "grey");
+
+            self.write_line(indentation + 4, &frame);
+        }
+
+        self.write_properties(error, indentation)?;
+
+        if self.seen.len() >= MAX_DEPTH {
+            return Ok(());
+        }
+
+        self.seen.push(error.clone());
+
+        if let Some(errors) = error.get::<_, Option<Array>>("errors")? {
+            for (index, child) in errors.iter::<Value>().enumerate() {
+                let label = self.paint(&format!("[errors[{}]]", index), "bold");
+
+                self.write_value(&child?, &format!("{} ", label), indentation + 2)?;
+            }
+        }
+
+        if error.contains_key("cause")? {
+            let cause: Value = error.get("cause")?;
+
+            let label = self.paint("Caused by:", "bold");
+
+            self.write_value(&cause, &format!("{} ", label), indentation + 2)?;
+        }
+
+        self.seen.pop();
+
+        Ok(())
+    }
+
+    /// Write `Name[code]: message`
+    fn write_header(
+        &mut self,
+        error: &Object<'js>,
+        label: &str,
+        indentation: usize,
+    ) -> QuickJsResult<()> {
+        let name = match error.get::<_, Value>("name")? {
+            name if name.is_undefined() => String::from("Error"),
+
+            name => crate::console::js_stringify(self.ctx, &name)?,
+        };
+
+        let message = match error.get::<_, Value>("message")? {
+            message if message.is_undefined() => String::new(),
+
+            message => crate::console::js_stringify(self.ctx, &message)?,
+        };
+
+        let mut header = self.paint(&name, "red");
+
+        if let Some(code) = error.get::<_, Option<String>>("code")? {
+            header.push_str(&self.paint(&format!("[{}]", code), "red"));
+        }
+
+        header = self.paint(&header, "bold");
+
+        if !message.is_empty() {
+            let message = message.replace('\n', &format!("\n{}", " ".repeat(indentation)));
+
+            header.push_str(&self.paint(&format!(": {}", message), "bold"));
+        }
+
+        self.write_line(indentation, &format!("{}{}", label, header));
+
+        Ok(())
+    }
+
+    /// Write the line at `location` with a caret under its column, when the file can be read
+    fn write_code_frame(&mut self, location: &Location, indentation: usize) {
+        let Ok(source) = std::fs::read_to_string(&location.file) else {
+            return;
+        };
+
+        let Some(line) = source.lines().nth(location.line.saturating_sub(1)) else {
+            return;
+        };
+
+        let line = line.trim_end();
+
+        let number = location.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = self.paint("|", "blue");
+
+        // Keep the tabs before the column so the caret lines up with the line above
+        let padding: String = line
+            .chars()
+            .take(location.column.saturating_sub(1))
+            .map(|char| if char == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let arrow = self.paint("-->", "blue");
+
+        self.write_line(
+            indentation,
+            &format!(
+                "{}{} {}:{}:{}",
+                gutter, arrow, location.file, location.line, location.column
+            ),
+        );
+
+        self.write_line(indentation, &format!("{} {}", gutter, bar));
+        self.write_line(
+            indentation,
+            &format!("{} {} {}", self.paint(&number, "blue"), bar, line),
+        );
+        self.write_line(
+            indentation,
+            &format!("{} {} {}{}", gutter, bar, padding, self.paint("^", "red")),
+        );
+        self.write_line(indentation, &format!("{} {}", gutter, bar));
+    }
+
+    /// Write the other own enumerable properties of `error`, like `errno` or `path`
+    fn write_properties(&mut self, error: &Object<'js>, indentation: usize) -> QuickJsResult<()> {
+        for key in error.keys::<String>() {
+            let key = key?;
+
+            if REPORTED_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+
+            let value: Value = error.get(key.as_str())?;
+
+            let value = self
+                .inspect(&value)?
+                .replace('\n', &format!("\n{}", " ".repeat(indentation + 4)));
+
+            self.write_line(
+                indentation + 2,
+                &format!("{} {}: {}", self.paint("=", "blue"), key, value),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a stack frame is in a file
+struct Location {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+/// The location of a frame like `at name (file:line:column)` or `at file:line:column`
+fn get_location(frame: &str) -> Option<Location> {
+    let frame = frame.strip_prefix("at ")?;
+
+    let location = match (frame.rfind('('), frame.strip_suffix(')')) {
+        (Some(start), Some(frame)) => &frame[start + 1..],
+
+        _ => frame,
+    };
+
+    let mut parts = location.rsplitn(3, ':');
+
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next()?;
+
+    Some(Location {
+        file: file.strip_prefix("file://").unwrap_or(file).to_string(),
+        line,
+        column,
+    })
+}
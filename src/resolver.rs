@@ -0,0 +1,801 @@
+use crate::module::attributes::{split_type, with_type};
+use crate::utils::{decode_percent, throw_type_error};
+
+use rquickjs::loader::Resolver;
+use rquickjs::{Ctx, Error, Object, Result as QuickJsResult, Value};
+
+use std::path::{Component, Path, PathBuf};
+
+/// How specifiers are resolved, which differs between `import` and `require()`
+#[derive(Debug)]
+pub struct ResolveOptions {
+    /// The conditions matched in the `exports` and `imports` of a `package.json`, besides `default`
+    conditions: &'static [&'static str],
+    /// The extensions tried when a path is given without one, also used for `index` files
+    extensions: &'static [&'static str],
+    /// The `package.json` fields holding the entry point of packages without `exports`
+    main_fields: &'static [&'static str],
+}
+
+pub const IMPORT: ResolveOptions = ResolveOptions {
+    conditions: &["yaso", "import"],
+    extensions: &["js", "mjs", "cjs", "ts", "mts", "cts"],
+    main_fields: &["module", "main"],
+};
+
+pub const REQUIRE: ResolveOptions = ResolveOptions {
+    conditions: &["yaso", "require"],
+    extensions: &["js", "json", "cjs", "mjs", "ts", "cts", "mts"],
+    main_fields: &["main"],
+};
+
+/// Resolve module specifiers the way Node.js does, relative paths against the importing module,
+/// bare ones by walking up `node_modules` directories and `#` ones through the `imports` of the
+/// enclosing package
+#[derive(Debug, Default)]
+pub struct NodeResolver;
+
+impl Resolver for NodeResolver {
+    fn resolve<'js>(&mut self, ctx: &Ctx<'js>, base: &str, name: &str) -> QuickJsResult<String> {
+        if crate::module::is_source_module(ctx, name)? {
+            return Ok(name.to_string());
+        }
+
+        if let Some(builtin) = crate::module::get_builtin_name(ctx, name)? {
+            return Ok(builtin.to_string());
+        }
+
+        let (name, module_type) = split_type(name);
+
+        match resolve(ctx, &get_base_dir(ctx, base), name, &IMPORT) {
+            Ok(path) => check_module_type(ctx, &path, module_type),
+
+            Err(ResolveError::NotFound) => Err(Error::new_resolving(base, name)),
+
+            Err(ResolveError::NotExported(message) | ResolveError::NotImported(message)) => {
+                Err(Error::new_resolving_message(base, name, message))
+            }
+
+            Err(ResolveError::Js(err)) => Err(err),
+        }
+    }
+}
+
+/// Check the `type` import attribute against the file `path` points to, returning the name of
+/// the module, which keeps the type for text and bytes modules
+fn check_module_type(
+    ctx: &Ctx<'_>,
+    path: &Path,
+    module_type: Option<&str>,
+) -> QuickJsResult<String> {
+    let name = path.to_string_lossy().to_string();
+
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension == "json");
+
+    match module_type {
+        None if is_json => Err(throw_type_error(
+            ctx,
+            "ERR_IMPORT_ATTRIBUTE_MISSING",
+            &format!(
+                "Module \"{}\" needs an import attribute of \"type: json\"",
+                name
+            ),
+        )),
+
+        None => Ok(name),
+
+        Some("json") if is_json => Ok(name),
+
+        Some("json") => Err(throw_type_error(
+            ctx,
+            "ERR_IMPORT_ATTRIBUTE_TYPE_INCOMPATIBLE",
+            &format!("Module \"{}\" is not of type \"json\"", name),
+        )),
+
+        Some(module_type @ ("text" | "bytes")) => Ok(with_type(&name, module_type)),
+
+        Some(module_type) => Err(throw_type_error(
+            ctx,
+            "ERR_IMPORT_ATTRIBUTE_UNSUPPORTED",
+            &format!(
+                "Import attribute \"type\" with value \"{}\" is not supported",
+                module_type
+            ),
+        )),
+    }
+}
+
+/// Why a specifier could not be resolved
+pub enum ResolveError {
+    NotFound,
+    NotExported(String),
+    NotImported(String),
+    Js(Error),
+}
+
+impl From<Error> for ResolveError {
+    fn from(err: Error) -> ResolveError {
+        ResolveError::Js(err)
+    }
+}
+
+type ResolveResult<T> = Result<T, ResolveError>;
+
+/// Resolve `name` imported from a module in `base_dir` to the real path of a file
+pub fn resolve(
+    ctx: &Ctx<'_>,
+    base_dir: &Path,
+    name: &str,
+    options: &ResolveOptions,
+) -> ResolveResult<PathBuf> {
+    let path = if is_path(name) {
+        resolve_file_or_directory(ctx, &normalize(&base_dir.join(name)), options)?
+    } else if name.starts_with('#') {
+        resolve_imports(ctx, base_dir, name, options)?
+    } else {
+        resolve_package(ctx, base_dir, name, options)?
+    };
+
+    match path {
+        Some(path) => Ok(canonicalize(&path)),
+
+        None => Err(ResolveError::NotFound),
+    }
+}
+
+/// Whether `name` is a relative or absolute path rather than a package or builtin
+fn is_path(name: &str) -> bool {
+    name == "."
+        || name == ".."
+        || name.starts_with("./")
+        || name.starts_with("../")
+        || name.starts_with('/')
+}
+
+/// The absolute directory of the importing module, the current directory for the entry point
+pub fn get_base_dir(ctx: &Ctx<'_>, base: &str) -> PathBuf {
+    get_absolute_dir(ctx, Path::new(base).parent().unwrap_or(Path::new("")))
+}
+
+/// Name modules after the real path of their file so a file imported through different
+/// specifiers or symlinks is only instantiated once
+fn canonicalize(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| normalize(path))
+}
+
+/// Remove the `.` and `..` components of `path` without touching the file system
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+
+                Some(Component::RootDir) => (),
+
+                _ => normalized.push(".."),
+            },
+
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+
+    path.push(".");
+    path.push(extension);
+
+    PathBuf::from(path)
+}
+
+/// Find the file `path` points to, trying it as is, with its JavaScript extension swapped for the
+/// TypeScript one, with each known extension and as a directory
+fn resolve_file_or_directory(
+    ctx: &Ctx<'_>,
+    path: &Path,
+    options: &ResolveOptions,
+) -> QuickJsResult<Option<PathBuf>> {
+    if path.is_file() {
+        return Ok(Some(path.to_path_buf()));
+    }
+
+    // TypeScript imports name the `.js` file the `.ts` one compiles to
+    let typescript = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("js") => Some("ts"),
+
+        Some("mjs") => Some("mts"),
+
+        Some("cjs") => Some("cts"),
+
+        _ => None,
+    };
+
+    if let Some(extension) = typescript {
+        let file = path.with_extension(extension);
+
+        if file.is_file() {
+            return Ok(Some(file));
+        }
+    }
+
+    for extension in options.extensions {
+        let file = with_extension(path, extension);
+
+        if file.is_file() {
+            return Ok(Some(file));
+        }
+    }
+
+    if path.is_dir() {
+        return resolve_directory(ctx, path, options);
+    }
+
+    Ok(None)
+}
+
+/// Find the entry point of a directory, from the main fields of its `package.json` or else its
+/// `index` file
+fn resolve_directory(
+    ctx: &Ctx<'_>,
+    directory: &Path,
+    options: &ResolveOptions,
+) -> QuickJsResult<Option<PathBuf>> {
+    if let Some(package) = read_package_json(ctx, directory)? {
+        for field in options.main_fields {
+            let Some(main) = package.get::<_, Option<String>>(*field)? else {
+                continue;
+            };
+
+            let main = normalize(&directory.join(main));
+
+            if main.is_dir() {
+                if let Some(path) = resolve_index(&main, options) {
+                    return Ok(Some(path));
+                }
+
+                continue;
+            }
+
+            if let Some(path) = resolve_file_or_directory(ctx, &main, options)? {
+                return Ok(Some(path));
+            }
+        }
+    }
+
+    Ok(resolve_index(directory, options))
+}
+
+fn resolve_index(directory: &Path, options: &ResolveOptions) -> Option<PathBuf> {
+    options
+        .extensions
+        .iter()
+        .map(|extension| directory.join(format!("index.{}", extension)))
+        .find(|path| path.is_file())
+}
+
+fn read_package_json<'js>(ctx: &Ctx<'js>, directory: &Path) -> QuickJsResult<Option<Object<'js>>> {
+    let Ok(source) = std::fs::read_to_string(directory.join("package.json")) else {
+        return Ok(None);
+    };
+
+    Ok(ctx.json_parse(source)?.into_object())
+}
+
+/// Split a bare specifier into the package name, which includes the scope if any, and the subpath
+/// within the package in the `./path` form used by `exports`
+fn split_package_name(name: &str) -> Option<(&str, String)> {
+    let segments = if name.starts_with('@') { 2 } else { 1 };
+
+    let end = name
+        .match_indices('/')
+        .nth(segments - 1)
+        .map_or(name.len(), |(index, _)| index);
+
+    let package_name = &name[..end];
+
+    if package_name.is_empty() || (segments == 2 && !package_name.contains('/')) {
+        return None;
+    }
+
+    Some((package_name, format!(".{}", &name[end..])))
+}
+
+fn get_absolute_dir(ctx: &Ctx<'_>, directory: &Path) -> PathBuf {
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+
+    crate::process::absolute_path(ctx, directory)
+}
+
+/// Look for the package in the `node_modules` of `directory` and each of its ancestors
+fn resolve_package(
+    ctx: &Ctx<'_>,
+    directory: &Path,
+    name: &str,
+    options: &ResolveOptions,
+) -> ResolveResult<Option<PathBuf>> {
+    let Some((package_name, subpath)) = split_package_name(name) else {
+        return Ok(None);
+    };
+
+    for directory in directory.ancestors() {
+        let package_dir = directory.join("node_modules").join(package_name);
+
+        if package_dir.is_dir() {
+            return resolve_package_subpath(ctx, &package_dir, &subpath, options);
+        }
+    }
+
+    Ok(None)
+}
+
+fn resolve_package_subpath(
+    ctx: &Ctx<'_>,
+    package_dir: &Path,
+    subpath: &str,
+    options: &ResolveOptions,
+) -> ResolveResult<Option<PathBuf>> {
+    let package = read_package_json(ctx, package_dir)?;
+
+    let exports = match &package {
+        Some(package) => package.get::<_, Value>("exports")?,
+
+        None => Value::new_undefined(ctx.clone()),
+    };
+
+    if !exports.is_undefined() && !exports.is_null() {
+        return match resolve_exports(ctx, package_dir, subpath, exports, options)? {
+            Some(path) => Ok(Some(path)),
+
+            None => Err(ResolveError::NotExported(format!(
+                "Package subpath '{}' is not defined by \"exports\" in {}",
+                subpath,
+                package_dir.join("package.json").display()
+            ))),
+        };
+    }
+
+    if subpath == "." {
+        return Ok(resolve_directory(ctx, package_dir, options)?);
+    }
+
+    Ok(resolve_file_or_directory(
+        ctx,
+        &normalize(&package_dir.join(subpath)),
+        options,
+    )?)
+}
+
+/// Whether `exports` only describes the main entry point, as a target or conditions
+fn is_main_export(exports: &Value<'_>) -> QuickJsResult<bool> {
+    let Some(object) = exports.as_object().filter(|_| !exports.is_array()) else {
+        return Ok(true);
+    };
+
+    for key in object.keys::<String>() {
+        if key?.starts_with('.') {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn resolve_exports<'js>(
+    ctx: &Ctx<'js>,
+    package_dir: &Path,
+    subpath: &str,
+    exports: Value<'js>,
+    options: &ResolveOptions,
+) -> ResolveResult<Option<PathBuf>> {
+    if is_main_export(&exports)? {
+        if subpath != "." {
+            return Ok(None);
+        }
+
+        return resolve_target(ctx, package_dir, exports, None, false, options);
+    }
+
+    let Some(exports) = exports.into_object() else {
+        return Ok(None);
+    };
+
+    resolve_map(ctx, package_dir, subpath, &exports, false, options)
+}
+
+/// Resolve `#name` through the `imports` of the closest `package.json`
+fn resolve_imports(
+    ctx: &Ctx<'_>,
+    directory: &Path,
+    name: &str,
+    options: &ResolveOptions,
+) -> ResolveResult<Option<PathBuf>> {
+    for directory in directory.ancestors() {
+        let Some(package) = read_package_json(ctx, directory)? else {
+            continue;
+        };
+
+        if let Some(imports) = package.get::<_, Option<Object>>("imports")? {
+            if let Some(path) = resolve_map(ctx, directory, name, &imports, true, options)? {
+                return Ok(Some(path));
+            }
+        }
+
+        return Err(ResolveError::NotImported(format!(
+            "Package import specifier \"{}\" is not defined in {}",
+            name,
+            directory.join("package.json").display()
+        )));
+    }
+
+    Ok(None)
+}
+
+/// Find the entry of an `exports` or `imports` map matching `key`, either exactly or through a
+/// single `*` pattern, the pattern with the longest prefix winning
+fn resolve_map<'js>(
+    ctx: &Ctx<'js>,
+    package_dir: &Path,
+    key: &str,
+    map: &Object<'js>,
+    imports: bool,
+    options: &ResolveOptions,
+) -> ResolveResult<Option<PathBuf>> {
+    if !key.contains('*') {
+        if let Some(target) = map.get::<_, Option<Value>>(key)? {
+            return resolve_target(ctx, package_dir, target, None, imports, options);
+        }
+    }
+
+    let mut best: Option<(String, String)> = None;
+
+    for pattern in map.keys::<String>() {
+        let pattern = pattern?;
+
+        let Some((prefix, suffix)) = pattern.split_once('*') else {
+            continue;
+        };
+
+        if suffix.contains('*')
+            || !key.starts_with(prefix)
+            || !key.ends_with(suffix)
+            || key.len() < pattern.len()
+        {
+            continue;
+        }
+
+        let is_better = match &best {
+            Some((best, _)) => {
+                let best_prefix = best.split('*').next().unwrap_or("");
+
+                prefix.len() > best_prefix.len()
+                    || (prefix.len() == best_prefix.len() && pattern.len() > best.len())
+            }
+
+            None => true,
+        };
+
+        if is_better {
+            let matched = key[prefix.len()..key.len() - suffix.len()].to_string();
+
+            best = Some((pattern, matched));
+        }
+    }
+
+    match best {
+        Some((pattern, matched)) => {
+            let target: Value = map.get(pattern.as_str())?;
+
+            resolve_target(ctx, package_dir, target, Some(&matched), imports, options)
+        }
+
+        None => Ok(None),
+    }
+}
+
+/// Resolve the target of an `exports` or `imports` entry, picking the first matching condition of
+/// objects and the first valid target of arrays, `imports` may also point to other packages
+fn resolve_target<'js>(
+    ctx: &Ctx<'js>,
+    package_dir: &Path,
+    target: Value<'js>,
+    pattern: Option<&str>,
+    imports: bool,
+    options: &ResolveOptions,
+) -> ResolveResult<Option<PathBuf>> {
+    if let Some(target) = target.as_string() {
+        let mut target = target.to_string()?;
+
+        let field = if imports { "imports" } else { "exports" };
+
+        let package_json = package_dir.join("package.json");
+
+        if let Some(pattern) = pattern {
+            if target.starts_with("./") && has_invalid_segment(pattern) {
+                return Err(throw_type_error(
+                    ctx,
+                    "ERR_INVALID_MODULE_SPECIFIER",
+                    &format!(
+                        "Invalid module \"{}\" is not a valid match in pattern \"{}\" for the \"{}\" resolution of {}",
+                        pattern,
+                        target,
+                        field,
+                        package_json.display()
+                    ),
+                )
+                .into());
+            }
+
+            target = target.replace('*', pattern);
+        }
+
+        if let Some(subpath) = target.strip_prefix("./") {
+            let path = normalize(&package_dir.join(subpath));
+
+            if has_invalid_segment(subpath) || !path.starts_with(normalize(package_dir)) {
+                return Err(throw_type_error(
+                    ctx,
+                    "ERR_INVALID_PACKAGE_TARGET",
+                    &format!(
+                        "Invalid \"{}\" target \"{}\" defined in {}",
+                        field,
+                        target,
+                        package_json.display()
+                    ),
+                )
+                .into());
+            }
+
+            return Ok(Some(path));
+        }
+
+        if imports && !is_path(&target) {
+            return resolve_package(ctx, package_dir, &target, options);
+        }
+
+        return Ok(None);
+    }
+
+    if let Some(targets) = target.as_array() {
+        for target in targets.iter::<Value>() {
+            if let Some(path) =
+                resolve_target(ctx, package_dir, target?, pattern, imports, options)?
+            {
+                return Ok(Some(path));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    if let Some(conditions) = target.as_object() {
+        for condition in conditions.keys::<String>() {
+            let condition = condition?;
+
+            if condition != "default" && !options.conditions.contains(&condition.as_str()) {
+                continue;
+            }
+
+            let target: Value = conditions.get(condition.as_str())?;
+
+            if let Some(path) = resolve_target(ctx, package_dir, target, pattern, imports, options)?
+            {
+                return Ok(Some(path));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether `path` has a `.`, `..` or `node_modules` segment, even percent-encoded, which would let
+/// an `exports` or `imports` entry reach outside of the package or into its dependencies
+fn has_invalid_segment(path: &str) -> bool {
+    path.split(['/', '\\']).any(|segment| {
+        matches!(
+            decode_percent(segment).to_ascii_lowercase().as_str(),
+            "." | ".." | "node_modules"
+        )
+    })
+}
+
+/// The `type` field of the closest `package.json` above `path`
+pub fn get_package_type(ctx: &Ctx<'_>, path: &Path) -> QuickJsResult<Option<String>> {
+    for directory in path.ancestors().skip(1) {
+        if let Some(package) = read_package_json(ctx, directory)? {
+            return package.get("type");
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rquickjs::{Context, Runtime};
+
+    /// Resolve `key` through the `exports` or `imports` map `map` of the package in
+    /// `package_dir`, returning the code of the error thrown if any
+    fn resolve_key(
+        package_dir: &Path,
+        map: &str,
+        key: &str,
+        imports: bool,
+        options: &ResolveOptions,
+    ) -> Result<Option<PathBuf>, String> {
+        let runtime = Runtime::new().unwrap();
+        let context = Context::full(&runtime).unwrap();
+
+        context.with(|ctx| {
+            let map = ctx.json_parse(map).unwrap().into_object().unwrap();
+
+            match resolve_map(&ctx, package_dir, key, &map, imports, options) {
+                Ok(path) => Ok(path),
+
+                Err(ResolveError::Js(_)) => {
+                    let error = ctx.catch().into_object().unwrap();
+
+                    Err(error.get::<_, String>("code").unwrap())
+                }
+
+                Err(_) => Err("not resolved".to_string()),
+            }
+        })
+    }
+
+    fn exports(map: &str, key: &str, options: &ResolveOptions) -> Option<PathBuf> {
+        resolve_key(Path::new("/pkg"), map, key, false, options).unwrap()
+    }
+
+    #[test]
+    fn splits_package_names() {
+        assert_eq!(split_package_name("pkg"), Some(("pkg", ".".to_string())));
+        assert_eq!(
+            split_package_name("pkg/lib/a.js"),
+            Some(("pkg", "./lib/a.js".to_string()))
+        );
+        assert_eq!(
+            split_package_name("@scope/pkg"),
+            Some(("@scope/pkg", ".".to_string()))
+        );
+        assert_eq!(
+            split_package_name("@scope/pkg/lib"),
+            Some(("@scope/pkg", "./lib".to_string()))
+        );
+        assert_eq!(split_package_name("@scope"), None);
+        assert_eq!(split_package_name(""), None);
+    }
+
+    #[test]
+    fn prefers_exact_keys_then_longest_patterns() {
+        let map = r#"{
+            "./*": "./all/*.js",
+            "./a/*": "./a/*.js",
+            "./a/*.mjs": "./mjs/*.mjs",
+            "./a/b": "./exact.js"
+        }"#;
+
+        assert_eq!(
+            exports(map, "./a/b", &IMPORT),
+            Some(PathBuf::from("/pkg/exact.js"))
+        );
+        assert_eq!(
+            exports(map, "./a/c/d", &IMPORT),
+            Some(PathBuf::from("/pkg/a/c/d.js"))
+        );
+        assert_eq!(
+            exports(map, "./a/c.mjs", &IMPORT),
+            Some(PathBuf::from("/pkg/mjs/c.mjs"))
+        );
+        assert_eq!(
+            exports(map, "./z", &IMPORT),
+            Some(PathBuf::from("/pkg/all/z.js"))
+        );
+    }
+
+    #[test]
+    fn picks_the_first_matching_condition() {
+        let map = r#"{
+            ".": { "import": "./import.js", "require": "./require.js", "default": "./default.js" },
+            "./default": { "default": "./default.js", "yaso": "./yaso.js" },
+            "./nested": { "node": "./node.js", "require": { "yaso": "./nested.js" } },
+            "./fallback": ["not-a-path", { "browser": "./browser.js" }, "./fallback.js"],
+            "./none": { "browser": "./browser.js" }
+        }"#;
+
+        assert_eq!(
+            exports(map, ".", &IMPORT),
+            Some(PathBuf::from("/pkg/import.js"))
+        );
+        assert_eq!(
+            exports(map, ".", &REQUIRE),
+            Some(PathBuf::from("/pkg/require.js"))
+        );
+        assert_eq!(
+            exports(map, "./default", &REQUIRE),
+            Some(PathBuf::from("/pkg/default.js"))
+        );
+        assert_eq!(
+            exports(map, "./nested", &REQUIRE),
+            Some(PathBuf::from("/pkg/nested.js"))
+        );
+        assert_eq!(exports(map, "./nested", &IMPORT), None);
+        assert_eq!(
+            exports(map, "./fallback", &IMPORT),
+            Some(PathBuf::from("/pkg/fallback.js"))
+        );
+        assert_eq!(exports(map, "./none", &IMPORT), None);
+    }
+
+    #[test]
+    fn resolves_imports_to_packages() {
+        let root = std::env::temp_dir().join(format!("yaso-resolver-{}", std::process::id()));
+        let dependency = root.join("node_modules").join("dep");
+
+        std::fs::create_dir_all(dependency.join("lib")).unwrap();
+        std::fs::write(dependency.join("package.json"), r#"{ "main": "main.js" }"#).unwrap();
+        std::fs::write(dependency.join("main.js"), "").unwrap();
+        std::fs::write(dependency.join("lib").join("a.js"), "").unwrap();
+
+        let map = r##"{ "#dep": "dep", "#dep/*": "dep/lib/*.js", "#local": "./local.js" }"##;
+
+        let resolve = |key| resolve_key(&root, map, key, true, &IMPORT).unwrap();
+
+        assert!(resolve("#dep").is_some_and(|path| path.ends_with("node_modules/dep/main.js")));
+        assert!(resolve("#dep/a").is_some_and(|path| path.ends_with("node_modules/dep/lib/a.js")));
+        assert_eq!(resolve("#local"), Some(root.join("local.js")));
+
+        // Only `imports` may point at packages
+        assert_eq!(
+            resolve_key(&root, r#"{ ".": "dep" }"#, ".", false, &IMPORT),
+            Ok(None)
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rejects_matches_leaving_the_package() {
+        let map =
+            r#"{ "./f/*": "./src/*.js", "./up": "./../up.js", "./deps": "./node_modules/a.js" }"#;
+
+        let resolve = |key| resolve_key(Path::new("/pkg"), map, key, false, &IMPORT);
+
+        assert_eq!(
+            resolve("./f/a/b"),
+            Ok(Some(PathBuf::from("/pkg/src/a/b.js")))
+        );
+
+        for key in [
+            "./f/../a",
+            "./f/a/./b",
+            "./f/%2E%2e/a",
+            "./f/node_modules/a",
+        ] {
+            assert_eq!(
+                resolve(key),
+                Err("ERR_INVALID_MODULE_SPECIFIER".to_string())
+            );
+        }
+
+        for key in ["./up", "./deps"] {
+            assert_eq!(resolve(key), Err("ERR_INVALID_PACKAGE_TARGET".to_string()));
+        }
+    }
+}
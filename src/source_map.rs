@@ -0,0 +1,690 @@
+use crate::resolver::normalize;
+use crate::utils::decode_percent;
+
+use rquickjs::function::{Constructor, Rest, This};
+use rquickjs::object::Property;
+use rquickjs::{qjs, Array, Class, Ctx, Error, Function, Object, Result as QuickJsResult, Value};
+
+use std::collections::HashMap;
+use std::ffi::c_int;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+const MAPPED_ERRORS_KEY: &str = "__yaso_mapped_errors";
+
+const SOURCE_MAPS_KEY: &str = "__yaso_source_maps";
+
+const ERROR_CONSTRUCTORS_KEY: &str = "__yaso_error_constructors";
+
+/// The constructors replaced so the errors they create have their `stack` mapped right away
+const ERROR_CONSTRUCTORS: &[&str] = &[
+    "Error",
+    "EvalError",
+    "RangeError",
+    "ReferenceError",
+    "SyntaxError",
+    "TypeError",
+    "URIError",
+    "AggregateError",
+];
+
+/// Whether a virtual machine maps stack traces back to the original sources, as
+/// `--enable-source-maps` does, and the source maps it read so far
+#[rquickjs::class]
+#[derive(rquickjs::class::Trace)]
+struct SourceMaps {
+    #[qjs(skip_trace)]
+    enabled: bool,
+    /// The source maps by the path of the file they map and when that file was modified, `None`
+    /// for files without one
+    #[qjs(skip_trace)]
+    cache: HashMap<PathBuf, (Option<SystemTime>, Option<Rc<SourceMap>>)>,
+}
+
+/// Where a position of the generated code comes from
+#[derive(Debug, Clone, Copy)]
+struct Mapping {
+    generated_column: u32,
+    source: u32,
+    line: u32,
+    column: u32,
+}
+
+#[derive(Debug)]
+struct SourceMap {
+    sources: Vec<String>,
+    /// The mappings of each generated line, sorted by their generated column
+    lines: Vec<Vec<Mapping>>,
+}
+
+impl SourceMap {
+    /// Find the source, line and column, all starting at 1, a generated position comes from
+    fn find(&self, line: usize, column: usize) -> Option<(&str, usize, usize)> {
+        let mappings = self.lines.get(line.checked_sub(1)?)?;
+
+        let column = column.saturating_sub(1) as u32;
+
+        let index = mappings.partition_point(|mapping| mapping.generated_column <= column);
+
+        let mapping = mappings.get(index.checked_sub(1)?)?;
+
+        let source = self.sources.get(mapping.source as usize)?;
+
+        Some((
+            source,
+            mapping.line as usize + 1,
+            mapping.column as usize + 1,
+        ))
+    }
+}
+
+fn decode_base64_digit(byte: u8) -> Option<u32> {
+    match byte {
+        b'A'..=b'Z' => Some((byte - b'A') as u32),
+
+        b'a'..=b'z' => Some((byte - b'a') as u32 + 26),
+
+        b'0'..=b'9' => Some((byte - b'0') as u32 + 52),
+
+        b'+' | b'-' => Some(62),
+
+        b'/' | b'_' => Some(63),
+
+        _ => None,
+    }
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0;
+    let mut bits = 0;
+
+    for byte in data
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace() && *byte != b'=')
+    {
+        buffer = (buffer << 6) | decode_base64_digit(byte)?;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+
+            bytes.push((buffer >> bits) as u8);
+
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Decode the Base64 VLQ values of a segment of the `mappings` of a source map
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    let mut values = Vec::new();
+    let mut value: i64 = 0;
+    let mut shift = 0;
+
+    for byte in segment.bytes() {
+        let digit = decode_base64_digit(byte)? as i64;
+
+        value += (digit & 0b11111) << shift;
+
+        if digit & 0b100000 != 0 {
+            shift += 5;
+
+            if shift > 60 {
+                return None;
+            }
+
+            continue;
+        }
+
+        // The lowest bit holds the sign
+        values.push(if value & 1 == 1 {
+            -(value >> 1)
+        } else {
+            value >> 1
+        });
+
+        value = 0;
+        shift = 0;
+    }
+
+    Some(values)
+}
+
+fn decode_mappings(mappings: &str) -> Vec<Vec<Mapping>> {
+    let mut lines = Vec::new();
+
+    let (mut source, mut line, mut column) = (0i64, 0i64, 0i64);
+
+    for generated_line in mappings.split(';') {
+        let mut segments = Vec::new();
+        let mut generated_column = 0i64;
+
+        for segment in generated_line
+            .split(',')
+            .filter(|segment| !segment.is_empty())
+        {
+            let Some(values) = decode_vlq(segment) else {
+                continue;
+            };
+
+            generated_column += values[0];
+
+            // Segments without a source don't map to anything
+            if values.len() < 4 {
+                continue;
+            }
+
+            source += values[1];
+            line += values[2];
+            column += values[3];
+
+            if generated_column < 0 || source < 0 || line < 0 || column < 0 {
+                continue;
+            }
+
+            segments.push(Mapping {
+                generated_column: generated_column as u32,
+                source: source as u32,
+                line: line as u32,
+                column: column as u32,
+            });
+        }
+
+        segments.sort_by_key(|mapping| mapping.generated_column);
+
+        lines.push(segments);
+    }
+
+    lines
+}
+
+/// Find the `sourceMappingURL` comment of a file, the last one winning
+fn find_source_mapping_url(source: &str) -> Option<&str> {
+    source.lines().rev().find_map(|line| {
+        let line = line.trim();
+
+        let url = line
+            .strip_prefix("//# sourceMappingURL=")
+            .or_else(|| line.strip_prefix("//@ sourceMappingURL="))?;
+
+        Some(url.trim())
+    })
+}
+
+/// Read the source map `url` points to, an inline `data:` URL or a file relative to `directory`,
+/// returning its JSON and the directory its sources are relative to
+fn read_source_map(url: &str, directory: &Path) -> Option<(String, PathBuf)> {
+    if let Some(data) = url.strip_prefix("data:") {
+        let (media_type, data) = data.split_once(',')?;
+
+        let json = if media_type.ends_with(";base64") {
+            String::from_utf8(decode_base64(data)?).ok()?
+        } else {
+            decode_percent(data)
+        };
+
+        return Some((json, directory.to_path_buf()));
+    }
+
+    let path = url.strip_prefix("file://").unwrap_or(url);
+
+    let path = normalize(&directory.join(decode_percent(path)));
+
+    let json = std::fs::read_to_string(&path).ok()?;
+
+    Some((json, path.parent()?.to_path_buf()))
+}
+
+fn parse_source_map(
+    ctx: &Ctx<'_>,
+    json: &str,
+    directory: &Path,
+) -> QuickJsResult<Option<SourceMap>> {
+    let Ok(map) = ctx.json_parse(json) else {
+        // Drop the syntax error, a broken source map only means positions stay as they are
+        ctx.catch();
+
+        return Ok(None);
+    };
+
+    let Some(map) = map.into_object() else {
+        return Ok(None);
+    };
+
+    let source_root = map
+        .get::<_, Option<String>>("sourceRoot")?
+        .unwrap_or_default();
+
+    let sources: Vec<String> = match map.get::<_, Option<Array>>("sources")? {
+        Some(sources) => sources
+            .iter::<Option<String>>()
+            .map(|source| {
+                Ok(resolve_source(
+                    &source?.unwrap_or_default(),
+                    &source_root,
+                    directory,
+                ))
+            })
+            .collect::<QuickJsResult<_>>()?,
+
+        None => return Ok(None),
+    };
+
+    let Some(mappings) = map.get::<_, Option<String>>("mappings")? else {
+        return Ok(None);
+    };
+
+    Ok(Some(SourceMap {
+        sources,
+        lines: decode_mappings(&mappings),
+    }))
+}
+
+/// Turn a source of a source map into a path when it is one, keeping other URLs as they are
+fn resolve_source(source: &str, source_root: &str, directory: &Path) -> String {
+    let source = match source_root.is_empty() {
+        true => source.to_string(),
+
+        false => format!("{}/{}", source_root.trim_end_matches('/'), source),
+    };
+
+    if let Some(path) = source.strip_prefix("file://") {
+        return path.to_string();
+    }
+
+    if source.contains("://") {
+        return source;
+    }
+
+    normalize(&directory.join(source))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The source map of the file at `path` when source maps are enabled, read again whenever the
+/// file changed
+fn get_source_map(ctx: &Ctx<'_>, path: &Path) -> QuickJsResult<Option<Rc<SourceMap>>> {
+    let Some(source_maps) = ctx
+        .globals()
+        .get::<_, Option<Class<SourceMaps>>>(SOURCE_MAPS_KEY)?
+    else {
+        return Ok(None);
+    };
+
+    if !source_maps.borrow().enabled {
+        return Ok(None);
+    }
+
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    if let Some((cached, source_map)) = source_maps.borrow().cache.get(path) {
+        if *cached == modified {
+            return Ok(source_map.clone());
+        }
+    }
+
+    let source_map = match std::fs::read_to_string(path) {
+        Ok(source) => {
+            let directory = path.parent().unwrap_or(Path::new(""));
+
+            match find_source_mapping_url(&source).and_then(|url| read_source_map(url, directory)) {
+                Some((json, directory)) => parse_source_map(ctx, &json, &directory)?.map(Rc::new),
+
+                None => None,
+            }
+        }
+
+        Err(_) => None,
+    };
+
+    source_maps
+        .borrow_mut()
+        .cache
+        .insert(path.to_path_buf(), (modified, source_map.clone()));
+
+    Ok(source_map)
+}
+
+/// Split the `file:line:column` position of a stack frame
+fn parse_location(location: &str) -> Option<(&str, usize, usize)> {
+    let mut parts = location.rsplitn(3, ':');
+
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next()?;
+
+    Some((file, line, column))
+}
+
+/// Map the `file:line:column` position of a stack frame, returning `None` for frames on the
+/// first line of CommonJS modules, which is the header of their function or their ES module
+/// wrapper
+fn map_location(ctx: &Ctx<'_>, location: &str) -> QuickJsResult<Option<String>> {
+    let Some((file, mut line, column)) = parse_location(location) else {
+        return Ok(Some(location.to_string()));
+    };
+
+    if crate::module::is_wrapped(ctx, file)? {
+        if line == 1 {
+            return Ok(None);
+        }
+
+        line -= 1;
+    }
+
+    let path = Path::new(file.strip_prefix("file://").unwrap_or(file));
+
+    let source_map = get_source_map(ctx, path)?;
+
+    let mapped = source_map
+        .as_ref()
+        .and_then(|source_map| source_map.find(line, column));
+
+    Ok(Some(match mapped {
+        Some((source, line, column)) => format!("{}:{}:{}", source, line, column),
+
+        None => format!("{}:{}:{}", file, line, column),
+    }))
+}
+
+/// Map a line of a stack, either `at name (file:line:column)` or `at file:line:column`,
+/// returning `None` for frames that are left out
+fn map_frame(ctx: &Ctx<'_>, frame: &str) -> QuickJsResult<Option<String>> {
+    if !frame.trim_start().starts_with("at ") {
+        return Ok(Some(frame.to_string()));
+    }
+
+    let location = match (frame.rfind('('), frame.strip_suffix(')')) {
+        (Some(start), Some(frame)) => start + 1..frame.len(),
+
+        _ => {
+            let start = frame.find("at ").unwrap_or(0) + "at ".len();
+
+            start..frame.len()
+        }
+    };
+
+    Ok(map_location(ctx, &frame[location.clone()])?.map(|mapped| {
+        format!(
+            "{}{}{}",
+            &frame[..location.start],
+            mapped,
+            &frame[location.end..]
+        )
+    }))
+}
+
+/// Map the frames of `stack` to the lines of the files the code comes from, and to the original
+/// sources of files that have a source map when source maps are enabled
+fn map_stack(ctx: &Ctx<'_>, stack: &str) -> QuickJsResult<String> {
+    let mut mapped = Vec::new();
+
+    for frame in stack.split('\n') {
+        if let Some(frame) = map_frame(ctx, frame)? {
+            mapped.push(frame);
+        }
+    }
+
+    Ok(mapped.join("\n"))
+}
+
+/// The errors whose `stack` was mapped, which mapping again would shift further
+fn get_mapped_errors<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Object<'js>> {
+    let globals = ctx.globals();
+
+    if let Some(errors) = globals.get::<_, Option<Object>>(MAPPED_ERRORS_KEY)? {
+        return Ok(errors);
+    }
+
+    let errors: Object = globals.get::<_, Constructor>("WeakSet")?.construct(())?;
+
+    globals.prop(MAPPED_ERRORS_KEY, errors.clone())?;
+
+    Ok(errors)
+}
+
+/// The `stack` of `error` with its frames mapped, replacing it the first time it is read so that
+/// it is mapped only once and user code sees the mapped frames too
+pub fn get_mapped_stack<'js>(ctx: &Ctx<'js>, error: &Object<'js>) -> QuickJsResult<Option<String>> {
+    let Some(stack) = error.get::<_, Option<String>>("stack")? else {
+        return Ok(None);
+    };
+
+    let errors = get_mapped_errors(ctx)?;
+
+    let has: Function = errors.get("has")?;
+
+    if has.call((This(errors.clone()), error.clone()))? {
+        return Ok(Some(stack));
+    }
+
+    let stack = map_stack(ctx, &stack)?;
+
+    set_mapped_stack(ctx, error, &stack)?;
+
+    Ok(Some(stack))
+}
+
+/// Set the `stack` of `error` to one that was already mapped
+pub fn set_mapped_stack<'js>(
+    ctx: &Ctx<'js>,
+    error: &Object<'js>,
+    stack: &str,
+) -> QuickJsResult<()> {
+    // A frozen error keeps its stack, it is then mapped every time it is read
+    if error.set("stack", stack).is_err() {
+        ctx.catch();
+
+        return Ok(());
+    }
+
+    let errors = get_mapped_errors(ctx)?;
+
+    let add: Function = errors.get("add")?;
+
+    add.call::<_, Value>((This(errors.clone()), error.clone()))?;
+
+    Ok(())
+}
+
+/// Replace the `stack` of `error` with one mapped to the original sources
+pub fn map_error_stack<'js>(ctx: &Ctx<'js>, error: &Object<'js>) -> QuickJsResult<()> {
+    get_mapped_stack(ctx, error).map(|_| ())
+}
+
+/// Create an error with the original constructor called `name` and map its `stack`, `this`
+/// being the `new.target` of the call when it is a constructor call
+fn construct_error<'js>(
+    ctx: Ctx<'js>,
+    name: &str,
+    this: This<Value<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<Value<'js>> {
+    let original: Constructor = ctx
+        .globals()
+        .get::<_, Object>(ERROR_CONSTRUCTORS_KEY)?
+        .get(name)?;
+
+    let new_target = match this.0.is_constructor() {
+        true => this.0,
+
+        false => original.clone().into_value(),
+    };
+
+    let mut argv: Vec<qjs::JSValue> = args.0.iter().map(Value::as_raw).collect();
+
+    let error = unsafe {
+        let value = qjs::JS_CallConstructor2(
+            ctx.as_raw().as_ptr(),
+            original.as_raw(),
+            new_target.as_raw(),
+            argv.len() as c_int,
+            argv.as_mut_ptr(),
+        );
+
+        if qjs::JS_IsException(value) {
+            return Err(Error::Exception);
+        }
+
+        Value::from_raw(ctx.clone(), value)
+    };
+
+    if let Some(object) = error.as_object() {
+        map_error_stack(&ctx, object)?;
+    }
+
+    Ok(error)
+}
+
+/// Set whether stack traces are mapped back to the original sources, wrapping the error
+/// constructors right away when they are. Errors keep their `stack` until it is read through
+/// `get_mapped_stack` otherwise, which spares creating them the cost of mapping
+pub fn init(ctx: &Ctx<'_>, enabled: bool) -> QuickJsResult<()> {
+    ctx.globals().prop(
+        SOURCE_MAPS_KEY,
+        Class::instance(
+            ctx.clone(),
+            SourceMaps {
+                enabled,
+                cache: HashMap::new(),
+            },
+        )?,
+    )?;
+
+    if enabled {
+        wrap_error_constructors(ctx)?;
+    }
+
+    Ok(())
+}
+
+/// Replace the error constructors with ones mapping the `stack` of the errors they create, so
+/// user code reading it sees the frames uncaught errors are reported with, once mapping changes
+/// them because source maps are enabled or a CommonJS module was wrapped
+pub fn wrap_error_constructors(ctx: &Ctx<'_>) -> QuickJsResult<()> {
+    let globals = ctx.globals();
+
+    if globals.contains_key(ERROR_CONSTRUCTORS_KEY)? {
+        return Ok(());
+    }
+
+    let originals = Object::new(ctx.clone())?;
+
+    for name in ERROR_CONSTRUCTORS {
+        let Some(original) = globals.get::<_, Option<Constructor>>(*name)? else {
+            continue;
+        };
+
+        let prototype: Object = original.get("prototype")?;
+        let length: usize = original.get("length")?;
+
+        let constructor = Function::new(ctx.clone(), move |ctx, this, args| {
+            construct_error(ctx, name, this, args)
+        })?
+        .with_constructor(true)
+        .with_name(name)?;
+
+        constructor.set_length(length)?;
+        constructor.prop("prototype", Property::from(prototype.clone()))?;
+
+        // The other errors inherit the static properties of `Error`, replaced first
+        if *name != "Error" {
+            constructor.set_prototype(Some(&globals.get("Error")?))?;
+        }
+
+        prototype.prop(
+            "constructor",
+            Property::from(constructor.clone())
+                .writable()
+                .configurable(),
+        )?;
+
+        globals.prop(*name, Property::from(constructor).writable().configurable())?;
+
+        originals.set(*name, original)?;
+    }
+
+    globals.prop(ERROR_CONSTRUCTORS_KEY, originals)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_vlq() {
+        assert_eq!(decode_vlq("AAAA"), Some(vec![0, 0, 0, 0]));
+        assert_eq!(decode_vlq("CDgB"), Some(vec![1, -1, 16]));
+        assert_eq!(decode_vlq("2H"), Some(vec![123]));
+        assert_eq!(decode_vlq("A!"), None);
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(decode_base64("aGk"), Some(b"hi".to_vec()));
+        assert_eq!(decode_base64("a-_b"), decode_base64("a+/b"));
+        assert_eq!(decode_base64("a*"), None);
+    }
+
+    #[test]
+    fn decodes_mappings() {
+        let lines = decode_mappings("EAAE,AAAA;;ACCA,C");
+
+        let positions: Vec<Vec<_>> = lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|mapping| {
+                        (
+                            mapping.generated_column,
+                            mapping.source,
+                            mapping.line,
+                            mapping.column,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Every value but the generated column carries over to the next lines, segments without a
+        // source are left out
+        assert_eq!(
+            positions,
+            vec![vec![(2, 0, 0, 2), (2, 0, 0, 2)], vec![], vec![(0, 1, 1, 2)]]
+        );
+    }
+
+    #[test]
+    fn finds_positions() {
+        let source_map = SourceMap {
+            sources: vec![String::from("a.ts"), String::from("b.ts")],
+            lines: decode_mappings("CAAA,EAAE;;ACCA"),
+        };
+
+        assert_eq!(source_map.find(1, 1), None);
+        assert_eq!(source_map.find(1, 2), Some(("a.ts", 1, 1)));
+        assert_eq!(source_map.find(1, 4), Some(("a.ts", 1, 3)));
+        assert_eq!(source_map.find(1, 40), Some(("a.ts", 1, 3)));
+        assert_eq!(source_map.find(2, 1), None);
+        assert_eq!(source_map.find(3, 5), Some(("b.ts", 2, 3)));
+        assert_eq!(source_map.find(4, 1), None);
+        assert_eq!(source_map.find(0, 1), None);
+    }
+
+    #[test]
+    fn finds_source_mapping_urls() {
+        let source = "a();\n//# sourceMappingURL=old.map\n//# sourceMappingURL=a.js.map \n";
+
+        assert_eq!(find_source_mapping_url(source), Some("a.js.map"));
+        assert_eq!(parse_location("/a:b.js:3:14"), Some(("/a:b.js", 3, 14)));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::utils::throw_type_error;
+
+use rquickjs::function::Rest;
+use rquickjs::{Ctx, Function, Object, Result as QuickJsResult, TypedArray, Value};
+
+use std::ffi::c_int;
+use std::io::{self, stderr, stdout, BufWriter, ErrorKind, IsTerminal, Write};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+/// A file descriptor written to without any buffering of its own
+struct RawFd(c_int);
+
+impl Write for RawFd {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = unsafe { libc::write(self.0, buf.as_ptr().cast(), buf.len()) };
+
+        if written < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Everything written to stdout goes through this buffer, which is flushed at every newline when
+/// stdout is a terminal and otherwise only once it is full or the process exits
+struct Stdout {
+    writer: BufWriter<RawFd>,
+    line_buffered: bool,
+    broken: bool,
+}
+
+impl Stdout {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.broken {
+            return Ok(());
+        }
+
+        let mut result = self.writer.write_all(bytes);
+
+        if result.is_ok() && self.line_buffered && bytes.contains(&b'\n') {
+            result = self.writer.flush();
+        }
+
+        self.check(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.broken {
+            return Ok(());
+        }
+
+        let result = self.writer.flush();
+
+        self.check(result)
+    }
+
+    /// Drop everything written after the reading end of a pipe was closed
+    fn check(&mut self, result: io::Result<()>) -> io::Result<()> {
+        if let Err(err) = &result {
+            if err.kind() == ErrorKind::BrokenPipe {
+                self.broken = true;
+            }
+        }
+
+        result
+    }
+}
+
+static STDOUT: Lazy<Mutex<Stdout>> = Lazy::new(|| {
+    Mutex::new(Stdout {
+        writer: BufWriter::new(RawFd(libc::STDOUT_FILENO)),
+        line_buffered: stdout().is_terminal(),
+        broken: false,
+    })
+});
+
+fn lock_stdout() -> std::sync::MutexGuard<'static, Stdout> {
+    STDOUT.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Write to the buffered stdout, failing with `BrokenPipe` only for the first write after the
+/// reading end of a pipe was closed
+pub fn write_stdout(bytes: &[u8]) -> io::Result<()> {
+    lock_stdout().write(bytes)
+}
+
+/// Write straight to stderr, after flushing stdout so output to both stays in order when they
+/// are the same terminal or file
+pub fn write_stderr(bytes: &[u8]) -> io::Result<()> {
+    let mut stdout = lock_stdout();
+
+    let _ = stdout.flush();
+
+    let result = stderr().lock().write_all(bytes);
+
+    drop(stdout);
+
+    result
+}
+
+pub fn flush_stdout() -> io::Result<()> {
+    lock_stdout().flush()
+}
+
+/// Handle the result of writing to stdout or stderr, exiting quietly with the current exit code
+/// when the reading end of a pipe was closed and ignoring other errors like Node.js does
+pub fn check_write(ctx: &Ctx<'_>, result: io::Result<()>) -> QuickJsResult<()> {
+    match result {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => crate::process::terminate(ctx, None),
+
+        _ => Ok(()),
+    }
+}
+
+fn get_chunk_bytes<'js>(ctx: &Ctx<'js>, chunk: &Value<'js>) -> QuickJsResult<Vec<u8>> {
+    if let Some(string) = chunk.as_string() {
+        return Ok(string.to_string()?.into_bytes());
+    }
+
+    if let Ok(array) = TypedArray::<u8>::from_value(chunk.clone()) {
+        if let Some(bytes) = array.as_bytes() {
+            return Ok(bytes.to_vec());
+        }
+    }
+
+    Err(throw_type_error(
+        ctx,
+        "ERR_INVALID_ARG_TYPE",
+        "The \"chunk\" argument must be of type string or an instance of Buffer or Uint8Array",
+    ))
+}
+
+/// `write(chunk[, encoding][, callback])`, the callback is called once the chunk was written
+fn write_chunk<'js>(
+    ctx: &Ctx<'js>,
+    fd: c_int,
+    chunk: Value<'js>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<bool> {
+    let bytes = get_chunk_bytes(ctx, &chunk)?;
+
+    let result = if fd == libc::STDOUT_FILENO {
+        write_stdout(&bytes)
+    } else {
+        write_stderr(&bytes)
+    };
+
+    check_write(ctx, result)?;
+
+    if let Some(callback) = args.0.last().and_then(|arg| arg.as_function()) {
+        callback.defer(())?;
+    }
+
+    Ok(true)
+}
+
+fn stdout_write<'js>(
+    ctx: Ctx<'js>,
+    chunk: Value<'js>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<bool> {
+    write_chunk(&ctx, libc::STDOUT_FILENO, chunk, args)
+}
+
+fn stderr_write<'js>(
+    ctx: Ctx<'js>,
+    chunk: Value<'js>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<bool> {
+    write_chunk(&ctx, libc::STDERR_FILENO, chunk, args)
+}
+
+/// Create `process.stdout` or `process.stderr`, sharing their buffering with the console
+pub fn create_stream_object<'js>(ctx: &Ctx<'js>, fd: c_int) -> QuickJsResult<Object<'js>> {
+    let stream = Object::new(ctx.clone())?;
+
+    let write = if fd == libc::STDOUT_FILENO {
+        Function::new(ctx.clone(), stdout_write)?
+    } else {
+        Function::new(ctx.clone(), stderr_write)?
+    };
+
+    let is_terminal = unsafe { libc::isatty(fd) } == 1;
+
+    stream.set("fd", fd)?;
+    stream.set("writable", true)?;
+    stream.set("write", write.with_name("write")?)?;
+
+    if is_terminal {
+        stream.set("isTTY", true)?;
+    }
+
+    Ok(stream)
+}
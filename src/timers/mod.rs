@@ -0,0 +1,561 @@
+pub mod promises;
+
+use crate::utils::{export_default, throw_type_error};
+
+use rquickjs::class::Trace;
+use rquickjs::convert::Coerced;
+use rquickjs::function::{Args, Func, Opt, Rest, This};
+use rquickjs::module::{Declarations, Exports, ModuleDef};
+use rquickjs::{Class, Ctx, Function, Result as QuickJsResult, Symbol, Value};
+
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const TIMERS_KEY: &str = "__yaso_timers";
+
+/// The longest delay a timer accepts, longer or invalid ones fire after 1ms like in Node.js
+const TIMEOUT_MAX: f64 = 2147483647.0;
+
+/// The timers and immediates waiting to run in a context, `refs` counts the ones keeping the
+/// event loop alive
+#[rquickjs::class]
+#[derive(Trace, Default)]
+pub struct Timers<'js> {
+    timeouts: HashMap<u32, Class<'js, Timeout<'js>>>,
+    immediates: VecDeque<Class<'js, Immediate<'js>>>,
+    #[qjs(skip_trace)]
+    deadlines: HashMap<u32, Instant>,
+    #[qjs(skip_trace)]
+    queue: BTreeSet<(Instant, u32)>,
+    #[qjs(skip_trace)]
+    next_id: u32,
+    #[qjs(skip_trace)]
+    refs: usize,
+}
+
+impl<'js> Timers<'js> {
+    fn next_id(&mut self) -> u32 {
+        self.next_id = self.next_id.wrapping_add(1);
+
+        self.next_id
+    }
+
+    fn schedule(&mut self, timeout: Class<'js, Timeout<'js>>, deadline: Instant) {
+        let (id, refed) = {
+            let timeout = timeout.borrow();
+
+            (timeout.id, timeout.refed.get())
+        };
+
+        self.unschedule(id);
+
+        if refed {
+            self.refs += 1;
+        }
+
+        self.timeouts.insert(id, timeout);
+        self.deadlines.insert(id, deadline);
+        self.queue.insert((deadline, id));
+    }
+
+    fn unschedule(&mut self, id: u32) -> Option<Class<'js, Timeout<'js>>> {
+        let timeout = self.timeouts.remove(&id)?;
+
+        if let Some(deadline) = self.deadlines.remove(&id) {
+            self.queue.remove(&(deadline, id));
+        }
+
+        if timeout.borrow().refed.get() {
+            self.refs -= 1;
+        }
+
+        Some(timeout)
+    }
+
+    fn take_due(&mut self, now: Instant) -> Option<Class<'js, Timeout<'js>>> {
+        let &(deadline, id) = self.queue.first()?;
+
+        if deadline > now {
+            return None;
+        }
+
+        self.unschedule(id)
+    }
+
+    fn push_immediate(&mut self, immediate: Class<'js, Immediate<'js>>) {
+        if immediate.borrow().refed.get() {
+            self.refs += 1;
+        }
+
+        self.immediates.push_back(immediate);
+    }
+
+    fn take_immediate(&mut self) -> Option<Class<'js, Immediate<'js>>> {
+        while let Some(immediate) = self.immediates.pop_front() {
+            let (active, refed) = {
+                let immediate = immediate.borrow();
+
+                (immediate.active.get(), immediate.refed.get())
+            };
+
+            if !active {
+                continue;
+            }
+
+            immediate.borrow().active.set(false);
+
+            if refed {
+                self.refs -= 1;
+            }
+
+            return Some(immediate);
+        }
+
+        None
+    }
+
+    /// Update the count of timers keeping the event loop alive when `ref()` or `unref()` changed
+    /// whether an active one does
+    fn set_ref(&mut self, active: bool, was_refed: bool, refed: bool) {
+        if !active || was_refed == refed {
+            return;
+        }
+
+        if refed {
+            self.refs += 1;
+        } else {
+            self.refs -= 1;
+        }
+    }
+}
+
+/// The object `setTimeout` and `setInterval` return
+#[rquickjs::class]
+#[derive(Trace)]
+pub struct Timeout<'js> {
+    callback: Function<'js>,
+    args: Vec<Value<'js>>,
+    #[qjs(skip_trace)]
+    id: u32,
+    #[qjs(skip_trace)]
+    delay: Duration,
+    #[qjs(skip_trace)]
+    repeat: bool,
+    #[qjs(skip_trace)]
+    refed: Cell<bool>,
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> Timeout<'js> {
+    /// Keep the event loop alive until the timer fires, which timers do by default
+    #[qjs(rename = "ref")]
+    pub fn ref_(
+        &self,
+        ctx: Ctx<'js>,
+        this: This<Class<'js, Self>>,
+    ) -> QuickJsResult<Class<'js, Self>> {
+        self.set_ref(&ctx, true)?;
+
+        Ok(this.0)
+    }
+
+    /// Let the process exit while the timer is still waiting
+    pub fn unref(
+        &self,
+        ctx: Ctx<'js>,
+        this: This<Class<'js, Self>>,
+    ) -> QuickJsResult<Class<'js, Self>> {
+        self.set_ref(&ctx, false)?;
+
+        Ok(this.0)
+    }
+
+    pub fn has_ref(&self) -> bool {
+        self.refed.get()
+    }
+
+    /// Restart the timer with its original delay, rescheduling it if it already fired
+    pub fn refresh(
+        &self,
+        ctx: Ctx<'js>,
+        this: This<Class<'js, Self>>,
+    ) -> QuickJsResult<Class<'js, Self>> {
+        let timers = get_timers(&ctx)?;
+
+        timers
+            .borrow_mut()
+            .schedule(this.0.clone(), Instant::now() + self.delay);
+
+        Ok(this.0)
+    }
+
+    pub fn close(
+        &self,
+        ctx: Ctx<'js>,
+        this: This<Class<'js, Self>>,
+    ) -> QuickJsResult<Class<'js, Self>> {
+        get_timers(&ctx)?.borrow_mut().unschedule(self.id);
+
+        Ok(this.0)
+    }
+}
+
+impl<'js> Timeout<'js> {
+    fn set_ref(&self, ctx: &Ctx<'js>, refed: bool) -> QuickJsResult<()> {
+        let timers = get_timers(ctx)?;
+        let mut timers = timers.borrow_mut();
+
+        let active = timers.timeouts.contains_key(&self.id);
+
+        timers.set_ref(active, self.refed.replace(refed), refed);
+
+        Ok(())
+    }
+}
+
+/// The object `setImmediate` returns
+#[rquickjs::class]
+#[derive(Trace)]
+pub struct Immediate<'js> {
+    callback: Function<'js>,
+    args: Vec<Value<'js>>,
+    #[qjs(skip_trace)]
+    active: Cell<bool>,
+    #[qjs(skip_trace)]
+    refed: Cell<bool>,
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> Immediate<'js> {
+    /// Keep the event loop alive until the immediate runs, which immediates do by default
+    #[qjs(rename = "ref")]
+    pub fn ref_(
+        &self,
+        ctx: Ctx<'js>,
+        this: This<Class<'js, Self>>,
+    ) -> QuickJsResult<Class<'js, Self>> {
+        self.set_ref(&ctx, true)?;
+
+        Ok(this.0)
+    }
+
+    /// Let the process exit before the immediate runs
+    pub fn unref(
+        &self,
+        ctx: Ctx<'js>,
+        this: This<Class<'js, Self>>,
+    ) -> QuickJsResult<Class<'js, Self>> {
+        self.set_ref(&ctx, false)?;
+
+        Ok(this.0)
+    }
+
+    pub fn has_ref(&self) -> bool {
+        self.refed.get()
+    }
+}
+
+impl<'js> Immediate<'js> {
+    fn set_ref(&self, ctx: &Ctx<'js>, refed: bool) -> QuickJsResult<()> {
+        let timers = get_timers(ctx)?;
+
+        timers
+            .borrow_mut()
+            .set_ref(self.active.get(), self.refed.replace(refed), refed);
+
+        Ok(())
+    }
+}
+
+/// The id of a timer, which `clearTimeout` accepts in place of the object
+fn to_primitive<'js>(this: This<Class<'js, Timeout<'js>>>) -> u32 {
+    this.borrow().id
+}
+
+/// Get the timers of this context, creating them on first use
+fn get_timers<'js>(ctx: &Ctx<'js>) -> QuickJsResult<Class<'js, Timers<'js>>> {
+    let globals = ctx.globals();
+
+    if let Some(timers) = globals.get::<_, Option<Class<Timers>>>(TIMERS_KEY)? {
+        return Ok(timers);
+    }
+
+    let timers = Class::instance(ctx.clone(), Timers::default())?;
+
+    globals.prop(TIMERS_KEY, timers.clone())?;
+
+    Class::<Timeout>::register(ctx)?;
+
+    if let Some(prototype) = Class::<Timeout>::prototype(ctx.clone()) {
+        prototype.set(Symbol::to_primitive(ctx.clone()), Func::from(to_primitive))?;
+    }
+
+    Ok(timers)
+}
+
+/// When the event loop has to wake up to run a timer, which is right away if an immediate is
+/// waiting
+pub fn next_deadline(ctx: &Ctx<'_>) -> QuickJsResult<Option<Instant>> {
+    let timers = get_timers(ctx)?;
+    let timers = timers.borrow();
+
+    if timers
+        .immediates
+        .iter()
+        .any(|immediate| immediate.borrow().active.get())
+    {
+        return Ok(Some(Instant::now()));
+    }
+
+    Ok(timers.queue.first().map(|&(deadline, _)| deadline))
+}
+
+/// Whether a timer or immediate that was not `unref()`ed is waiting
+pub fn has_refs(ctx: &Ctx<'_>) -> QuickJsResult<bool> {
+    Ok(get_timers(ctx)?.borrow().refs > 0)
+}
+
+fn call<'js>(
+    ctx: &Ctx<'js>,
+    callback: Function<'js>,
+    this: Value<'js>,
+    args: Vec<Value<'js>>,
+) -> QuickJsResult<()> {
+    let mut call = Args::new(ctx.clone(), args.len());
+
+    call.this(this)?;
+    call.push_args(args)?;
+
+    callback.call_arg(call)
+}
+
+/// Run the earliest timer that is due, or else the oldest immediate, returning whether anything
+/// ran
+pub fn run_next(ctx: &Ctx<'_>) -> QuickJsResult<bool> {
+    let timers = get_timers(ctx)?;
+
+    let now = Instant::now();
+
+    let timeout = timers.borrow_mut().take_due(now);
+
+    if let Some(timeout) = timeout {
+        let (callback, args, delay, repeat) = {
+            let timeout = timeout.borrow();
+
+            (
+                timeout.callback.clone(),
+                timeout.args.clone(),
+                timeout.delay,
+                timeout.repeat,
+            )
+        };
+
+        // Intervals are rescheduled before their callback runs, so they can clear themselves
+        if repeat {
+            timers.borrow_mut().schedule(timeout.clone(), now + delay);
+        }
+
+        call(ctx, callback, timeout.into_value(), args)?;
+
+        return Ok(true);
+    }
+
+    let immediate = timers.borrow_mut().take_immediate();
+
+    if let Some(immediate) = immediate {
+        let (callback, args) = {
+            let borrow = immediate.borrow();
+
+            (borrow.callback.clone(), borrow.args.clone())
+        };
+
+        call(ctx, callback, immediate.into_value(), args)?;
+
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+fn get_callback<'js>(ctx: &Ctx<'js>, callback: Value<'js>) -> QuickJsResult<Function<'js>> {
+    match callback.into_function() {
+        Some(callback) => Ok(callback),
+
+        None => Err(throw_type_error(
+            ctx,
+            "ERR_INVALID_ARG_TYPE",
+            "The \"callback\" argument must be of type function",
+        )),
+    }
+}
+
+fn get_delay(delay: Opt<Value<'_>>) -> QuickJsResult<Duration> {
+    let delay = match delay.0 {
+        Some(delay) if !delay.is_undefined() => delay.get::<Coerced<f64>>()?.0,
+
+        _ => 1.0,
+    };
+
+    let delay = if (1.0..=TIMEOUT_MAX).contains(&delay) {
+        delay
+    } else {
+        1.0
+    };
+
+    Ok(Duration::from_secs_f64(delay / 1000.0))
+}
+
+/// Schedule `callback` to be called with `args` after `delay`, and every `delay` after that if it
+/// repeats
+pub fn create_timeout<'js>(
+    ctx: &Ctx<'js>,
+    callback: Function<'js>,
+    args: Vec<Value<'js>>,
+    delay: Duration,
+    repeat: bool,
+    refed: bool,
+) -> QuickJsResult<Class<'js, Timeout<'js>>> {
+    let timers = get_timers(ctx)?;
+
+    let id = timers.borrow_mut().next_id();
+
+    let timeout = Class::instance(
+        ctx.clone(),
+        Timeout {
+            callback,
+            args,
+            id,
+            delay,
+            repeat,
+            refed: Cell::new(refed),
+        },
+    )?;
+
+    timers
+        .borrow_mut()
+        .schedule(timeout.clone(), Instant::now() + delay);
+
+    Ok(timeout)
+}
+
+/// Schedule `callback` to be called with `args` once the current turn of the event loop is over
+pub fn create_immediate<'js>(
+    ctx: &Ctx<'js>,
+    callback: Function<'js>,
+    args: Vec<Value<'js>>,
+    refed: bool,
+) -> QuickJsResult<Class<'js, Immediate<'js>>> {
+    let immediate = Class::instance(
+        ctx.clone(),
+        Immediate {
+            callback,
+            args,
+            active: Cell::new(true),
+            refed: Cell::new(refed),
+        },
+    )?;
+
+    get_timers(ctx)?
+        .borrow_mut()
+        .push_immediate(immediate.clone());
+
+    Ok(immediate)
+}
+
+pub fn set_timeout<'js>(
+    ctx: Ctx<'js>,
+    callback: Value<'js>,
+    delay: Opt<Value<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<Class<'js, Timeout<'js>>> {
+    let callback = get_callback(&ctx, callback)?;
+
+    create_timeout(&ctx, callback, args.0, get_delay(delay)?, false, true)
+}
+
+pub fn set_interval<'js>(
+    ctx: Ctx<'js>,
+    callback: Value<'js>,
+    delay: Opt<Value<'js>>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<Class<'js, Timeout<'js>>> {
+    let callback = get_callback(&ctx, callback)?;
+
+    create_timeout(&ctx, callback, args.0, get_delay(delay)?, true, true)
+}
+
+/// Cancel a timer given its object or the id it turns into, clearing both timeouts and intervals
+pub fn clear_timeout<'js>(ctx: Ctx<'js>, timer: Opt<Value<'js>>) -> QuickJsResult<()> {
+    let Some(timer) = timer.0 else {
+        return Ok(());
+    };
+
+    let id = if let Ok(timeout) = Class::<Timeout>::from_value(timer.clone()) {
+        timeout.borrow().id
+    } else if timer.is_number() || timer.is_string() {
+        timer.get::<Coerced<f64>>()?.0 as u32
+    } else {
+        return Ok(());
+    };
+
+    get_timers(&ctx)?.borrow_mut().unschedule(id);
+
+    Ok(())
+}
+
+pub fn set_immediate<'js>(
+    ctx: Ctx<'js>,
+    callback: Value<'js>,
+    args: Rest<Value<'js>>,
+) -> QuickJsResult<Class<'js, Immediate<'js>>> {
+    let callback = get_callback(&ctx, callback)?;
+
+    create_immediate(&ctx, callback, args.0, true)
+}
+
+pub fn clear_immediate<'js>(ctx: Ctx<'js>, immediate: Opt<Value<'js>>) -> QuickJsResult<()> {
+    let Some(immediate) = immediate
+        .0
+        .and_then(|immediate| Class::<Immediate>::from_value(immediate).ok())
+    else {
+        return Ok(());
+    };
+
+    let immediate = immediate.borrow();
+
+    if immediate.active.replace(false) && immediate.refed.get() {
+        get_timers(&ctx)?.borrow_mut().refs -= 1;
+    }
+
+    Ok(())
+}
+
+pub struct TimersModule;
+
+impl ModuleDef for TimersModule {
+    fn declare(declare: &mut Declarations) -> QuickJsResult<()> {
+        declare.declare("setTimeout")?;
+        declare.declare("clearTimeout")?;
+        declare.declare("setInterval")?;
+        declare.declare("clearInterval")?;
+        declare.declare("setImmediate")?;
+        declare.declare("clearImmediate")?;
+        declare.declare("default")?;
+
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &mut Exports<'js>) -> QuickJsResult<()> {
+        export_default(ctx, exports, |default| {
+            default.set("setTimeout", Func::from(set_timeout))?;
+            default.set("clearTimeout", Func::from(clear_timeout))?;
+            default.set("setInterval", Func::from(set_interval))?;
+            default.set("clearInterval", Func::from(clear_timeout))?;
+            default.set("setImmediate", Func::from(set_immediate))?;
+            default.set("clearImmediate", Func::from(clear_immediate))?;
+
+            Ok(())
+        })
+    }
+}
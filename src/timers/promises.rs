@@ -0,0 +1,198 @@
+use super::{clear_timeout, create_immediate, create_timeout, get_delay};
+use crate::utils::export_default;
+
+use rquickjs::function::{Func, Opt, This};
+use rquickjs::module::{Declarations, Exports, ModuleDef};
+use rquickjs::object::Property;
+use rquickjs::{Array, Ctx, Function, Object, Result as QuickJsResult, Symbol, Value};
+
+/// Whether the timer keeps the event loop alive, `options.ref` defaulting to true
+fn get_ref(options: &Opt<Object<'_>>) -> QuickJsResult<bool> {
+    match &options.0 {
+        Some(options) => Ok(options.get::<_, Option<bool>>("ref")?.unwrap_or(true)),
+
+        None => Ok(true),
+    }
+}
+
+fn get_value<'js>(ctx: &Ctx<'js>, value: Opt<Value<'js>>) -> Value<'js> {
+    value.0.unwrap_or_else(|| Value::new_undefined(ctx.clone()))
+}
+
+/// Resolve with `value` after `delay`
+fn set_timeout<'js>(
+    ctx: Ctx<'js>,
+    delay: Opt<Value<'js>>,
+    value: Opt<Value<'js>>,
+    options: Opt<Object<'js>>,
+) -> QuickJsResult<Object<'js>> {
+    let (promise, resolve, _) = ctx.promise()?;
+
+    let value = get_value(&ctx, value);
+
+    create_timeout(
+        &ctx,
+        resolve,
+        vec![value],
+        get_delay(delay)?,
+        false,
+        get_ref(&options)?,
+    )?;
+
+    Ok(promise)
+}
+
+/// Resolve with `value` once the current turn of the event loop is over
+fn set_immediate<'js>(
+    ctx: Ctx<'js>,
+    value: Opt<Value<'js>>,
+    options: Opt<Object<'js>>,
+) -> QuickJsResult<Object<'js>> {
+    let (promise, resolve, _) = ctx.promise()?;
+
+    let value = get_value(&ctx, value);
+
+    create_immediate(&ctx, resolve, vec![value], get_ref(&options)?)?;
+
+    Ok(promise)
+}
+
+fn create_iterator_result<'js>(
+    ctx: &Ctx<'js>,
+    value: Value<'js>,
+    done: bool,
+) -> QuickJsResult<Object<'js>> {
+    let result = Object::new(ctx.clone())?;
+
+    result.set("value", value)?;
+    result.set("done", done)?;
+
+    Ok(result)
+}
+
+/// Hand the value of the interval to the oldest pending `next()` call, or keep count of it until
+/// the next one
+fn tick<'js>(ctx: Ctx<'js>, iterator: Object<'js>) -> QuickJsResult<()> {
+    let resolvers: Array = iterator.get("_resolvers")?;
+
+    if resolvers.is_empty() {
+        let ticks: u32 = iterator.get("_ticks")?;
+
+        return iterator.set("_ticks", ticks + 1);
+    }
+
+    let shift: Function = resolvers.as_object().get("shift")?;
+    let resolve: Function = shift.call((This(resolvers),))?;
+
+    resolve.call((create_iterator_result(
+        &ctx,
+        iterator.get("_value")?,
+        false,
+    )?,))
+}
+
+fn next<'js>(ctx: Ctx<'js>, this: This<Object<'js>>) -> QuickJsResult<Object<'js>> {
+    let (promise, resolve, _) = ctx.promise()?;
+
+    let ticks: u32 = this.get("_ticks")?;
+
+    if this.get("_done")? {
+        let undefined = Value::new_undefined(ctx.clone());
+
+        resolve.call::<_, ()>((create_iterator_result(&ctx, undefined, true)?,))?;
+    } else if ticks > 0 {
+        this.set("_ticks", ticks - 1)?;
+
+        resolve.call::<_, ()>((create_iterator_result(&ctx, this.get("_value")?, false)?,))?;
+    } else {
+        let resolvers: Array = this.get("_resolvers")?;
+
+        resolvers.set(resolvers.len(), resolve)?;
+    }
+
+    Ok(promise)
+}
+
+/// Stop the interval, finishing the pending `next()` calls
+fn finish<'js>(ctx: Ctx<'js>, this: This<Object<'js>>) -> QuickJsResult<Object<'js>> {
+    this.set("_done", true)?;
+
+    clear_timeout(ctx.clone(), Opt(Some(this.get("_timer")?)))?;
+
+    let resolvers: Array = this.get("_resolvers")?;
+
+    for resolve in resolvers.iter::<Function>() {
+        let undefined = Value::new_undefined(ctx.clone());
+
+        resolve?.call::<_, ()>((create_iterator_result(&ctx, undefined, true)?,))?;
+    }
+
+    this.set("_resolvers", Array::new(ctx.clone())?)?;
+
+    next(ctx, this)
+}
+
+fn get_iterator<'js>(this: This<Object<'js>>) -> Object<'js> {
+    this.0
+}
+
+/// An async iterator yielding `value` every `delay`
+fn set_interval<'js>(
+    ctx: Ctx<'js>,
+    delay: Opt<Value<'js>>,
+    value: Opt<Value<'js>>,
+    options: Opt<Object<'js>>,
+) -> QuickJsResult<Object<'js>> {
+    let iterator = Object::new(ctx.clone())?;
+
+    iterator.prop("_value", get_value(&ctx, value))?;
+    iterator.prop(
+        "_resolvers",
+        Property::from(Array::new(ctx.clone())?).writable(),
+    )?;
+    iterator.prop("_ticks", Property::from(0).writable())?;
+    iterator.prop("_done", Property::from(false).writable())?;
+
+    iterator.set("next", Func::from(next))?;
+    iterator.set("return", Func::from(finish))?;
+    iterator.set(
+        Symbol::async_iterator(ctx.clone()),
+        Func::from(get_iterator),
+    )?;
+
+    let timer = create_timeout(
+        &ctx,
+        Function::new(ctx.clone(), tick)?,
+        vec![iterator.clone().into_value()],
+        get_delay(delay)?,
+        true,
+        get_ref(&options)?,
+    )?;
+
+    iterator.prop("_timer", timer)?;
+
+    Ok(iterator)
+}
+
+pub struct TimersPromisesModule;
+
+impl ModuleDef for TimersPromisesModule {
+    fn declare(declare: &mut Declarations) -> QuickJsResult<()> {
+        declare.declare("setTimeout")?;
+        declare.declare("setImmediate")?;
+        declare.declare("setInterval")?;
+        declare.declare("default")?;
+
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &mut Exports<'js>) -> QuickJsResult<()> {
+        export_default(ctx, exports, |default| {
+            default.set("setTimeout", Func::from(set_timeout))?;
+            default.set("setImmediate", Func::from(set_immediate))?;
+            default.set("setInterval", Func::from(set_interval))?;
+
+            Ok(())
+        })
+    }
+}
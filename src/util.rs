@@ -0,0 +1,608 @@
+use crate::console::Stream;
+use crate::inspect::COLORS;
+use crate::utils::{export_default, throw_type_error};
+
+use rquickjs::function::{Func, Opt};
+use rquickjs::module::{Declarations, Exports, ModuleDef};
+use rquickjs::{Array, Ctx, Function, Object, Result as QuickJsResult, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptionType {
+    Boolean,
+    String,
+}
+
+struct ArgOption<'js> {
+    name: String,
+    kind: OptionType,
+    short: Option<char>,
+    multiple: bool,
+    default: Option<Value<'js>>,
+}
+
+struct ParseArgsConfig<'js> {
+    args: Vec<String>,
+    options: Vec<ArgOption<'js>>,
+    strict: bool,
+    allow_positionals: bool,
+    allow_negative: bool,
+    tokens: bool,
+}
+
+fn get_default_args(ctx: &Ctx<'_>) -> QuickJsResult<Vec<String>> {
+    let process = crate::process::get_process_object(ctx)?;
+
+    let argv: Vec<String> = process.get("argv")?;
+
+    Ok(argv.into_iter().skip(2).collect())
+}
+
+fn read_config<'js>(
+    ctx: &Ctx<'js>,
+    config: Option<Object<'js>>,
+) -> QuickJsResult<ParseArgsConfig<'js>> {
+    let config = match config {
+        Some(config) => config,
+
+        None => Object::new(ctx.clone())?,
+    };
+
+    let args = match config.get::<_, Option<Vec<String>>>("args")? {
+        Some(args) => args,
+
+        None => get_default_args(ctx)?,
+    };
+
+    let strict = config.get::<_, Option<bool>>("strict")?.unwrap_or(true);
+
+    let allow_positionals = config
+        .get::<_, Option<bool>>("allowPositionals")?
+        .unwrap_or(!strict);
+
+    let allow_negative = config
+        .get::<_, Option<bool>>("allowNegative")?
+        .unwrap_or(false);
+
+    let tokens = config.get::<_, Option<bool>>("tokens")?.unwrap_or(false);
+
+    let mut options = Vec::new();
+
+    if let Some(configs) = config.get::<_, Option<Object>>("options")? {
+        for name in configs.keys::<String>() {
+            let name = name?;
+
+            let option: Object = configs.get(&name)?;
+
+            let kind = match option.get::<_, Option<String>>("type")?.as_deref() {
+                Some("boolean") => OptionType::Boolean,
+
+                Some("string") => OptionType::String,
+
+                _ => {
+                    return Err(throw_type_error(
+                        ctx,
+                        "ERR_INVALID_ARG_VALUE",
+                        &format!(
+                            "The option '{}' must have a type of 'boolean' or 'string'",
+                            name
+                        ),
+                    ))
+                }
+            };
+
+            let short = match option.get::<_, Option<String>>("short")? {
+                Some(short) if short.chars().count() == 1 => short.chars().next(),
+
+                Some(_) => {
+                    return Err(throw_type_error(
+                        ctx,
+                        "ERR_INVALID_ARG_VALUE",
+                        &format!(
+                            "The short alias of option '{}' must be a single character",
+                            name
+                        ),
+                    ))
+                }
+
+                None => None,
+            };
+
+            let multiple = option.get::<_, Option<bool>>("multiple")?.unwrap_or(false);
+
+            let default = option
+                .get::<_, Option<Value>>("default")?
+                .filter(|default| !default.is_undefined());
+
+            options.push(ArgOption {
+                name,
+                kind,
+                short,
+                multiple,
+                default,
+            });
+        }
+    }
+
+    Ok(ParseArgsConfig {
+        args,
+        options,
+        strict,
+        allow_positionals,
+        allow_negative,
+        tokens,
+    })
+}
+
+fn store_value<'js>(
+    ctx: &Ctx<'js>,
+    values: &Object<'js>,
+    name: &str,
+    value: Value<'js>,
+    multiple: bool,
+) -> QuickJsResult<()> {
+    if !multiple {
+        return values.set(name, value);
+    }
+
+    let array = match values.get::<_, Option<Array>>(name)? {
+        Some(array) => array,
+
+        None => {
+            let array = Array::new(ctx.clone())?;
+
+            values.set(name, array.clone())?;
+
+            array
+        }
+    };
+
+    array.set(array.len(), value)
+}
+
+/// Add a token of `kind` when tokens were asked for, returning it for the fields of its kind to
+/// be set in the order Node.js sets them
+fn push_token<'js>(
+    ctx: &Ctx<'js>,
+    tokens: Option<&Array<'js>>,
+    kind: &str,
+) -> QuickJsResult<Option<Object<'js>>> {
+    let Some(tokens) = tokens else {
+        return Ok(None);
+    };
+
+    let token = Object::new(ctx.clone())?;
+
+    token.set("kind", kind)?;
+
+    tokens.set(tokens.len(), token.clone())?;
+
+    Ok(Some(token))
+}
+
+/// Add the token of an option, `inline_value` telling whether its value was part of the same
+/// argument, both left undefined for options without a value
+fn push_option_token<'js>(
+    ctx: &Ctx<'js>,
+    tokens: Option<&Array<'js>>,
+    name: &str,
+    raw_name: &str,
+    index: usize,
+    value: Option<&str>,
+    inline_value: Option<bool>,
+) -> QuickJsResult<()> {
+    if let Some(token) = push_token(ctx, tokens, "option")? {
+        token.set("name", name)?;
+        token.set("rawName", raw_name)?;
+        token.set("index", index)?;
+        token.set("value", value)?;
+        token.set("inlineValue", inline_value)?;
+    }
+
+    Ok(())
+}
+
+fn push_positional_token<'js>(
+    ctx: &Ctx<'js>,
+    tokens: Option<&Array<'js>>,
+    index: usize,
+    value: &str,
+) -> QuickJsResult<()> {
+    if let Some(token) = push_token(ctx, tokens, "positional")? {
+        token.set("index", index)?;
+        token.set("value", value)?;
+    }
+
+    Ok(())
+}
+
+/// Parse command line flags according to the options given, mirroring Node.js' `util.parseArgs`
+fn parse_args<'js>(ctx: Ctx<'js>, config: Opt<Object<'js>>) -> QuickJsResult<Object<'js>> {
+    let config = read_config(&ctx, config.0)?;
+
+    let values = Object::new(ctx.clone())?;
+
+    values.set_prototype(None)?;
+
+    let positionals = Array::new(ctx.clone())?;
+
+    let tokens = match config.tokens {
+        true => Some(Array::new(ctx.clone())?),
+
+        false => None,
+    };
+
+    let mut args = config.args.iter().enumerate();
+
+    while let Some((index, arg)) = args.next() {
+        if arg == "--" {
+            if let Some(token) = push_token(&ctx, tokens.as_ref(), "option-terminator")? {
+                token.set("index", index)?;
+            }
+
+            for (index, arg) in args.by_ref() {
+                if !config.allow_positionals {
+                    return Err(throw_unexpected_positional(&ctx, arg));
+                }
+
+                positionals.set(positionals.len(), arg.as_str())?;
+
+                push_positional_token(&ctx, tokens.as_ref(), index, arg)?;
+            }
+
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+
+                None => (long, None),
+            };
+
+            let option = config.options.iter().find(|option| option.name == name);
+
+            let negated = name.strip_prefix("no-").and_then(|name| {
+                config.options.iter().find(|option| {
+                    config.allow_negative
+                        && option.name == name
+                        && option.kind == OptionType::Boolean
+                })
+            });
+
+            match (option, negated) {
+                (Some(option), _) => {
+                    let value = match option.kind {
+                        OptionType::Boolean => {
+                            if inline_value.is_some() && config.strict {
+                                return Err(throw_type_error(
+                                    &ctx,
+                                    "ERR_PARSE_ARGS_INVALID_OPTION_VALUE",
+                                    &format!("Option '--{}' does not take an argument", name),
+                                ));
+                            }
+
+                            push_option_token(
+                                &ctx,
+                                tokens.as_ref(),
+                                name,
+                                &format!("--{}", name),
+                                index,
+                                inline_value.as_deref(),
+                                inline_value.is_some().then_some(true),
+                            )?;
+
+                            Value::new_bool(ctx.clone(), true)
+                        }
+
+                        OptionType::String => {
+                            let value = match &inline_value {
+                                Some(value) => Some(value.clone()),
+
+                                None => args.next().map(|(_, value)| value.clone()),
+                            };
+
+                            let Some(value) = value else {
+                                return Err(throw_missing_value(&ctx, &format!("--{}", name)));
+                            };
+
+                            push_option_token(
+                                &ctx,
+                                tokens.as_ref(),
+                                name,
+                                &format!("--{}", name),
+                                index,
+                                Some(&value),
+                                Some(inline_value.is_some()),
+                            )?;
+
+                            rquickjs::String::from_str(ctx.clone(), &value)?.into_value()
+                        }
+                    };
+
+                    store_value(&ctx, &values, &option.name, value, option.multiple)?;
+                }
+
+                (None, Some(option)) => {
+                    let value = Value::new_bool(ctx.clone(), false);
+
+                    push_option_token(
+                        &ctx,
+                        tokens.as_ref(),
+                        &option.name,
+                        &format!("--{}", name),
+                        index,
+                        None,
+                        None,
+                    )?;
+
+                    store_value(&ctx, &values, &option.name, value, option.multiple)?;
+                }
+
+                (None, None) => {
+                    if config.strict {
+                        return Err(throw_unknown_option(&ctx, &format!("--{}", name)));
+                    }
+
+                    push_option_token(
+                        &ctx,
+                        tokens.as_ref(),
+                        name,
+                        &format!("--{}", name),
+                        index,
+                        inline_value.as_deref(),
+                        inline_value.is_some().then_some(true),
+                    )?;
+
+                    match inline_value {
+                        Some(value) => values.set(name, value)?,
+
+                        None => values.set(name, true)?,
+                    }
+                }
+            }
+
+            continue;
+        }
+
+        if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            for (i, short) in shorts.char_indices() {
+                let option = config
+                    .options
+                    .iter()
+                    .find(|option| option.short == Some(short));
+
+                let Some(option) = option else {
+                    if config.strict {
+                        return Err(throw_unknown_option(&ctx, &format!("-{}", short)));
+                    }
+
+                    push_option_token(
+                        &ctx,
+                        tokens.as_ref(),
+                        &short.to_string(),
+                        &format!("-{}", short),
+                        index,
+                        None,
+                        None,
+                    )?;
+
+                    values.set(short.to_string(), true)?;
+
+                    continue;
+                };
+
+                if option.kind == OptionType::Boolean {
+                    push_option_token(
+                        &ctx,
+                        tokens.as_ref(),
+                        &option.name,
+                        &format!("-{}", short),
+                        index,
+                        None,
+                        None,
+                    )?;
+
+                    let value = Value::new_bool(ctx.clone(), true);
+
+                    store_value(&ctx, &values, &option.name, value, option.multiple)?;
+
+                    continue;
+                }
+
+                // A string option takes the rest of the group, or the next argument
+                let rest = &shorts[i + short.len_utf8()..];
+
+                let value = if rest.is_empty() {
+                    args.next().map(|(_, value)| value.clone())
+                } else {
+                    Some(rest.to_string())
+                };
+
+                let Some(value) = value else {
+                    return Err(throw_missing_value(&ctx, &format!("-{}", short)));
+                };
+
+                push_option_token(
+                    &ctx,
+                    tokens.as_ref(),
+                    &option.name,
+                    &format!("-{}", short),
+                    index,
+                    Some(&value),
+                    Some(!rest.is_empty()),
+                )?;
+
+                let value = rquickjs::String::from_str(ctx.clone(), &value)?.into_value();
+
+                store_value(&ctx, &values, &option.name, value, option.multiple)?;
+
+                break;
+            }
+
+            continue;
+        }
+
+        if !config.allow_positionals {
+            return Err(throw_unexpected_positional(&ctx, arg));
+        }
+
+        positionals.set(positionals.len(), arg.as_str())?;
+
+        push_positional_token(&ctx, tokens.as_ref(), index, arg)?;
+    }
+
+    for option in config.options.iter() {
+        if let Some(default) = &option.default {
+            if !values.contains_key(option.name.as_str())? {
+                values.set(option.name.as_str(), default.clone())?;
+            }
+        }
+    }
+
+    let result = Object::new(ctx)?;
+
+    result.set("values", values)?;
+    result.set("positionals", positionals)?;
+
+    if let Some(tokens) = tokens {
+        result.set("tokens", tokens)?;
+    }
+
+    Ok(result)
+}
+
+fn throw_unknown_option(ctx: &Ctx<'_>, option: &str) -> rquickjs::Error {
+    throw_type_error(
+        ctx,
+        "ERR_PARSE_ARGS_UNKNOWN_OPTION",
+        &format!("Unknown option '{}'", option),
+    )
+}
+
+fn throw_missing_value(ctx: &Ctx<'_>, option: &str) -> rquickjs::Error {
+    throw_type_error(
+        ctx,
+        "ERR_PARSE_ARGS_INVALID_OPTION_VALUE",
+        &format!("Option '{} <value>' argument missing", option),
+    )
+}
+
+fn throw_unexpected_positional(ctx: &Ctx<'_>, arg: &str) -> rquickjs::Error {
+    throw_type_error(
+        ctx,
+        "ERR_PARSE_ARGS_UNEXPECTED_POSITIONAL",
+        &format!(
+            "Unexpected argument '{}'. This command does not take positional arguments",
+            arg
+        ),
+    )
+}
+
+/// Wrap `text` in the escape codes of the colors and modifiers named by `format`, leaving it as is
+/// when stdout is not colored and `validateStream` is not turned off
+fn style_text<'js>(
+    ctx: Ctx<'js>,
+    format: Value<'js>,
+    text: String,
+    options: Opt<Object<'js>>,
+) -> QuickJsResult<String> {
+    let formats: Vec<String> = match format.as_string() {
+        Some(format) => vec![format.to_string()?],
+
+        None => match format.as_array() {
+            Some(formats) => formats.iter().collect::<QuickJsResult<_>>()?,
+
+            None => {
+                return Err(throw_type_error(
+                    &ctx,
+                    "ERR_INVALID_ARG_TYPE",
+                    "The \"format\" argument must be of type string or an instance of Array",
+                ))
+            }
+        },
+    };
+
+    let mut codes = Vec::new();
+
+    for format in formats.iter() {
+        if format == "none" {
+            continue;
+        }
+
+        match COLORS.iter().find(|(name, _, _)| name == format) {
+            Some((_, open, close)) => codes.push((*open, *close)),
+
+            None => {
+                let names: Vec<&str> = COLORS.iter().map(|(name, _, _)| *name).collect();
+
+                return Err(throw_type_error(
+                    &ctx,
+                    "ERR_INVALID_ARG_VALUE",
+                    &format!(
+                        "The argument 'format' must be one of: 'none', '{}'. Received '{}'",
+                        names.join("', '"),
+                        format
+                    ),
+                ));
+            }
+        }
+    }
+
+    let validate_stream = match options.0 {
+        Some(options) => options
+            .get::<_, Option<bool>>("validateStream")?
+            .unwrap_or(true),
+
+        None => true,
+    };
+
+    if validate_stream && !crate::console::has_colors(&ctx, Stream::Stdout)? {
+        return Ok(text);
+    }
+
+    let mut result = text;
+
+    for (open, close) in codes.into_iter().rev() {
+        result = format!("\x1b[{}m{}\x1b[{}m", open, result, close);
+    }
+
+    Ok(result)
+}
+
+pub struct UtilModule;
+
+impl ModuleDef for UtilModule {
+    fn declare(declare: &mut Declarations) -> QuickJsResult<()> {
+        declare.declare("format")?;
+        declare.declare("formatWithOptions")?;
+        declare.declare("inspect")?;
+        declare.declare("parseArgs")?;
+        declare.declare("styleText")?;
+        declare.declare("default")?;
+
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &mut Exports<'js>) -> QuickJsResult<()> {
+        export_default(ctx, exports, |default| {
+            let inspect = Function::new(ctx.clone(), crate::inspect::js_inspect)?;
+
+            let (colors, styles) = crate::inspect::create_colors_objects(ctx)?;
+
+            inspect.set("custom", crate::inspect::get_custom_inspect_symbol(ctx)?)?;
+            inspect.set("colors", colors)?;
+            inspect.set("styles", styles)?;
+
+            default.set("format", Func::from(crate::inspect::js_format))?;
+            default.set(
+                "formatWithOptions",
+                Func::from(crate::inspect::js_format_with_options),
+            )?;
+            default.set("inspect", inspect)?;
+            default.set("parseArgs", Func::from(parse_args))?;
+            default.set("styleText", Func::from(style_text))?;
+
+            Ok(())
+        })
+    }
+}
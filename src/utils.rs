@@ -1,5 +1,9 @@
+use rquickjs::function::Constructor;
 use rquickjs::module::Exports;
-use rquickjs::{Ctx, Object, Result as QuickJsResult, Value};
+use rquickjs::{qjs, Ctx, Error, Exception, Object, Result as QuickJsResult, Value};
+
+use std::ffi::{c_int, CString};
+use std::io;
 
 pub fn export_default<'js, F>(ctx: &Ctx<'js>, exports: &mut Exports<'js>, f: F) -> QuickJsResult<()>
 where
@@ -9,6 +13,10 @@ where
 
     f(&default)?;
 
+    export_object(exports, default)
+}
+
+pub fn export_object<'js>(exports: &mut Exports<'js>, default: Object<'js>) -> QuickJsResult<()> {
     for name in default.keys::<String>() {
         let name = name?;
         let value: Value = default.get(name.clone())?;
@@ -20,3 +28,113 @@ where
 
     Ok(())
 }
+
+/// Evaluate `source` as a global script, naming it `filename` in stack traces
+pub fn eval_script<'js>(ctx: &Ctx<'js>, source: &str, filename: &str) -> QuickJsResult<Value<'js>> {
+    let length = source.len();
+
+    let source = CString::new(source)?;
+    let filename = CString::new(filename)?;
+
+    unsafe {
+        let value = qjs::JS_Eval(
+            ctx.as_raw().as_ptr(),
+            source.as_ptr(),
+            length as _,
+            filename.as_ptr(),
+            qjs::JS_EVAL_TYPE_GLOBAL as c_int,
+        );
+
+        if qjs::JS_IsException(value) {
+            return Err(Error::Exception);
+        }
+
+        Ok(Value::from_raw(ctx.clone(), value))
+    }
+}
+
+/// Throw an error describing `err`, with Node.js style `code` and `errno` properties when known
+pub fn throw_io_error(ctx: &Ctx<'_>, message: &str, err: io::Error) -> rquickjs::Error {
+    let error = Exception::from_message(ctx.clone(), &format!("{}: {}", message, err));
+
+    let error = error.and_then(|error| {
+        if let Some(errno) = err.raw_os_error() {
+            if let Some(code) = crate::os::constants::errno_name(errno) {
+                error.as_object().set("code", code)?;
+            }
+
+            error.as_object().set("errno", -errno)?;
+        }
+
+        Ok(error)
+    });
+
+    match error {
+        Ok(error) => error.throw(),
+
+        Err(err) => err,
+    }
+}
+
+/// Throw an `Error` with a Node.js style `code`
+pub fn throw_error(ctx: &Ctx<'_>, code: &str, message: &str) -> rquickjs::Error {
+    throw_with_code(ctx, "Error", code, message)
+}
+
+/// Throw a `SyntaxError` with a Node.js style `code`
+pub fn throw_syntax_error(ctx: &Ctx<'_>, code: &str, message: &str) -> rquickjs::Error {
+    throw_with_code(ctx, "SyntaxError", code, message)
+}
+
+/// Throw a `TypeError` with a Node.js style `code`
+pub fn throw_type_error(ctx: &Ctx<'_>, code: &str, message: &str) -> rquickjs::Error {
+    throw_with_code(ctx, "TypeError", code, message)
+}
+
+fn throw_with_code(ctx: &Ctx<'_>, name: &str, code: &str, message: &str) -> rquickjs::Error {
+    let constructor: QuickJsResult<Constructor> = ctx.globals().get(name);
+
+    let error = constructor.and_then(|constructor| {
+        let error: Object = constructor.construct((message,))?;
+
+        error.set("code", code)?;
+
+        Ok(error)
+    });
+
+    match error {
+        Ok(error) => ctx.throw(error.into_value()),
+
+        Err(err) => err,
+    }
+}
+
+/// Decode the `%XX` escapes of a URL or a part of it, leaving invalid ones as they are
+pub fn decode_percent(data: &str) -> String {
+    let bytes = data.as_bytes();
+
+    let mut decoded = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let hex = data
+            .get(index + 1..index + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+
+                index += 3;
+            }
+
+            (byte, _) => {
+                decoded.push(byte);
+
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -1,118 +1,969 @@
+use crate::cli::{ColorMode, LogFormat, UnhandledRejections};
+use crate::console::{ConsoleSink, JsonSink, TextSink};
+use crate::error::YasoError;
 use crate::fs::FsModule;
+use crate::inspect::{get_promise_state, PromiseState};
+use crate::module::loader::{CommonJsLoader, DataLoader, EsmLoader};
+use crate::module::ModuleModule;
 use crate::os::OsModule;
 use crate::path::PathModule;
-use crate::process::ProcessModule;
+use crate::process::{ExitState, ProcessModule};
+use crate::resolver::NodeResolver;
+use crate::timers::promises::TimersPromisesModule;
+use crate::timers::TimersModule;
+use crate::util::UtilModule;
+use crate::utils::throw_type_error;
 
-use rquickjs::loader::{BuiltinResolver, FileResolver, ModuleLoader, ScriptLoader};
-use rquickjs::{AsyncContext, AsyncRuntime, CatchResultExt, CaughtError, Ctx, Module, Object};
+use rquickjs::function::{IntoArgs, Rest, This};
+use rquickjs::loader::{BuiltinResolver, ModuleLoader};
+use rquickjs::module::ModuleDef;
+use rquickjs::{
+    qjs, AsyncContext, AsyncRuntime, CatchResultExt, CaughtError, Ctx, FromJs, Function, IntoJs,
+    Module, Object, Persistent, Result as QuickJsResult, Value,
+};
 
-use std::path::Path;
-use std::process::exit;
+use std::ffi::{c_int, c_void, OsString};
+use std::path::{Path, PathBuf};
+use std::ptr::{self, NonNull};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use tokio::time::{sleep_until, Instant};
 
 macro_rules! create_modules {
-    ($($name:expr => $module:expr),*) => {
-        pub fn create_module_instances() -> (BuiltinResolver, ModuleLoader) {
-            let mut builtin_resolver = BuiltinResolver::default();
-            let mut module_loader = ModuleLoader::default();
+    ($($name:literal => $module:expr),*) => {
+        /// The names of the builtin modules
+        pub const BUILTIN_MODULES: &[&str] = &[$($name),*];
+
+        /// Add the builtin module `name` to the resolver and the loader, returning `false` if
+        /// there is no such module
+        fn add_builtin_module(
+            builtin_resolver: &mut BuiltinResolver,
+            module_loader: &mut ModuleLoader,
+            name: &str,
+        ) -> bool {
+            match name {
+                $(
+                    $name => {
+                        builtin_resolver.add_module($name);
+                        module_loader.add_module($name, $module);
+                    }
+                )*
 
-            $(
-                builtin_resolver = builtin_resolver.with_module($name);
-                module_loader = module_loader.with_module($name, $module);
-            )*
+                _ => return false,
+            }
 
-            (builtin_resolver, module_loader)
+            true
         }
     };
 }
 
 create_modules!(
     "fs" => FsModule,
+    "module" => ModuleModule,
     "os" => OsModule,
     "path" => PathModule,
-    "process" => ProcessModule
+    "process" => ProcessModule,
+    "timers" => TimersModule,
+    "timers/promises" => TimersPromisesModule,
+    "util" => UtilModule
 );
 
+unsafe extern "C" fn promise_rejection_tracker(
+    ctx: *mut qjs::JSContext,
+    promise: qjs::JSValue,
+    reason: qjs::JSValue,
+    is_handled: c_int,
+    _opaque: *mut c_void,
+) {
+    let ctx = Ctx::from_raw(NonNull::new_unchecked(ctx));
+
+    let promise = Value::from_raw(ctx.clone(), qjs::JS_DupValue(promise));
+    let reason = Value::from_raw(ctx.clone(), qjs::JS_DupValue(reason));
+
+    let _ = crate::process::track_rejection(&ctx, promise, reason, is_handled != 0);
+}
+
+/// Sets up the globals of a context, run once the builtin globals are defined
+type GlobalsInit = Box<dyn for<'js> FnOnce(&Ctx<'js>, &Object<'js>) -> QuickJsResult<()>>;
+
+/// Configures and creates a `VirtualMachine`, which by default has every builtin module, the
+/// Node.js globals and the environment and working directory of the process
+pub struct VirtualMachineBuilder {
+    builtin_modules: Vec<String>,
+    builtin_resolver: BuiltinResolver,
+    module_loader: ModuleLoader,
+    custom_modules: Vec<String>,
+    globals: Vec<GlobalsInit>,
+    unhandled_rejections: UnhandledRejections,
+    node_globals: bool,
+    color: ColorMode,
+    log_format: LogFormat,
+    source_maps: bool,
+    console_sink: Option<Rc<dyn ConsoleSink>>,
+    argv: Option<Vec<String>>,
+    exec_argv: Vec<String>,
+    env: Option<Vec<(OsString, OsString)>>,
+    cwd: Option<PathBuf>,
+}
+
+impl Default for VirtualMachineBuilder {
+    fn default() -> Self {
+        VirtualMachineBuilder {
+            builtin_modules: BUILTIN_MODULES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            builtin_resolver: BuiltinResolver::default(),
+            module_loader: ModuleLoader::default(),
+            custom_modules: Vec::new(),
+            globals: Vec::new(),
+            unhandled_rejections: UnhandledRejections::Throw,
+            node_globals: true,
+            color: ColorMode::Auto,
+            log_format: LogFormat::Text,
+            source_maps: false,
+            console_sink: None,
+            argv: None,
+            exec_argv: Vec::new(),
+            env: None,
+            cwd: None,
+        }
+    }
+}
+
+impl VirtualMachineBuilder {
+    /// Only provide the builtin modules in `names`, out of `BUILTIN_MODULES`
+    pub fn builtin_modules(mut self, names: &[&str]) -> Self {
+        self.builtin_modules = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Provide `module` to `import` and `require` as `name`, like a builtin module
+    pub fn module<M: ModuleDef>(mut self, name: &str, module: M) -> Self {
+        self.builtin_resolver.add_module(name);
+        self.module_loader.add_module(name, module);
+        self.custom_modules.push(name.to_string());
+        self
+    }
+
+    /// Define the global `name` as `value`
+    pub fn global<V>(self, name: &str, value: V) -> Self
+    where
+        V: for<'js> IntoJs<'js> + 'static,
+    {
+        let name = name.to_string();
+
+        self.globals(move |_ctx, globals| globals.set(name, value))
+    }
+
+    /// Define globals with `init`, called with the global object once the builtin ones exist
+    pub fn globals<F>(mut self, init: F) -> Self
+    where
+        F: for<'js> FnOnce(&Ctx<'js>, &Object<'js>) -> QuickJsResult<()> + 'static,
+    {
+        self.globals.push(Box::new(init));
+        self
+    }
+
+    pub fn unhandled_rejections(mut self, unhandled_rejections: UnhandledRejections) -> Self {
+        self.unhandled_rejections = unhandled_rejections;
+        self
+    }
+
+    pub fn node_globals(mut self, node_globals: bool) -> Self {
+        self.node_globals = node_globals;
+        self
+    }
+
+    /// Set when console output is colored
+    pub fn color(mut self, color: ColorMode) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set how console output is written, unless a sink was given with `console_sink`
+    pub fn log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
+    /// Set whether stack traces are mapped to the original sources of files with a source map
+    pub fn source_maps(mut self, source_maps: bool) -> Self {
+        self.source_maps = source_maps;
+        self
+    }
+
+    /// Hand everything the global console writes to `sink` instead of stdout and stderr
+    pub fn console_sink(mut self, sink: impl ConsoleSink + 'static) -> Self {
+        self.console_sink = Some(Rc::new(sink));
+        self
+    }
+
+    /// Set `process.argv`, which defaults to only holding the path of the executable
+    pub fn argv(mut self, argv: Vec<String>) -> Self {
+        self.argv = Some(argv);
+        self
+    }
+
+    /// Set `process.execArgv`, the runtime flags given before the script path
+    pub fn exec_argv(mut self, exec_argv: Vec<String>) -> Self {
+        self.exec_argv = exec_argv;
+        self
+    }
+
+    /// Give the virtual machine an environment holding only `vars`, instead of a view of the
+    /// environment of the process
+    pub fn env<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        self.env = Some(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Give the virtual machine its own working directory, which `process.cwd()` returns and
+    /// relative module paths are resolved against, without changing the one of the process
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub async fn build(mut self) -> Result<VirtualMachine, YasoError> {
+        let mut builtin_resolver = std::mem::take(&mut self.builtin_resolver);
+        let mut module_loader = std::mem::take(&mut self.module_loader);
+
+        for name in &self.builtin_modules {
+            if !add_builtin_module(&mut builtin_resolver, &mut module_loader, name) {
+                return Err(YasoError::InvalidOption(format!(
+                    "There is no builtin module '{}'",
+                    name
+                )));
+            }
+        }
+
+        if let Some(cwd) = &self.cwd {
+            let directory = std::fs::canonicalize(cwd)
+                .ok()
+                .filter(|directory| directory.is_dir());
+
+            self.cwd = Some(directory.ok_or_else(|| {
+                YasoError::InvalidOption(format!(
+                    "The working directory {} is not a directory",
+                    cwd.display()
+                ))
+            })?);
+        }
+
+        let resolver = (builtin_resolver, NodeResolver);
+
+        let loader = (module_loader, DataLoader, CommonJsLoader, EsmLoader);
+
+        let runtime = AsyncRuntime::new()?;
+
+        runtime.set_loader(resolver, loader).await;
+
+        let exit_state = Arc::new(ExitState::default());
+
+        runtime
+            .set_interrupt_handler(Some(Box::new({
+                let exit_state = exit_state.clone();
+
+                move || exit_state.is_unwinding()
+            })))
+            .await;
+
+        let context = AsyncContext::full(&runtime).await?;
+
+        let unhandled_rejections = self.unhandled_rejections;
+
+        context
+            .with(|ctx| unsafe {
+                qjs::JS_SetHostPromiseRejectionTracker(
+                    qjs::JS_GetRuntime(ctx.as_raw().as_ptr()),
+                    Some(promise_rejection_tracker),
+                    ptr::null_mut(),
+                );
+            })
+            .await;
+
+        context
+            .with(|ctx| {
+                self.init(&ctx, exit_state.clone())
+                    .catch(&ctx)
+                    .map_err(|err| YasoError::from_caught(&ctx, err))
+            })
+            .await?;
+
+        Ok(VirtualMachine {
+            context,
+            runtime,
+            unhandled_rejections,
+            exit_state,
+        })
+    }
+
+    fn create_console_sink(&self) -> Rc<dyn ConsoleSink> {
+        if let Some(sink) = &self.console_sink {
+            return sink.clone();
+        }
+
+        match self.log_format {
+            LogFormat::Text => Rc::new(TextSink::new(self.color)),
+
+            LogFormat::Json => Rc::new(JsonSink),
+        }
+    }
+
+    fn init(self, ctx: &Ctx<'_>, exit_state: Arc<ExitState>) -> QuickJsResult<()> {
+        crate::process::set_exit_state(ctx, exit_state)?;
+
+        crate::console::init(ctx, self.create_console_sink())?;
+
+        self.init_process(ctx)?;
+
+        crate::globals::init(ctx, self.node_globals)?;
+
+        crate::source_map::init(ctx, self.source_maps)?;
+
+        let mut module_names = self.builtin_modules;
+
+        for name in self.custom_modules {
+            if !module_names.contains(&name) {
+                module_names.push(name);
+            }
+        }
+
+        crate::module::set_builtin_modules(ctx, module_names)?;
+
+        for init in self.globals {
+            init(ctx, &ctx.globals())?;
+        }
+
+        Ok(())
+    }
+
+    fn init_process(&self, ctx: &Ctx<'_>) -> QuickJsResult<()> {
+        let process = crate::process::get_process_object(ctx)?;
+
+        if let Some(argv) = &self.argv {
+            process.set("argv", argv.clone())?;
+        }
+
+        process.set("execArgv", self.exec_argv.clone())?;
+
+        if let Some(env) = &self.env {
+            crate::process::set_environment(ctx, env.clone())?;
+        }
+
+        if let Some(cwd) = &self.cwd {
+            crate::process::set_working_dir(ctx, cwd.clone())?;
+        }
+
+        Ok(())
+    }
+}
+
 pub struct VirtualMachine {
     context: AsyncContext,
     runtime: AsyncRuntime,
+    unhandled_rejections: UnhandledRejections,
+    exit_state: Arc<ExitState>,
 }
 
 impl VirtualMachine {
-    pub async fn new() -> VirtualMachine {
-        let (builtin_resolver, module_loader) = create_module_instances();
-
-        let resolver = (
-            builtin_resolver,
-            FileResolver::default()
-                .with_path(".")
-                .with_pattern("{}.cjs")
-                .with_pattern("{}.mjs"),
-        );
+    pub fn builder() -> VirtualMachineBuilder {
+        VirtualMachineBuilder::default()
+    }
 
-        let loader = (
-            module_loader,
-            ScriptLoader::default()
-                .with_extension("mjs")
-                .with_extension("cjs"),
-        );
+    /// Run pending jobs and futures until there is nothing left to do or the process is exiting,
+    /// emitting `beforeExit` whenever the event loop becomes empty. Errors nothing caught are
+    /// reported through the console as they happen and make the process exit, the first of them
+    /// is returned once the loop stopped
+    pub async fn idle(&self) -> Result<(), YasoError> {
+        loop {
+            self.run_event_loop().await;
 
-        let runtime = AsyncRuntime::new().expect("failed to create an AsyncRuntime");
+            if self.exit_state.is_exiting() {
+                break;
+            }
 
-        runtime.set_loader(resolver, loader).await;
+            self.context
+                .with(|ctx| {
+                    let code = crate::process::exit_code(&ctx).unwrap_or(0);
+
+                    crate::process::emit(
+                        ctx.clone(),
+                        String::from("beforeExit"),
+                        Rest(vec![Value::new_int(ctx.clone(), code)]),
+                    )
+                    .catch(&ctx)
+                    .unwrap_or_else(|err| {
+                        VirtualMachine::report_error(&ctx, err);
+
+                        false
+                    });
+                })
+                .await;
+
+            if !self.runtime.is_job_pending().await && !self.get_next_timer().await.1 {
+                break;
+            }
+        }
+
+        if let Some(error) = self.exit_state.take_uncaught_error() {
+            return Err(error);
+        }
+
+        match self.exit_state.is_exiting() {
+            true => {
+                self.context
+                    .with(|ctx| {
+                        Err(YasoError::Exit(
+                            crate::process::exit_code(&ctx).unwrap_or(1),
+                        ))
+                    })
+                    .await
+            }
+
+            false => Ok(()),
+        }
+    }
+
+    /// Run the jobs, futures, timers and immediates until none are left, timers that were
+    /// `unref()`ed only run while something else keeps the loop alive
+    async fn run_event_loop(&self) {
+        while !self.exit_state.is_exiting() && self.run_event_loop_once().await {}
+    }
+
+    /// Run the next due timer and the next job, or wait for the next timer, returning `false`
+    /// once nothing is left to run
+    async fn run_event_loop_once(&self) -> bool {
+        self.run_next_timer().await;
+
+        let (deadline, _) = self.get_next_timer().await;
+
+        let job = self.runtime.execute_pending_job();
+
+        let result = match deadline {
+            Some(deadline) => tokio::select! {
+                biased;
+
+                result = job => Some(result),
+
+                _ = sleep_until(Instant::from_std(deadline)) => None,
+            },
+
+            None => Some(job.await),
+        };
+
+        match result {
+            // A timer is due
+            None => true,
+
+            Some(Ok(true)) => {
+                self.process_unhandled_rejections().await;
+
+                true
+            }
+
+            Some(Ok(false)) => {
+                self.process_unhandled_rejections().await;
+
+                if self.runtime.is_job_pending().await {
+                    return true;
+                }
+
+                match self.get_next_timer().await {
+                    (Some(deadline), true) => {
+                        sleep_until(Instant::from_std(deadline)).await;
+
+                        true
+                    }
+
+                    _ => false,
+                }
+            }
+
+            Some(Err(exception)) => {
+                exception
+                    .0
+                    .with(|ctx| {
+                        // The failed job hands out its context without taking a reference,
+                        // take the one this `AsyncContext` releases when it is dropped
+                        unsafe { qjs::JS_DupContext(ctx.as_raw().as_ptr()) };
+
+                        let err = CaughtError::from_error(&ctx, rquickjs::Error::Exception);
+
+                        VirtualMachine::report_error(&ctx, err);
+                    })
+                    .await;
+
+                true
+            }
+        }
+    }
 
-        let context = AsyncContext::full(&runtime)
+    /// Run the next due timer or immediate, once the microtasks queued by the previous one ran
+    async fn run_next_timer(&self) {
+        self.context
+            .with(|ctx| {
+                let job_pending =
+                    unsafe { qjs::JS_IsJobPending(qjs::JS_GetRuntime(ctx.as_raw().as_ptr())) };
+
+                if job_pending != 0 {
+                    return;
+                }
+
+                if let Err(err) = crate::timers::run_next(&ctx).catch(&ctx) {
+                    VirtualMachine::report_error(&ctx, err);
+                }
+            })
             .await
-            .expect("failed to create an AsyncContext");
+    }
+
+    /// When the next timer is due, and whether any timer keeps the event loop alive
+    async fn get_next_timer(&self) -> (Option<std::time::Instant>, bool) {
+        self.context
+            .with(|ctx| {
+                let deadline = crate::timers::next_deadline(&ctx).unwrap_or(None);
 
-        VirtualMachine { context, runtime }
+                let has_refs = crate::timers::has_refs(&ctx).unwrap_or(false);
+
+                (deadline, has_refs)
+            })
+            .await
     }
 
-    pub async fn init(&self) {
+    /// Report the rejections that are still unhandled once the microtask queue is drained
+    async fn process_unhandled_rejections(&self) {
+        let unhandled_rejections = self.unhandled_rejections;
+
         self.context
             .with(|ctx| {
-                crate::console::init(&ctx)
+                let job_pending =
+                    unsafe { qjs::JS_IsJobPending(qjs::JS_GetRuntime(ctx.as_raw().as_ptr())) };
+
+                if job_pending != 0 || crate::process::is_exiting(&ctx) {
+                    return;
+                }
+
+                let rejections = match crate::process::take_unhandled_rejections(&ctx) {
+                    Ok(rejections) => rejections,
+
+                    Err(err) => return VirtualMachine::report_error(&ctx, CaughtError::Error(err)),
+                };
+
+                for (promise, reason) in rejections {
+                    if crate::process::is_exiting(&ctx) {
+                        break;
+                    }
+
+                    if unhandled_rejections == UnhandledRejections::Strict {
+                        VirtualMachine::report_uncaught(
+                            &ctx,
+                            CaughtError::Value(reason),
+                            "unhandledRejection",
+                        );
+
+                        continue;
+                    }
+
+                    let emitted = match crate::process::emit(
+                        ctx.clone(),
+                        String::from("unhandledRejection"),
+                        Rest(vec![reason.clone(), promise]),
+                    )
                     .catch(&ctx)
-                    .unwrap_or_else(|err| VirtualMachine::print_error_and_exit(ctx, err));
+                    {
+                        Ok(emitted) => emitted,
+
+                        Err(err) => {
+                            VirtualMachine::report_error(&ctx, err);
+
+                            continue;
+                        }
+                    };
+
+                    match unhandled_rejections {
+                        UnhandledRejections::Throw if !emitted => VirtualMachine::report_uncaught(
+                            &ctx,
+                            CaughtError::Value(reason),
+                            "unhandledRejection",
+                        ),
+
+                        UnhandledRejections::Warn => {
+                            VirtualMachine::print_error(
+                                &ctx,
+                                CaughtError::Value(reason),
+                                "Warning: Unhandled promise rejection: ",
+                            );
+                        }
+
+                        _ => (),
+                    }
+                }
             })
             .await
     }
 
-    pub async fn idle(self) {
-        self.runtime.idle().await;
+    /// Run the `exit` listeners if they did not run yet, flush the output and shut down,
+    /// returning the code the process should exit with
+    pub async fn exit(self) -> i32 {
+        let code = self
+            .context
+            .with(|ctx| {
+                if let Err(err) = crate::process::begin_exit(&ctx, None).catch(&ctx) {
+                    VirtualMachine::print_error(&ctx, err, "");
+                }
+
+                crate::process::exit_code(&ctx).unwrap_or(1)
+            })
+            .await;
+
+        let _ = crate::stdio::flush_stdout();
 
         drop(self.context);
         drop(self.runtime);
+
+        code
     }
 
     fn load_module<'js>(ctx: &Ctx<'js>, file_path: &Path) -> Result<Object<'js>, rquickjs::Error> {
+        // Make the entry point an absolute path so it isn't looked up in `node_modules`
+        let file_path = crate::process::absolute_path(ctx, file_path);
+
+        crate::module::set_main(ctx, &file_path)?;
+
         Module::import(ctx, file_path.to_string_lossy().to_string())
     }
 
-    pub async fn run_module(&self, file_path: &Path) {
+    /// Run the module at `file_path`, returning what it threw when no `uncaughtException`
+    /// listener handled it, or the exit code if it called `process.exit()`
+    pub async fn run_module(&self, file_path: &Path) -> Result<(), YasoError> {
+        self.context
+            .with(|ctx| {
+                let result = VirtualMachine::load_module(&ctx, file_path)
+                    .map(|_| ())
+                    .catch(&ctx);
+
+                VirtualMachine::handle_result(&ctx, result)
+            })
+            .await
+    }
+
+    /// Evaluate `source` as a global script, with the same outcome as `run_module`
+    pub async fn eval(&self, source: &str) -> Result<(), YasoError> {
+        self.context
+            .with(|ctx| {
+                let result = ctx.eval::<(), _>(source).catch(&ctx);
+
+                VirtualMachine::handle_result(&ctx, result)
+            })
+            .await
+    }
+
+    /// Evaluate `source` as a global script named `name`, returning the value of its last
+    /// statement, once it settled if it is a promise, errors are returned rather than handed to
+    /// the `uncaughtException` listeners
+    pub async fn eval_script<R>(&self, source: &str, name: &str) -> Result<R, YasoError>
+    where
+        R: for<'js> FromJs<'js>,
+    {
+        let value = self
+            .context
+            .with(|ctx| {
+                crate::utils::eval_script(&ctx, source, name)
+                    .and_then(|value| VirtualMachine::save_result(&ctx, value))
+                    .catch(&ctx)
+                    .map_err(|err| YasoError::from_caught(&ctx, err))
+            })
+            .await?;
+
+        self.settle(value).await
+    }
+
+    /// Evaluate `source` as the ES module `specifier`, which other modules then import and `call`
+    /// finds by that name, relative imports resolving from the directory of `specifier`
+    pub async fn eval_module(&self, source: &str, specifier: &str) -> Result<(), YasoError> {
+        self.context
+            .with(|ctx| {
+                VirtualMachine::evaluate_module(&ctx, source, specifier)
+                    .catch(&ctx)
+                    .map_err(|err| YasoError::from_caught(&ctx, err))
+            })
+            .await
+    }
+
+    fn evaluate_module(ctx: &Ctx<'_>, source: &str, specifier: &str) -> QuickJsResult<()> {
+        let source = crate::module::loader::prepare_source(ctx, specifier, source.to_string())?;
+
+        Module::declare(ctx.clone(), specifier, source)?;
+
+        crate::module::add_source_module(ctx, specifier)?;
+
+        Module::import::<Value, _>(ctx, specifier).map(|_| ())
+    }
+
+    /// Call the function `name` exported by `specifier`, a module given to `eval_module`, a
+    /// builtin module or a file, returning its result once it settled if it is a promise
+    pub async fn call<A, R>(&self, specifier: &str, name: &str, args: A) -> Result<R, YasoError>
+    where
+        A: for<'js> IntoArgs<'js>,
+        R: for<'js> FromJs<'js>,
+    {
+        let value = self
+            .context
+            .with(|ctx| {
+                VirtualMachine::call_export(&ctx, specifier, name, args)
+                    .and_then(|value| VirtualMachine::save_result(&ctx, value))
+                    .catch(&ctx)
+                    .map_err(|err| YasoError::from_caught(&ctx, err))
+            })
+            .await?;
+
+        self.settle(value).await
+    }
+
+    fn call_export<'js, A>(
+        ctx: &Ctx<'js>,
+        specifier: &str,
+        name: &str,
+        args: A,
+    ) -> QuickJsResult<Value<'js>>
+    where
+        A: IntoArgs<'js>,
+    {
+        let namespace: Object = Module::import(ctx, specifier)?;
+
+        let Some(function) = namespace.get::<_, Value>(name)?.into_function() else {
+            return Err(throw_type_error(
+                ctx,
+                "ERR_INVALID_ARG_TYPE",
+                &format!("'{}' exported by '{}' is not a function", name, specifier),
+            ));
+        };
+
+        function.call(args)
+    }
+
+    /// Keep what some code returned until it is converted, catching it first if it is a promise
+    /// since what it rejects with is returned to the host
+    fn save_result<'js>(
+        ctx: &Ctx<'js>,
+        value: Value<'js>,
+    ) -> QuickJsResult<Persistent<Value<'static>>> {
+        if let Some(promise) = value.as_object() {
+            if get_promise_state(ctx, promise).is_some() {
+                let catch: Function = promise.get("catch")?;
+
+                catch.call::<_, Value>((
+                    This(promise.clone()),
+                    Function::new(ctx.clone(), || {})?,
+                ))?;
+            }
+        }
+
+        Ok(Persistent::save(ctx, value))
+    }
+
+    /// Run the event loop until `value` settled if it is a promise, then convert what it settled
+    /// with to `R`
+    async fn settle<R>(&self, value: Persistent<Value<'static>>) -> Result<R, YasoError>
+    where
+        R: for<'js> FromJs<'js>,
+    {
+        loop {
+            let result = self
+                .context
+                .with(|ctx| VirtualMachine::get_settled(&ctx, value.clone()))
+                .await?;
+
+            if let Some(result) = result {
+                return Ok(result);
+            }
+
+            if !self.run_event_loop_once().await || self.exit_state.is_exiting() {
+                break;
+            }
+        }
+
+        self.context
+            .with(|ctx| match crate::process::is_exiting(&ctx) {
+                true => Err(YasoError::Exit(
+                    crate::process::exit_code(&ctx).unwrap_or(1),
+                )),
+
+                false => Err(YasoError::Unsettled),
+            })
+            .await
+    }
+
+    /// What `value` settled with converted to `R`, or `None` while it is a pending promise
+    fn get_settled<'js, R>(
+        ctx: &Ctx<'js>,
+        value: Persistent<Value<'static>>,
+    ) -> Result<Option<R>, YasoError>
+    where
+        R: FromJs<'js>,
+    {
+        let value = value.restore(ctx)?;
+
+        let state = value
+            .as_object()
+            .and_then(|object| get_promise_state(ctx, object));
+
+        let result = match state {
+            Some(PromiseState::Pending) => return Ok(None),
+
+            Some(PromiseState::Fulfilled(result)) => R::from_js(ctx, result).catch(ctx),
+
+            Some(PromiseState::Rejected(reason)) => Err(CaughtError::Value(reason)),
+
+            None => R::from_js(ctx, value).catch(ctx),
+        };
+
+        result
+            .map(Some)
+            .map_err(|err| YasoError::from_caught(ctx, err))
+    }
+
+    /// The environment variables as the scripts changed them through `process.env`, for processes
+    /// spawned on their behalf to get with `Command::env_clear` and `Command::envs`
+    pub async fn effective_env(&self) -> Result<Vec<(OsString, OsString)>, YasoError> {
+        self.context
+            .with(|ctx| {
+                crate::process::effective_env(&ctx)
+                    .catch(&ctx)
+                    .map_err(|err| YasoError::from_caught(&ctx, err))
+            })
+            .await
+    }
+
+    /// Set `process.exitCode`, the code `exit` returns unless the scripts change it
+    pub async fn set_exit_code(&self, code: i32) {
         self.context
             .with(|ctx| {
-                VirtualMachine::load_module(&ctx, file_path)
+                if let Err(err) = crate::process::get_process_object(&ctx)
+                    .and_then(|process| process.set("exitCode", code))
                     .catch(&ctx)
-                    .unwrap_or_else(|err| VirtualMachine::print_error_and_exit(ctx, err));
+                {
+                    VirtualMachine::print_error(&ctx, err, "");
+                }
             })
             .await
     }
 
-    fn print_error_and_exit<'js>(ctx: Ctx<'js>, err: CaughtError<'js>) -> ! {
-        let error_message = match err {
-            CaughtError::Error(err) => err.to_string(),
+    /// Hand what code run from the host threw to the `uncaughtException` listeners, or return it
+    /// when there are none or a listener threw
+    fn handle_result<'js>(
+        ctx: &Ctx<'js>,
+        result: Result<(), CaughtError<'js>>,
+    ) -> Result<(), YasoError> {
+        let Err(err) = result else {
+            return Ok(());
+        };
+
+        if !crate::process::is_exiting(ctx)
+            && crate::process::has_listeners(ctx, "uncaughtException").unwrap_or(false)
+        {
+            return VirtualMachine::emit_uncaught(ctx, err, "uncaughtException")
+                .map_err(|err| YasoError::from_caught(ctx, err));
+        }
+
+        Err(YasoError::from_caught(ctx, err))
+    }
+
+    fn report_error<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) {
+        VirtualMachine::report_uncaught(ctx, err, "uncaughtException")
+    }
+
+    /// Hand an uncaught error to the `uncaughtException` listeners, or print it and start exiting
+    /// with code 1 if there are none, errors raised while the process is already exiting only come
+    /// from unwinding `process.exit` and are ignored
+    fn report_uncaught<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>, origin: &str) {
+        if crate::process::is_exiting(ctx) {
+            return;
+        }
+
+        if crate::process::has_listeners(ctx, "uncaughtException").unwrap_or(false) {
+            // A throwing handler is fatal, report what it threw instead
+            if let Err(handler_err) = VirtualMachine::emit_uncaught(ctx, err, origin) {
+                VirtualMachine::exit_with_error(ctx, handler_err);
+            }
+
+            return;
+        }
+
+        VirtualMachine::exit_with_error(ctx, err);
+    }
+
+    /// Emit `uncaughtException` with an uncaught error, returning what a listener threw
+    fn emit_uncaught<'js>(
+        ctx: &Ctx<'js>,
+        err: CaughtError<'js>,
+        origin: &str,
+    ) -> Result<(), CaughtError<'js>> {
+        let error = match err {
+            CaughtError::Error(ref err) => {
+                rquickjs::String::from_str(ctx.clone(), &err.to_string()).map(|s| s.into_value())
+            }
+
+            CaughtError::Exception(ref exception) => {
+                crate::source_map::map_error_stack(ctx, exception.as_object())
+                    .map(|_| exception.clone().into_value())
+            }
+
+            CaughtError::Value(ref value) => Ok(value.clone()),
+        };
+
+        let origin = rquickjs::String::from_str(ctx.clone(), origin).map(|s| s.into_value());
+
+        error
+            .and_then(|error| {
+                crate::process::emit(
+                    ctx.clone(),
+                    String::from("uncaughtException"),
+                    Rest(vec![error, origin?]),
+                )
+            })
+            .map(|_| ())
+            .catch(ctx)
+    }
+
+    fn exit_with_error<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) {
+        if crate::process::is_exiting(ctx) {
+            return;
+        }
+
+        let error = VirtualMachine::print_error(ctx, err, "");
+
+        let _ = crate::process::set_uncaught_error(ctx, error);
+
+        if let Err(err) = crate::process::begin_exit(ctx, Some(1)).catch(ctx) {
+            VirtualMachine::print_error(ctx, err, "");
+        }
+    }
+
+    /// Report an error through the sink of the console, colored like the output of
+    /// `console.error`, values that aren't errors are inspected, returning the error reported
+    fn print_error<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>, prefix: &str) -> YasoError {
+        let error = YasoError::from_uncaught(ctx, err);
 
-            CaughtError::Exception(exception) => crate::console::js_stringify(exception.as_value())
-                .catch(&ctx)
-                .unwrap_or_else(|err| VirtualMachine::print_error_and_exit(ctx, err)),
+        let report = match &error {
+            YasoError::Exception(exception) => exception.report().trim_end().to_string(),
 
-            CaughtError::Value(value) => crate::console::js_stringify(&value)
-                .catch(&ctx)
-                .unwrap_or_else(|err| VirtualMachine::print_error_and_exit(ctx, err)),
+            error => error.to_string(),
         };
 
-        eprintln!("{}", error_message);
+        // A failing sink leaves nowhere to report the error to
+        let _ = crate::console::write_error(ctx, &format!("{}{}", prefix, report)).catch(ctx);
 
-        exit(1);
+        error
     }
 }